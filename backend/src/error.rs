@@ -27,8 +27,6 @@ pub enum AppError {
     PathRejection(#[from] rejection::PathRejection),
     #[error("Authentication error: {0}")]
     Auth(#[from] AuthError),
-    #[error("Not implemented")]
-    NotImplemented,
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -57,7 +55,6 @@ impl IntoResponse for AppError {
                 (StatusCode::BAD_REQUEST, path_error.to_string())
             }
             AppError::Auth(auth_error) => (auth_error.status_code(), auth_error.to_string()),
-            AppError::NotImplemented => (StatusCode::NOT_IMPLEMENTED, self.to_string()),
             AppError::Internal(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error: Something went wrong".to_owned(),
@@ -67,7 +64,13 @@ impl IntoResponse for AppError {
     }
 }
 
-impl_internal_from!(mongodb::error::Error, jsonwebtoken::errors::Error);
+impl_internal_from!(
+    mongodb::error::Error,
+    mongodb::bson::ser::Error,
+    jsonwebtoken::errors::Error,
+    zip::result::ZipError,
+    std::io::Error,
+);
 
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
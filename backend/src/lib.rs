@@ -2,6 +2,7 @@ use std::{net::SocketAddr, sync::Arc};
 
 use mongodb::{bson::doc, Client};
 use routes::create_routes;
+use services::scramble_services::ScrambleTables;
 use tokio::signal;
 
 mod error;
@@ -49,6 +50,7 @@ impl Config {
 pub struct AppState {
     client: Client,
     env: Config,
+    scramble_tables: ScrambleTables,
 }
 
 pub async fn run(config: Config) -> anyhow::Result<()> {
@@ -63,6 +65,7 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
     let state = Arc::new(AppState {
         client,
         env: config,
+        scramble_tables: ScrambleTables::default(),
     });
 
     match services::auth_services::register(
@@ -92,6 +95,11 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
         ),
     }
 
+    let migrated = services::session_services::assign_time_ids(&state).await?;
+    if migrated > 0 {
+        tracing::info!("Assigned ids to the times of {} sessions", migrated);
+    }
+
     let addr: SocketAddr = format!(
         "127.0.0.1:{}",
         state
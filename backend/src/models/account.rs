@@ -1,11 +1,11 @@
-use crate::models::event::Event;
 use mongodb::bson::Uuid;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub enum Role {
     User,
-    EventModerator(Event),
+    /// Moderator of the event with this id.
+    EventModerator(Uuid),
     Admin,
 }
 
@@ -33,17 +33,8 @@ impl Account {
             .contains(&role)
     }
 
-    #[allow(unused)]
     pub fn is_event_moderator(&self, event_id: Uuid) -> bool {
-        self.roles
-            .iter()
-            .any(|role| {
-                if let Role::EventModerator(event) = role {
-                    event.id == event_id
-                } else {
-                    false
-                }
-            })
+        self.has_role(Role::EventModerator(event_id))
     }
 }
 
@@ -1,11 +1,19 @@
 use mongodb::bson::Uuid;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    models::session::{Penalty, MAX_MILLIS},
+    routes::scrambles::ScrambleKind,
+    services::validation_services,
+};
 
 // NOTE: models should be refactored into domain models, DB entities and endpoint DTOs
 // (or at least just add the separate DTOs for now)
 
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 pub struct Event {
+    #[serde(rename = "_id")]
     pub id: Uuid,
     pub is_private: bool,
     pub creator_id: Uuid,
@@ -14,9 +22,44 @@ pub struct Event {
     pub date_timestamp: i64,
     pub moderators: Vec<Uuid>,
     pub participants: Vec<Uuid>,
+    /// Most participants the event takes, unlimited when `None`.
+    #[serde(default)]
+    pub capacity: Option<u32>,
+    #[serde(flatten)]
+    pub registration: RegistrationWindow,
+    /// Accounts waiting for a place, first come first served.
+    #[serde(default)]
+    pub waitlist: Vec<Uuid>,
+    /// Registrations to a private event awaiting the approval of a moderator.
+    #[serde(default)]
+    pub pending: Vec<Uuid>,
+    /// Rounds of every puzzle of the event, each puzzle's in the order they take place.
+    #[serde(default)]
+    pub rounds: Vec<Round>,
+}
+
+/// When registration to an event is open, in milliseconds before its date.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Validate, PartialEq)]
+#[validate(schema(function = "validation_services::valid_registration_window"))]
+pub struct RegistrationWindow {
+    /// Open from the creation of the event when `None`.
+    #[serde(default)]
+    #[validate(range(min = 0, message = "must not be negative"))]
+    pub registration_opens: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "must not be negative"))]
+    pub registration_closes: i64,
+}
+
+/// Where a registration to an event ended up.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Registration {
+    Participant,
+    Waitlisted,
+    Pending,
 }
 
-#[allow(unused)]
 impl Event {
     pub fn new(
         title: &str,
@@ -34,16 +77,289 @@ impl Event {
             date_timestamp,
             moderators: vec![creator_id],
             participants: vec![],
+            capacity: None,
+            registration: RegistrationWindow::default(),
+            waitlist: vec![],
+            pending: vec![],
+            rounds: vec![],
+        }
+    }
+
+    pub fn has_room(&self) -> bool {
+        self.capacity
+            .is_none_or(|capacity| {
+                self.participants
+                    .len()
+                    < capacity as usize
+            })
+    }
+
+    pub fn round(&self, round_id: Uuid) -> Option<&Round> {
+        self.rounds
+            .iter()
+            .find(|round| round.id == round_id)
+    }
+
+    /// Number of the round among the rounds of its puzzle, from 1.
+    pub fn round_number(&self, round: &Round) -> usize {
+        self.rounds
+            .iter()
+            .take_while(|other| other.id != round.id)
+            .filter(|other| other.kind == round.kind)
+            .count()
+            + 1
+    }
+}
+
+/// How many attempts a round has and how they are ranked.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundFormat {
+    /// Best of 1.
+    Bo1,
+    /// Best of 3.
+    Bo3,
+    /// Mean of 3.
+    Mo3,
+    /// Average of 5, the best and worst attempts left out.
+    Ao5,
+}
+
+impl RoundFormat {
+    pub fn attempt_count(&self) -> usize {
+        match self {
+            RoundFormat::Bo1 => 1,
+            RoundFormat::Bo3 | RoundFormat::Mo3 => 3,
+            RoundFormat::Ao5 => 5,
+        }
+    }
+
+    /// Whether competitors are ranked by their average rather than their best attempt.
+    pub fn is_average(&self) -> bool {
+        matches!(self, RoundFormat::Mo3 | RoundFormat::Ao5)
+    }
+}
+
+/// Competitors get the rest of their attempts only if one of the first ones is under `millis`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Validate)]
+pub struct Cutoff {
+    #[validate(range(min = 1, max = 2, message = "must be in range (1..=2)"))]
+    pub attempts: usize,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub millis: u64,
+}
+
+/// Attempts which reach the time limit are DNFs. A cumulative limit is on the total time of the
+/// attempts of a competitor, DNFs included.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Validate)]
+pub struct TimeLimit {
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub millis: u64,
+    #[serde(default)]
+    pub cumulative: bool,
+}
+
+/// Who goes on to the next round of the puzzle.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Advancement {
+    /// The best ranked competitors.
+    Top(usize),
+    /// The best ranked competitors, as a percentage of the competitors of the round.
+    Percent(usize),
+}
+
+/// An attempt as entered, its penalty applied when the round is ranked.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Validate)]
+pub struct Attempt {
+    #[validate(range(max = MAX_MILLIS, message = "must be at most 24 hours"))]
+    pub millis: u64,
+    #[serde(default)]
+    pub penalty: Penalty,
+}
+
+impl Attempt {
+    /// Milliseconds the attempt counts for, `None` when it is a DNF.
+    pub fn result(&self) -> Option<u64> {
+        match self.penalty {
+            Penalty::None => Some(self.millis),
+            Penalty::PlusTwo => Some(
+                self.millis
+                    .saturating_add(2000),
+            ),
+            Penalty::Dnf => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RoundResult {
+    pub account_id: Uuid,
+    pub attempts: Vec<Attempt>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Round {
+    pub id: Uuid,
+    pub kind: ScrambleKind,
+    pub format: RoundFormat,
+    pub cutoff: Option<Cutoff>,
+    pub time_limit: Option<TimeLimit>,
+    /// `None` for the final round of the puzzle.
+    pub advancement: Option<Advancement>,
+    #[serde(default)]
+    pub results: Vec<RoundResult>,
+    /// Every change made to the results, oldest first.
+    #[serde(default)]
+    pub audit: Vec<ResultChange>,
+}
+
+/// A change to the result of a competitor, by a moderator.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ResultChange {
+    pub account_id: Uuid,
+    pub moderator_id: Uuid,
+    /// UNIX timestamp in milliseconds.
+    pub changed_at: i64,
+    /// The attempts entered, `None` when the result was deleted.
+    pub attempts: Option<Vec<Attempt>>,
+}
+
+/// The attempts of a competitor in a round, replacing the ones entered before only when
+/// `confirm` is set.
+#[derive(Deserialize, Validate)]
+pub struct ResultEntryDto {
+    #[validate(
+        length(min = 1, max = 5, message = "length must be in range (1..=5)"),
+        nested
+    )]
+    pub attempts: Vec<Attempt>,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+impl Round {
+    pub fn new(dto: CreateRoundDto) -> Round {
+        Round {
+            id: Uuid::new(),
+            kind: dto.kind,
+            format: dto.format,
+            cutoff: dto.cutoff,
+            time_limit: dto.time_limit,
+            advancement: dto.advancement,
+            results: vec![],
+            audit: vec![],
         }
     }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateEventDto {
+    #[validate(length(min = 1, max = 64, message = "length must be in range (1..=64)"))]
+    pub title: String,
+    #[serde(default)]
+    #[validate(length(max = 4096, message = "length must be at most 4096"))]
+    pub description: String,
+    pub date_timestamp: i64,
+    #[serde(default)]
+    pub is_private: bool,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub capacity: Option<u32>,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub registration: RegistrationWindow,
+}
+
+/// Replaces every editable field of an event, moderators and participants are kept.
+#[derive(Debug, Deserialize, Validate, PartialEq)]
+pub struct UpdateEventDto {
+    #[validate(length(min = 1, max = 64, message = "length must be in range (1..=64)"))]
+    pub title: String,
+    #[serde(default)]
+    #[validate(length(max = 4096, message = "length must be at most 4096"))]
+    pub description: String,
+    pub date_timestamp: i64,
+    #[serde(default)]
+    pub is_private: bool,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub capacity: Option<u32>,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub registration: RegistrationWindow,
+}
 
-    pub fn add_moderator(&mut self, user_id: Uuid) {
-        self.moderators
-            .push(user_id);
+#[derive(Deserialize, Serialize)]
+pub struct EventDto {
+    pub id: Uuid,
+    pub is_private: bool,
+    pub creator_id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub date_timestamp: i64,
+    pub moderators: Vec<Uuid>,
+    pub participants: Vec<Uuid>,
+    pub capacity: Option<u32>,
+    #[serde(flatten)]
+    pub registration: RegistrationWindow,
+    pub waitlist: Vec<Uuid>,
+}
+
+impl EventDto {
+    pub fn from(event: Event) -> EventDto {
+        EventDto {
+            id: event.id,
+            is_private: event.is_private,
+            creator_id: event.creator_id,
+            title: event.title,
+            description: event.description,
+            date_timestamp: event.date_timestamp,
+            moderators: event.moderators,
+            participants: event.participants,
+            capacity: event.capacity,
+            registration: event.registration,
+            waitlist: event.waitlist,
+        }
     }
+}
+
+/// A new round, taking place after the rounds of its puzzle already in the event.
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validation_services::valid_round"))]
+pub struct CreateRoundDto {
+    pub kind: ScrambleKind,
+    pub format: RoundFormat,
+    #[validate(nested)]
+    pub cutoff: Option<Cutoff>,
+    #[validate(nested)]
+    pub time_limit: Option<TimeLimit>,
+    #[validate(custom(function = "validation_services::valid_advancement"))]
+    pub advancement: Option<Advancement>,
+}
 
-    pub fn add_participant(&mut self, user_id: Uuid) {
-        self.participants
-            .push(user_id);
+/// A round without its results.
+#[derive(Serialize)]
+pub struct RoundDto {
+    pub id: Uuid,
+    pub kind: ScrambleKind,
+    pub number: usize,
+    pub format: RoundFormat,
+    pub cutoff: Option<Cutoff>,
+    pub time_limit: Option<TimeLimit>,
+    pub advancement: Option<Advancement>,
+}
+
+impl RoundDto {
+    pub fn from(event: &Event, round: &Round) -> RoundDto {
+        RoundDto {
+            id: round.id,
+            kind: round
+                .kind
+                .clone(),
+            number: event.round_number(round),
+            format: round.format,
+            cutoff: round.cutoff,
+            time_limit: round.time_limit,
+            advancement: round.advancement,
+        }
     }
 }
@@ -1,13 +1,91 @@
 use mongodb::bson::Uuid;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use validator::Validate;
 
-use crate::routes::scrambles::Scramble;
+use crate::{
+    routes::scrambles::{Scramble, ScrambleKind},
+    services::validation_services,
+};
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+/// Longest time a solve or an attempt may last, 24 hours, which keeps results well within the
+/// integers BSON stores.
+pub const MAX_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// Most moves a solution may have once written out, well above the solves of the largest cubes.
+pub const MAX_SOLUTION_MOVES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum Penalty {
+    #[default]
+    None,
+    PlusTwo,
+    Dnf,
+}
+
+/// The end of a phase of a solve (cross, F2L pair, OLL, ...), counted from the start.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Validate)]
+pub struct Split {
+    #[validate(length(min = 1, max = 32, message = "length must be in range (1..=32)"))]
+    pub name: String,
+    pub millis: u64,
+}
+
+/// The moves a solve was made with, written in the notation of the puzzle.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Validate)]
+pub struct Solution {
+    #[validate(length(min = 1, max = 4096, message = "length must be in range (1..=4096)"))]
+    pub sequence: String,
+    /// When every move was made, in milliseconds from the start of the solve.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(length(max = 1000, message = "length must be at most 1000"))]
+    pub timestamps: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Validate)]
+#[validate(schema(function = "validation_services::valid_splits"))]
+#[validate(schema(function = "validation_services::valid_solution"))]
 pub struct Time {
+    /// Times recorded before ids existed get theirs from `session_services::assign_time_ids`.
+    #[serde(default = "Uuid::new")]
+    pub id: Uuid,
+    #[validate(range(max = MAX_MILLIS, message = "must be at most 24 hours"))]
     pub millis: u64,
     pub recorded_at: u64,
+    #[validate(custom(function = "validation_services::valid_scramble"))]
     pub scramble: Scramble,
+    #[serde(default)]
+    pub penalty: Penalty,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 256, message = "length must be at most 256"))]
+    pub comment: Option<String>,
+    /// Phases of the solve in order, the last one ending at `millis`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(length(max = 16, message = "length must be at most 16"), nested)]
+    pub splits: Vec<Split>,
+    /// Checked to solve the scramble, see `solution_services::check`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub solution: Option<Solution>,
+}
+
+impl Time {
+    /// The time counted in statistics, with the +2 added. A DNF has none.
+    pub fn result(&self) -> Option<u64> {
+        match self.penalty {
+            Penalty::None => Some(self.millis),
+            Penalty::PlusTwo => Some(self.millis + 2000),
+            Penalty::Dnf => None,
+        }
+    }
+}
+
+/// Changes to a recorded time, the fields left out are kept as they are. Changing `millis`
+/// drops the splits, which would no longer end at it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Validate)]
+pub struct TimeUpdate {
+    #[validate(range(max = MAX_MILLIS, message = "must be at most 24 hours"))]
+    pub millis: Option<u64>,
+    pub penalty: Option<Penalty>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -17,6 +95,13 @@ pub struct Session {
     pub account_id: Uuid,
     pub name: String,
     pub times: Vec<Time>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The puzzle every time of the session is of, when the session is bound to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ScrambleKind>,
 }
 
 impl Session {
@@ -26,6 +111,60 @@ impl Session {
             account_id,
             name: name.to_owned(),
             times: times.to_owned(),
+            description: None,
+            tags: Vec::new(),
+            kind: None,
         }
     }
 }
+
+/// Tells a field set to `null` (`Some(None)`) apart from a field left out (`None`).
+fn nullable<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// Changes to the metadata of a session, the fields left out are kept as they are and the
+/// ones set to `null` are removed.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Validate)]
+pub struct SessionUpdate {
+    #[validate(length(min = 1, max = 32, message = "length must be in range (1..=32)"))]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(max = 1024, message = "length must be at most 1024"))]
+    pub description: Option<Option<String>>,
+    #[validate(
+        length(max = 16, message = "length must be at most 16"),
+        custom(function = "validation_services::valid_tags")
+    )]
+    pub tags: Option<Vec<String>>,
+    /// Binding a session to a puzzle requires every time it already has to be of it.
+    #[serde(default, deserialize_with = "nullable")]
+    pub kind: Option<Option<ScrambleKind>>,
+}
+
+/// A session without its times, for listing many sessions at once.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SessionSummary {
+    #[serde(rename = "_id")]
+    pub id: Uuid,
+    pub account_id: Uuid,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ScrambleKind>,
+    pub time_count: u64,
+    /// When the last time was recorded, `None` for an empty session.
+    pub last_recorded_at: Option<u64>,
+}
+
+/// A time along with its position in the session.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct IndexedTime {
+    pub index: u64,
+    pub time: Time,
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Extension, Router};
+use axum_extra::json;
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    services::{
+        self,
+        alg_services::{self, Operation},
+        validation_services::{self, ValidatedJson},
+    },
+    AppState,
+};
+
+use super::scrambles::ScrambleKind;
+
+#[derive(Deserialize, Validate)]
+struct TransformPayload {
+    kind: ScrambleKind,
+    #[validate(length(max = 2048, message = "length must be at most 2048"))]
+    sequence: String,
+    #[validate(
+        length(min = 1, max = 32, message = "length must be in range (1..=32)"),
+        custom(function = "validation_services::valid_operations")
+    )]
+    operations: Vec<Operation>,
+}
+
+async fn transform(
+    ValidatedJson(payload): ValidatedJson<TransformPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    // Sequences may expand to thousands of moves, so keep the work off the async runtime.
+    let sequence = tokio::task::spawn_blocking(move || {
+        alg_services::transform(&payload.kind, &payload.sequence, &payload.operations)
+    })
+    .await
+    .map_err(anyhow::Error::new)??;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Sequence transformed",
+            "payload": {
+                "sequence": sequence,
+            }
+        }),
+    ))
+}
+
+pub fn create_routes(state: Arc<AppState>) -> Router {
+    let protected_routes = Router::new()
+        .route("/transform", post(transform))
+        .layer(axum::middleware::from_fn(
+            services::auth_services::auth_guard,
+        ));
+
+    Router::new()
+        .merge(protected_routes)
+        .layer(Extension(state))
+}
@@ -1,59 +1,600 @@
-use crate::error::AppError;
-use crate::models::event::Event;
-use crate::AppState;
-use axum::extract::Path;
-use axum::response::IntoResponse;
-use axum::routing::{delete, get, post, put};
-use axum::{Extension, Json, Router};
-use mongodb::bson::Uuid;
 use std::sync::Arc;
 
+use axum::{
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Extension, Router,
+};
+use axum_extra::json;
+use mongodb::bson::Uuid;
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AuthError},
+    models::{
+        account::Account,
+        event::{
+            CreateEventDto, CreateRoundDto, Event, EventDto, ResultEntryDto, Round, RoundDto,
+            UpdateEventDto,
+        },
+    },
+    services::{
+        self, event_services, round_services,
+        validation_services::{field_error, ValidatedJson, ValidatedPath, ValidatedQuery},
+    },
+    AppState,
+};
+
+use super::PathId;
+
+fn default_page() -> u64 {
+    1
+}
+
+fn default_limit() -> u32 {
+    20
+}
+
+#[derive(Deserialize, Validate)]
+struct EventsQuery {
+    #[serde(default = "default_page")]
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    page: u64,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100, message = "must be in range (1..=100)"))]
+    limit: u32,
+}
+
 async fn get_all(
-    Extension(_state): Extension<Arc<AppState>>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedQuery(query): ValidatedQuery<EventsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    Err::<AppError, _>(AppError::NotImplemented)
-    // TODO: get all non-private events
+    let events: Vec<EventDto> =
+        event_services::find_visible(&state, &account, query.page, query.limit)
+            .await?
+            .into_iter()
+            .map(EventDto::from)
+            .collect();
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": &format!("Found {} events", events.len()),
+            "payload": {
+                "events": events,
+            }
+        }),
+    ))
 }
 
 async fn create(
-    Extension(_state): Extension<Arc<AppState>>,
-    Json(_payload): Json<Event>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedJson(payload): ValidatedJson<CreateEventDto>,
 ) -> Result<impl IntoResponse, AppError> {
-    Err::<AppError, _>(AppError::NotImplemented)
-    // TODO: create a new event (payload shouldn't be of type Event but some DTO instead)
+    let mut event = Event::new(
+        &payload.title,
+        &payload.description,
+        payload.date_timestamp,
+        account.id,
+        payload.is_private,
+    );
+    event.capacity = payload.capacity;
+    event.registration = payload.registration;
+    let event_id = event.id;
+    event_services::insert(&state, event).await?;
+    event_services::grant_moderator(&state, event_id, account.id).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        json!({
+            "message": "Event created",
+            "payload": {
+                "event_id": event_id,
+            }
+        }),
+    ))
 }
 
 async fn get_one(
-    Extension(_state): Extension<Arc<AppState>>,
-    Path(_id): Path<Uuid>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
 ) -> Result<impl IntoResponse, AppError> {
-    Err::<AppError, _>(AppError::NotImplemented)
-    // TODO: get a single event by id (if it's private, should be authorized to see it)
+    let event = event_services::find_visible_by_id(&state, &account, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Event found",
+            "payload": {
+                "event": EventDto::from(event),
+            }
+        }),
+    ))
+}
+
+/// The event, if the account may change it. Events the account can't see aren't found.
+async fn find_managed(
+    state: &Arc<AppState>,
+    account: &Account,
+    id: Uuid,
+) -> Result<Event, AppError> {
+    let event = event_services::find_visible_by_id(state, account, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if !event_services::can_manage(account, &event) {
+        return Err(AuthError::Forbidden.into());
+    }
+    Ok(event)
 }
 
 async fn update(
-    Extension(_state): Extension<Arc<AppState>>,
-    Path(_id): Path<Uuid>,
-    Json(_payload): Json<Event>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedJson(payload): ValidatedJson<UpdateEventDto>,
 ) -> Result<impl IntoResponse, AppError> {
-    Err::<AppError, _>(AppError::NotImplemented)
-    // TODO: update a single event by id (should be authorized)
+    let event = find_managed(&state, &account, path.id).await?;
+    let result = event_services::update_by_id(&state, event.id, &payload).await?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Event updated",
+            "payload": {
+                "matched_count": result.matched_count,
+                "modified_count": result.modified_count,
+            }
+        }),
+    ))
 }
 
 async fn delete_one(
-    Extension(_state): Extension<Arc<AppState>>,
-    Path(_id): Path<Uuid>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let deleted_count = event_services::delete_by_id(&state, event.id)
+        .await?
+        .deleted_count;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": if deleted_count > 0 { "Event deleted" } else { "Nothing to delete" },
+            "payload": {
+                "deleted_count": deleted_count,
+            }
+        }),
+    ))
+}
+
+async fn register(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    // Private events can't be seen before registering, their id works as an invitation.
+    let event = event_services::find_by_id(&state, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    event_services::check_registration_window(&event, chrono::Utc::now().timestamp_millis())
+        .map_err(|err| field_error("event", err))?;
+
+    let approved = event_services::can_manage(&account, &event);
+    let registration = event_services::register(&state, &event, account.id, approved)
+        .await?
+        .ok_or_else(|| field_error("event", "already registered"))?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Registered",
+            "payload": {
+                "registration": registration,
+            }
+        }),
+    ))
+}
+
+async fn withdraw(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = event_services::withdraw(&state, path.id, account.id).await?;
+    if result.modified_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Registration withdrawn",
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn get_registrations(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Registrations found",
+            "payload": {
+                "participants": event.participants,
+                "waitlist": event.waitlist,
+                "pending": event.pending,
+            }
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+struct AccountPath {
+    id: Uuid,
+    account_id: Uuid,
+}
+
+async fn approve_registration(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<AccountPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let registration = event_services::approve(&state, event.id, path.account_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Registration approved",
+            "payload": {
+                "registration": registration,
+            }
+        }),
+    ))
+}
+
+async fn reject_registration(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<AccountPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let result = event_services::reject(&state, event.id, path.account_id).await?;
+    if result.modified_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Registration rejected",
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn grant_moderator(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<AccountPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let result = event_services::grant_moderator(&state, event.id, path.account_id).await?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": if result.modified_count > 0 { "Moderator granted" } else { "Already a moderator" },
+            "payload": {
+                "modified_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn revoke_moderator(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<AccountPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    if !event_services::revoke_moderator(&state, event.id, path.account_id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((StatusCode::OK, json!({ "message": "Moderator revoked" })))
+}
+
+async fn get_rounds(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = event_services::find_visible_by_id(&state, &account, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let rounds: Vec<RoundDto> = event
+        .rounds
+        .iter()
+        .map(|round| RoundDto::from(&event, round))
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": &format!("Found {} rounds", rounds.len()),
+            "payload": {
+                "rounds": rounds,
+            }
+        }),
+    ))
+}
+
+async fn create_round(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedJson(payload): ValidatedJson<CreateRoundDto>,
 ) -> Result<impl IntoResponse, AppError> {
-    Err::<AppError, _>(AppError::NotImplemented)
-    // TODO: delete a single event by id (should be authorized)
+    let event = find_managed(&state, &account, path.id).await?;
+    let round = Round::new(payload);
+    let result = round_services::insert(&state, event.id, &round).await?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        json!({
+            "message": "Round created",
+            "payload": {
+                "round_id": round.id,
+            }
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+struct RoundPath {
+    id: Uuid,
+    round_id: Uuid,
+}
+
+async fn get_round_results(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<RoundPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = event_services::find_visible_by_id(&state, &account, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let round = event
+        .round(path.round_id)
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Results found",
+            "payload": {
+                "round": RoundDto::from(&event, round),
+                "results": round_services::rank(round),
+            }
+        }),
+    ))
+}
+
+async fn delete_round(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<RoundPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let result = round_services::delete_by_id(&state, event.id, path.round_id).await?;
+    if result.modified_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Round deleted",
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+struct ResultPath {
+    id: Uuid,
+    round_id: Uuid,
+    account_id: Uuid,
+}
+
+async fn enter_result(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<ResultPath>,
+    ValidatedJson(payload): ValidatedJson<ResultEntryDto>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let round = event
+        .round(path.round_id)
+        .ok_or(AppError::NotFound)?;
+    if !round_services::may_compete(&event, round, path.account_id) {
+        return Err(field_error(
+            "account_id",
+            "the account doesn't compete in this round",
+        ));
+    }
+    round_services::check_attempts(round, &payload.attempts)
+        .map_err(|err| field_error("attempts", err))?;
+
+    let entered = round
+        .results
+        .iter()
+        .find(|result| result.account_id == path.account_id);
+    if let Some(entered) = entered {
+        if entered.attempts == payload.attempts {
+            return Ok((
+                StatusCode::OK,
+                json!({
+                    "message": "Result unchanged",
+                    "payload": {
+                        "modified_count": 0,
+                    }
+                }),
+            ));
+        }
+        if !payload.confirm {
+            return Err(field_error(
+                "confirm",
+                "a result was already entered, confirm to replace it",
+            ));
+        }
+    }
+
+    let result = round_services::set_result(
+        &state,
+        event.id,
+        round.id,
+        path.account_id,
+        account.id,
+        &payload.attempts,
+        entered.map(|entered| {
+            entered
+                .attempts
+                .as_slice()
+        }),
+    )
+    .await?;
+    if result.matched_count == 0 {
+        return Err(field_error(
+            "confirm",
+            "the result was changed in the meantime, check it and enter it again",
+        ));
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Result entered",
+            "payload": {
+                "modified_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn delete_result(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<ResultPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let result =
+        round_services::delete_result(&state, event.id, path.round_id, path.account_id, account.id)
+            .await?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Result deleted",
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn get_round_audit(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<RoundPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let event = find_managed(&state, &account, path.id).await?;
+    let round = event
+        .round(path.round_id)
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": &format!("Found {} changes", round.audit.len()),
+            "payload": {
+                "changes": &round.audit,
+            }
+        }),
+    ))
 }
 
 pub fn create_routes(state: Arc<AppState>) -> Router {
-    Router::new()
+    let protected_routes = Router::new()
         .route("/", get(get_all))
         .route("/", post(create))
         .route("/{id}", get(get_one))
         .route("/{id}", put(update))
         .route("/{id}", delete(delete_one))
+        .route("/{id}/register", post(register))
+        .route("/{id}/register", delete(withdraw))
+        .route("/{id}/registrations", get(get_registrations))
+        .route(
+            "/{id}/registrations/{account_id}/approve",
+            post(approve_registration),
+        )
+        .route(
+            "/{id}/registrations/{account_id}",
+            delete(reject_registration),
+        )
+        .route("/{id}/rounds", get(get_rounds))
+        .route("/{id}/rounds", post(create_round))
+        .route("/{id}/rounds/{round_id}/results", get(get_round_results))
+        .route("/{id}/rounds/{round_id}", delete(delete_round))
+        .route(
+            "/{id}/rounds/{round_id}/results/{account_id}",
+            put(enter_result),
+        )
+        .route(
+            "/{id}/rounds/{round_id}/results/{account_id}",
+            delete(delete_result),
+        )
+        .route("/{id}/rounds/{round_id}/audit", get(get_round_audit))
+        .route("/{id}/moderators/{account_id}", put(grant_moderator))
+        .route("/{id}/moderators/{account_id}", delete(revoke_moderator))
+        .layer(axum::middleware::from_fn(
+            services::auth_services::auth_guard,
+        ));
+
+    Router::new()
+        .merge(protected_routes)
         .layer(Extension(state))
 }
@@ -9,11 +9,12 @@ use validator::Validate;
 use crate::AppState;
 
 mod accounts;
+mod algs;
 pub mod auth;
 mod events;
 mod hello;
 pub mod scrambles;
-mod sessions;
+pub mod sessions;
 
 #[derive(Deserialize, Validate)]
 pub struct PathId {
@@ -28,6 +29,7 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
             accounts::create_routes(Arc::clone(&state)),
         )
         .nest("/api/v1/auth", auth::create_routes(Arc::clone(&state)))
+        .nest("/api/v1/algs", algs::create_routes(Arc::clone(&state)))
         .nest("/api/v1/events", events::create_routes(Arc::clone(&state)))
         .nest(
             "/api/v1/scrambles",
@@ -1,26 +1,75 @@
 use std::sync::Arc;
 
-use axum::{http::StatusCode, response::IntoResponse, routing::get, Extension, Router};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Extension, Router,
+};
 use axum_extra::json;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::{
     error::AppError,
-    services::{scramble_services, validation_services::ValidatedQuery},
+    services::{
+        preview_services,
+        scramble_services::{self, training},
+        validation_services::{field_error, ValidatedQuery},
+    },
     AppState,
 };
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum ScrambleKind {
+    Two,
     Three,
-    // TODO (wedkarz): Add more puzzles later
+    Four,
+    Five,
+    Six,
+    Seven,
+    Pyraminx,
+    Skewb,
+    Megaminx,
+    SquareOne,
+    Clock,
+}
+
+impl ScrambleKind {
+    /// Number of layers of the cube, `None` for puzzles which aren't cubes.
+    pub fn cube_size(&self) -> Option<usize> {
+        match self {
+            ScrambleKind::Two => Some(2),
+            ScrambleKind::Three => Some(3),
+            ScrambleKind::Four => Some(4),
+            ScrambleKind::Five => Some(5),
+            ScrambleKind::Six => Some(6),
+            ScrambleKind::Seven => Some(7),
+            _ => None,
+        }
+    }
+}
+
+/// Steps of a 3x3x3 solve which can be practised on their own: only the pieces of the step
+/// are mixed, the rest of the cube is solved.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum TrainingMode {
+    LastLayer,
+    Oll,
+    Pll,
+    Zbll,
+    LastSlot,
+    TwoGen,
+    CrossSolved,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Scramble {
     pub kind: ScrambleKind,
     pub sequence: String,
+    /// Case of a training scramble (e.g. `PLL Ga`), so that times can be grouped by case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Validate)]
@@ -28,20 +77,61 @@ struct ScrambleQuery {
     kind: ScrambleKind,
     #[validate(range(min = 1, max = 64, message = "must be in range (1..=64)"))]
     count: usize,
+    #[validate(range(max = scramble_services::MAX_SEED, message = "must be at most 2^53 - 1"))]
+    seed: Option<u64>,
+    mode: Option<TrainingMode>,
+    #[validate(length(max = 1024, message = "length must be at most 1024"))]
+    cases: Option<String>,
 }
 
 async fn generate(
+    Extension(state): Extension<Arc<AppState>>,
     ValidatedQuery(query): ValidatedQuery<ScrambleQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let scrambles: Vec<Scramble> = std::iter::repeat_with(|| {
-        scramble_services::generate(
+    let seed = query
+        .seed
+        .unwrap_or_else(scramble_services::random_seed);
+    let cases = match query.mode {
+        Some(_) if query.kind != ScrambleKind::Three => {
+            return Err(field_error("mode", "only available for Three"));
+        }
+        Some(mode) => training::select_cases(
+            mode,
             query
-                .kind
-                .clone(),
+                .cases
+                .as_deref(),
         )
+        .map_err(|message| field_error("cases", message))?,
+        None if query
+            .cases
+            .is_some() =>
+        {
+            return Err(field_error("cases", "requires a training mode"));
+        }
+        None => Vec::new(),
+    };
+
+    // Random-state scrambles are solved on the spot (and may need to build lookup tables first),
+    // so keep that work off the async runtime.
+    let scrambles: Vec<Scramble> = tokio::task::spawn_blocking(move || {
+        let mut rng = scramble_services::seeded_rng(seed);
+        std::iter::repeat_with(|| match query.mode {
+            Some(mode) => {
+                scramble_services::generate_training(&state.scramble_tables, mode, &cases, &mut rng)
+            }
+            None => scramble_services::generate(
+                &state.scramble_tables,
+                query
+                    .kind
+                    .clone(),
+                &mut rng,
+            ),
+        })
+        .take(query.count)
+        .collect()
     })
-    .take(query.count)
-    .collect();
+    .await
+    .map_err(anyhow::Error::new)?;
 
     Ok((
         StatusCode::OK,
@@ -49,13 +139,41 @@ async fn generate(
             "message": &format!("Successfully generated {} new scrambles", scrambles.len()),
             "payload": {
                 "scrambles": scrambles,
+                "seed": seed,
             }
         }),
     ))
 }
 
+#[derive(Clone, Deserialize, Validate)]
+struct PreviewQuery {
+    kind: ScrambleKind,
+    #[validate(length(max = 2048, message = "length must be at most 2048"))]
+    sequence: String,
+    scheme: Option<String>,
+}
+
+async fn preview(
+    ValidatedQuery(query): ValidatedQuery<PreviewQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let svg = preview_services::render(
+        &query.kind,
+        &query.sequence,
+        query
+            .scheme
+            .as_deref(),
+    )?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "image/svg+xml")],
+        svg,
+    ))
+}
+
 pub fn create_routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/", get(generate))
+        .route("/preview", get(preview))
         .layer(Extension(state))
 }
@@ -1,10 +1,12 @@
 use std::sync::Arc;
 
 use axum::{
-    http::StatusCode,
+    body::Body,
+    extract::DefaultBodyLimit,
+    http::{header, StatusCode},
     response::IntoResponse,
-    routing::{delete, get, post},
-    Extension, Router,
+    routing::{delete, get, patch, post, put},
+    Extension, Json, Router,
 };
 use axum_extra::json;
 use mongodb::bson::Uuid;
@@ -15,21 +17,46 @@ use crate::{
     error::AppError,
     models::{
         account::Account,
-        session::{Session, Time},
+        session::{Penalty, Session, SessionUpdate, Solution, Time, TimeUpdate},
     },
     services::{
-        self, session_services,
-        validation_services::{ValidatedJson, ValidatedPath},
+        self,
+        cstimer_services::{self, CsTimerExport},
+        export_services,
+        session_services::{self, TimeRef},
+        solution_services, stats_services,
+        validation_services::{field_error, ValidatedJson, ValidatedPath, ValidatedQuery},
     },
     AppState,
 };
 
-use super::PathId;
+use super::{scrambles::ScrambleKind, PathId};
+
+#[derive(Deserialize, Validate)]
+struct SessionsQuery {
+    /// Leaves the times out, giving their count and the last time they were recorded instead.
+    #[serde(default)]
+    summary: bool,
+}
 
 async fn get_all_sessions(
     Extension(state): Extension<Arc<AppState>>,
     Extension(account): Extension<Account>,
+    ValidatedQuery(query): ValidatedQuery<SessionsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
+    if query.summary {
+        let sessions = session_services::find_summaries_by_account_id(&state, account.id).await?;
+        return Ok((
+            StatusCode::OK,
+            json!({
+                "message": &format!("Found {} sessions", sessions.len()),
+                "payload": {
+                    "sessions": sessions,
+                }
+            }),
+        ));
+    }
+
     let sessions = session_services::find_all_by_account_id(&state, account.id).await?;
     Ok((
         StatusCode::OK,
@@ -62,6 +89,101 @@ async fn get_by_id(
     ))
 }
 
+async fn get_stats(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = session_services::find_by_id_and_account_id(&state, account.id, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let stats = stats_services::session_stats(&session.times);
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Session statistics computed",
+            "payload": {
+                "stats": stats,
+            }
+        }),
+    ))
+}
+
+async fn get_phase_stats(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = session_services::find_by_id_and_account_id(&state, account.id, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let phases = stats_services::phase_stats(&session.times);
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": &format!("Statistics of {} phases computed", phases.len()),
+            "payload": {
+                "phases": phases,
+            }
+        }),
+    ))
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+fn default_limit() -> u32 {
+    100
+}
+
+/// Filters and page of a time listing, every bound is inclusive.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct TimesQuery {
+    /// `next_cursor` of the previous page.
+    #[validate(length(max = 64, message = "length must be at most 64"))]
+    pub cursor: Option<String>,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 500, message = "must be in range (1..=500)"))]
+    pub limit: u32,
+    /// Range of `recorded_at`.
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub penalty: Option<Penalty>,
+    pub kind: Option<ScrambleKind>,
+    pub min_millis: Option<u64>,
+    pub max_millis: Option<u64>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+async fn get_times(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedQuery(query): ValidatedQuery<TimesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = session_services::find_times(&state, account.id, path.id, &query)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": &format!("Found {} times", page.times.len()),
+            "payload": {
+                "times": page.times,
+                "next_cursor": page.next_cursor,
+            }
+        }),
+    ))
+}
+
 #[derive(Deserialize, Validate)]
 struct EmptySessionPayload {
     #[validate(length(min = 1, max = 32, message = "length must be in range (1..=32)"))]
@@ -88,23 +210,154 @@ async fn create_empty(
     ))
 }
 
+async fn update_metadata(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedJson(payload): ValidatedJson<SessionUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload == SessionUpdate::default() {
+        return Err(field_error("session", "nothing to update"));
+    }
+
+    let result = session_services::update_metadata(&state, account.id, path.id, &payload).await?;
+    if result.matched_count == 0 {
+        // Either there is no such session, or it has times of another puzzle than the one
+        // it was to be bound to.
+        return match session_services::find_by_id_and_account_id(&state, account.id, path.id)
+            .await?
+        {
+            Some(_) => Err(field_error(
+                "kind",
+                "the session has times of another puzzle",
+            )),
+            None => Err(AppError::NotFound),
+        };
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Session updated",
+            "payload": {
+                "matched_count": result.matched_count,
+                "modified_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
 #[derive(Deserialize, Validate)]
 struct AddTimePayload {
     session_id: Uuid,
+    #[validate(nested)]
     time: Time,
 }
 
 async fn insert_time(
     Extension(state): Extension<Arc<AppState>>,
     Extension(account): Extension<Account>,
-    ValidatedJson(payload): ValidatedJson<AddTimePayload>,
+    ValidatedJson(mut payload): ValidatedJson<AddTimePayload>,
 ) -> Result<impl IntoResponse, AppError> {
+    // Ids are always given by the server.
+    payload
+        .time
+        .id = Uuid::new();
+    let time_id = payload
+        .time
+        .id;
+
     let result =
         session_services::insert_time(&state, account.id, payload.session_id, payload.time).await?;
+    if result.matched_count == 0 {
+        return Err(session_services::insert_error(&state, account.id, payload.session_id).await);
+    }
+
     Ok((
         StatusCode::CREATED,
         json!({
             "message": "New time inserted",
+            "payload": {
+                "time_id": time_id,
+            }
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+struct AddTimesPayload {
+    #[validate(
+        length(min = 1, max = 1000, message = "length must be in range (1..=1000)"),
+        nested
+    )]
+    times: Vec<Time>,
+}
+
+async fn insert_times(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedJson(mut payload): ValidatedJson<AddTimesPayload>,
+) -> Result<impl IntoResponse, AppError> {
+    for time in &mut payload.times {
+        time.id = Uuid::new();
+    }
+    let time_ids: Vec<Uuid> = payload
+        .times
+        .iter()
+        .map(|time| time.id)
+        .collect();
+
+    let result =
+        session_services::insert_times(&state, account.id, path.id, &payload.times).await?;
+    if result.matched_count == 0 {
+        return Err(session_services::insert_error(&state, account.id, path.id).await);
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        json!({
+            "message": &format!("Inserted {} new times", time_ids.len()),
+            "payload": {
+                "time_ids": time_ids,
+            }
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+struct TimePath {
+    id: Uuid,
+    /// Id of the time, or its position in the session.
+    time: String,
+}
+
+#[derive(Deserialize, Validate)]
+struct TimeIdPath {
+    id: Uuid,
+    time: Uuid,
+}
+
+async fn update_time(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<TimePath>,
+    ValidatedJson(payload): ValidatedJson<TimeUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    let time = parse_time_ref(&path.time)?;
+    if payload == TimeUpdate::default() {
+        return Err(field_error("time", "nothing to update"));
+    }
+
+    let result = session_services::update_time(&state, account.id, path.id, time, &payload).await?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Time updated",
             "payload": {
                 "matched_count": result.matched_count,
                 "modified_count": result.modified_count,
@@ -113,6 +366,240 @@ async fn insert_time(
     ))
 }
 
+fn parse_time_ref(time: &str) -> Result<TimeRef, AppError> {
+    time.parse()
+        .map_err(|_| field_error("time", "must be the id or index of a time"))
+}
+
+async fn get_solution(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<TimePath>,
+) -> Result<impl IntoResponse, AppError> {
+    let time =
+        session_services::find_time(&state, account.id, path.id, parse_time_ref(&path.time)?)
+            .await?
+            .ok_or(AppError::NotFound)?;
+    let solution = time
+        .solution
+        .ok_or(AppError::NotFound)?;
+    let analysis = solution_services::analyze(&time.scramble, &solution, time.millis)
+        .map_err(|err| field_error("solution", err))?;
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Solution found",
+            "payload": {
+                "solution": solution,
+                "analysis": analysis,
+            }
+        }),
+    ))
+}
+
+async fn set_solution(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<TimePath>,
+    ValidatedJson(solution): ValidatedJson<Solution>,
+) -> Result<impl IntoResponse, AppError> {
+    let time =
+        session_services::find_time(&state, account.id, path.id, parse_time_ref(&path.time)?)
+            .await?
+            .ok_or(AppError::NotFound)?;
+    let analysis = solution_services::analyze(&time.scramble, &solution, time.millis)
+        .map_err(|err| field_error("solution", err))?;
+
+    let result =
+        session_services::set_solution(&state, account.id, path.id, time.id, Some(&solution))
+            .await?;
+    // The time was deleted in the meantime.
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Solution attached",
+            "payload": {
+                "analysis": analysis,
+            }
+        }),
+    ))
+}
+
+async fn delete_solution(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<TimeIdPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let result =
+        session_services::set_solution(&state, account.id, path.id, path.time, None).await?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": if result.modified_count > 0 { "Solution deleted" } else { "Nothing to delete" },
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+async fn delete_time(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<TimeIdPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = session_services::delete_time(&state, account.id, path.id, path.time).await?;
+    if result.modified_count == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok((
+        StatusCode::OK,
+        json!({
+            "message": "Time deleted",
+            "payload": {
+                "deleted_count": result.modified_count,
+            }
+        }),
+    ))
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Deserialize, Validate)]
+struct ExportQuery {
+    format: ExportFormat,
+}
+
+async fn export_session(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedPath(path): ValidatedPath<PathId>,
+    ValidatedQuery(query): ValidatedQuery<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let session = session_services::find_by_id_and_account_id(&state, account.id, path.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let chunks = export_services::export_chunks(query.format, session.times);
+    let body = Body::from_stream(futures::stream::iter(
+        chunks.map(Ok::<_, std::convert::Infallible>),
+    ));
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                query
+                    .format
+                    .content_type()
+                    .to_owned(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"session-{}.{}\"",
+                    session.id,
+                    query
+                        .format
+                        .extension()
+                ),
+            ),
+        ],
+        body,
+    ))
+}
+
+async fn export_all_sessions(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedQuery(query): ValidatedQuery<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let sessions = session_services::find_all_by_account_id(&state, account.id).await?;
+    let zip = export_services::export_zip(query.format, sessions)?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"sessions.zip\"",
+            ),
+        ],
+        zip,
+    ))
+}
+
+/// Largest csTimer export accepted, which holds every session of an account.
+const CSTIMER_IMPORT_LIMIT: usize = 32 * 1024 * 1024;
+
+async fn import_cstimer(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+    ValidatedJson(export): ValidatedJson<CsTimerExport>,
+) -> Result<impl IntoResponse, AppError> {
+    let imported = cstimer_services::import(&export, account.id)?;
+    session_services::create_many(&state, &imported.sessions).await?;
+
+    let session_ids: Vec<Uuid> = imported
+        .sessions
+        .iter()
+        .map(|session| session.id)
+        .collect();
+    let time_count: usize = imported
+        .sessions
+        .iter()
+        .map(|session| {
+            session
+                .times
+                .len()
+        })
+        .sum();
+
+    Ok((
+        StatusCode::CREATED,
+        json!({
+            "message": &format!("Imported {} sessions", session_ids.len()),
+            "payload": {
+                "session_ids": session_ids,
+                "time_count": time_count,
+                "skipped_count": imported.skipped_times,
+            }
+        }),
+    ))
+}
+
+async fn export_cstimer(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(account): Extension<Account>,
+) -> Result<impl IntoResponse, AppError> {
+    let sessions = session_services::find_all_by_account_id(&state, account.id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"cstimer.txt\"",
+        )],
+        Json(cstimer_services::export(&sessions)),
+    ))
+}
+
 async fn delete_by_id(
     Extension(state): Extension<Arc<AppState>>,
     Extension(account): Extension<Account>,
@@ -156,8 +643,25 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
     let protected_routes = Router::new()
         .route("/", get(get_all_sessions))
         .route("/{id}", get(get_by_id))
+        .route("/{id}/stats", get(get_stats))
+        .route("/{id}/stats/phases", get(get_phase_stats))
         .route("/empty", post(create_empty))
         .route("/add-time", post(insert_time))
+        .route(
+            "/import/cstimer",
+            post(import_cstimer).layer(DefaultBodyLimit::max(CSTIMER_IMPORT_LIMIT)),
+        )
+        .route("/export/cstimer", get(export_cstimer))
+        .route("/export", get(export_all_sessions))
+        .route("/{id}/export", get(export_session))
+        .route("/{id}/times", get(get_times))
+        .route("/{id}/times", post(insert_times))
+        .route("/{id}/times/{time}", patch(update_time))
+        .route("/{id}/times/{time}", delete(delete_time))
+        .route("/{id}/times/{time}/solution", get(get_solution))
+        .route("/{id}/times/{time}/solution", put(set_solution))
+        .route("/{id}/times/{time}/solution", delete(delete_solution))
+        .route("/{id}", patch(update_metadata))
         .route("/{id}", delete(delete_by_id))
         .route("/", delete(delete_all_sessions))
         .layer(axum::middleware::from_fn(
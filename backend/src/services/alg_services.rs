@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, routes::scrambles::ScrambleKind};
+
+use super::{
+    notation::{
+        self,
+        clock::ClockNotation,
+        cube::{self, CubeMove, CubeNotation, CubeTurn, Orientation, Slice},
+        megaminx::MegaminxNotation,
+        pyraminx::PyraminxNotation,
+        skewb::SkewbNotation,
+        square_one::SquareOneNotation,
+        Node, Notation, ParseError,
+    },
+    validation_services::field_error,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// The inverse sequence, keeping its brackets.
+    Invert,
+    /// The sequence seen in a mirror standing on the M, E or S plane.
+    Mirror { plane: Slice },
+    /// The sequence to make after turning the cube by `rotation` (e.g. `y` or `x2 z'`),
+    /// i.e. `[rotation: A]` written without the rotation.
+    Rotate { rotation: String },
+    /// The expanded sequence, with moves of the same layers merged or cancelled.
+    Simplify,
+    /// Brackets and repetitions written out as plain moves.
+    Expand,
+}
+
+fn sequence_error(err: ParseError) -> AppError {
+    field_error("sequence", err.to_string())
+}
+
+/// Applies the operations every puzzle supports, and hands the others to `specific`.
+fn transform_nodes<N: Notation>(
+    notation: &N,
+    sequence: &str,
+    operations: &[Operation],
+    specific: impl Fn(Vec<Node<N::Move>>, &Operation) -> Result<Vec<Node<N::Move>>, AppError>,
+) -> Result<String, AppError> {
+    let mut nodes = notation::parse(notation, sequence).map_err(sequence_error)?;
+
+    for operation in operations {
+        nodes = match operation {
+            Operation::Invert => notation::invert_nodes(&nodes),
+            Operation::Expand => notation::expand_nodes(&nodes),
+            operation => specific(nodes, operation)?,
+        };
+    }
+    Ok(notation::format(&nodes))
+}
+
+fn unsupported<M>(_: Vec<Node<M>>, _: &Operation) -> Result<Vec<Node<M>>, AppError> {
+    Err(field_error(
+        "operations",
+        "only invert and expand are supported for this puzzle",
+    ))
+}
+
+/// Parses the rotations of a `rotate` operation into the orientation they turn the cube to.
+/// `[x y: A]` is `[x: [y: A]]`, so the last rotation relabels the moves first.
+fn parse_orientation(notation: &CubeNotation, rotation: &str) -> Result<Orientation, AppError> {
+    notation::parse_moves(notation, rotation)
+        .map_err(|err| field_error("operations", format!("invalid rotation: {}", err)))?
+        .into_iter()
+        .rev()
+        .try_fold(Orientation::IDENTITY, |orientation, mv| match mv.turn {
+            CubeTurn::Rotation(rotation) => Ok(orientation.then(rotation, mv.amount)),
+            _ => Err(field_error(
+                "operations",
+                format!("`{}` is not a rotation", mv),
+            )),
+        })
+}
+
+fn transform_cube(
+    size: usize,
+    sequence: &str,
+    operations: &[Operation],
+) -> Result<String, AppError> {
+    let notation = CubeNotation { size };
+
+    transform_nodes(&notation, sequence, operations, |nodes, operation| {
+        let nodes = match operation {
+            Operation::Mirror { plane } => {
+                notation::map_moves(&nodes, &|mv: &CubeMove| mv.mirrored(*plane))
+            }
+            Operation::Rotate { rotation } => {
+                let orientation = parse_orientation(&notation, rotation)?;
+                notation::map_moves(&nodes, &|mv: &CubeMove| mv.oriented(orientation))
+            }
+            Operation::Simplify => cube::simplify(notation::expand_with_offsets(&nodes))
+                .into_iter()
+                .map(|(offset, mv)| Node::Move(mv, offset))
+                .collect(),
+            Operation::Invert | Operation::Expand => unreachable!("handled for every puzzle"),
+        };
+        Ok(nodes)
+    })
+}
+
+/// Applies the operations to `sequence` in order, and writes the result back in the
+/// notation of the puzzle.
+pub fn transform(
+    kind: &ScrambleKind,
+    sequence: &str,
+    operations: &[Operation],
+) -> Result<String, AppError> {
+    match kind {
+        ScrambleKind::Pyraminx => {
+            transform_nodes(&PyraminxNotation, sequence, operations, unsupported)
+        }
+        ScrambleKind::Skewb => transform_nodes(&SkewbNotation, sequence, operations, unsupported),
+        ScrambleKind::Megaminx => {
+            transform_nodes(&MegaminxNotation, sequence, operations, unsupported)
+        }
+        ScrambleKind::SquareOne => {
+            transform_nodes(&SquareOneNotation, sequence, operations, unsupported)
+        }
+        ScrambleKind::Clock => transform_nodes(&ClockNotation, sequence, operations, unsupported),
+        cube_kind => {
+            let size = cube_kind
+                .cube_size()
+                .expect("every other kind is a cube");
+            transform_cube(size, sequence, operations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::cube_state::CubeState;
+
+    fn transform_3x3(sequence: &str, operations: &[Operation]) -> String {
+        transform(&ScrambleKind::Three, sequence, operations).unwrap()
+    }
+
+    fn rotate(rotation: &str) -> Operation {
+        Operation::Rotate {
+            rotation: rotation.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_invert_keeps_brackets() {
+        assert_eq!(
+            transform_3x3("R U2 [R, U] [F: R] (R U')3", &[Operation::Invert]),
+            "(U R')3 [F: R'] [U, R] U2' R'"
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            transform_3x3("[F: [R, U]]", &[Operation::Expand]),
+            "F R U R' U' F'"
+        );
+    }
+
+    #[test]
+    fn test_mirror() {
+        let mirror = |plane| Operation::Mirror { plane };
+
+        assert_eq!(
+            transform_3x3("R U R' U' M x", &[mirror(Slice::M)]),
+            "L' U' L U M x"
+        );
+        assert_eq!(
+            transform_3x3("R U F2 Rw S z", &[mirror(Slice::S)]),
+            "R' U' B2' Rw' S z"
+        );
+        assert_eq!(
+            transform_3x3("[R, U]", &[mirror(Slice::M), mirror(Slice::M)]),
+            "[R, U]"
+        );
+    }
+
+    #[test]
+    fn test_rotate() {
+        assert_eq!(transform_3x3("R U M x", &[rotate("y")]), "B U S z'");
+
+        // The result must do the same as the sequence made after turning the cube.
+        for rotation in ["y", "x2", "z'", "x y"] {
+            let sequence = "R U2 F' Rw D M' E S2 x";
+            let rotated = transform_3x3(sequence, &[rotate(rotation)]);
+            let inverse = transform_3x3(rotation, &[Operation::Invert]);
+
+            assert_eq!(
+                CubeState::from_sequence(3, &rotated).unwrap(),
+                CubeState::from_sequence(3, &format!("{} {} {}", rotation, sequence, inverse))
+                    .unwrap(),
+                "{}",
+                rotation
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotate_invalid_rotation() {
+        assert!(transform(&ScrambleKind::Three, "R", &[rotate("R")]).is_err());
+        assert!(transform(&ScrambleKind::Three, "R", &[rotate("q")]).is_err());
+    }
+
+    #[test]
+    fn test_rotate_by_many_rotations() {
+        assert_eq!(transform_3x3("R U M x", &[rotate("(y)4001")]), "B U S z'");
+        assert_eq!(transform_3x3("R U", &[rotate("(x y)6")]), "R U");
+    }
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(
+            transform_3x3("R R U U' R' L R2 F3 D4", &[Operation::Simplify]),
+            "R' L F'"
+        );
+        assert_eq!(
+            transform_3x3("[R, U]2 (U R U' R')2", &[Operation::Simplify]),
+            ""
+        );
+        assert_eq!(
+            transform_3x3("Rw r M x' x", &[Operation::Simplify]),
+            "Rw2 M"
+        );
+    }
+
+    #[test]
+    fn test_operations_in_order() {
+        assert_eq!(
+            transform_3x3("R U", &[Operation::Invert, rotate("y")]),
+            "U' B'"
+        );
+    }
+
+    #[test]
+    fn test_other_puzzles() {
+        assert_eq!(
+            transform(&ScrambleKind::Pyraminx, "[R, U]", &[Operation::Invert]).unwrap(),
+            "[U, R]"
+        );
+        assert_eq!(
+            transform(
+                &ScrambleKind::SquareOne,
+                "(1,0)/ (3,-3)/",
+                &[Operation::Invert]
+            )
+            .unwrap(),
+            "/ (-3,3) / (-1,0)"
+        );
+        assert!(matches!(
+            transform(&ScrambleKind::Megaminx, "R++ U", &[Operation::Simplify]),
+            Err(AppError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_sequence() {
+        assert!(matches!(
+            transform(&ScrambleKind::Three, "R [U", &[Operation::Invert]),
+            Err(AppError::Validation(_))
+        ));
+    }
+}
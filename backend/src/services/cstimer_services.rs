@@ -0,0 +1,433 @@
+use mongodb::bson::Uuid;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::session::{Penalty, Session, Time},
+    routes::scrambles::{Scramble, ScrambleKind},
+};
+
+use super::validation_services::field_error;
+
+/// Longest session name, as for sessions created empty.
+const MAX_NAME_LENGTH: usize = 32;
+
+/// A csTimer export: `session1`, `session2`, ... hold the solves of each session, and
+/// `properties.sessionData` (a JSON object stored as a string) their names and scramble types.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CsTimerExport {
+    #[serde(flatten)]
+    pub entries: Map<String, Value>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Imported {
+    pub sessions: Vec<Session>,
+    /// Solves which aren't valid times, e.g. whose scramble isn't in the notation of the puzzle of
+    /// their session or whose comment is too long.
+    pub skipped_times: usize,
+}
+
+/// The puzzle of a csTimer scramble type. Every 3x3x3 event (one-handed, blindfolded, ...)
+/// uses `333` scrambles, some with a suffix.
+fn kind_of(scramble_type: &str) -> Option<ScrambleKind> {
+    let kind = match scramble_type {
+        "222so" | "222o" | "2223" => ScrambleKind::Two,
+        "444wca" | "444m" | "444o" => ScrambleKind::Four,
+        "555wca" | "555" => ScrambleKind::Five,
+        "666wca" | "666si" => ScrambleKind::Six,
+        "777wca" | "777si" => ScrambleKind::Seven,
+        "pyrso" | "pyro" | "pyrm" => ScrambleKind::Pyraminx,
+        "skbso" | "skbo" => ScrambleKind::Skewb,
+        "mgmp" | "mgmo" => ScrambleKind::Megaminx,
+        "sqrs" | "sq1h" => ScrambleKind::SquareOne,
+        "clkwca" | "clko" => ScrambleKind::Clock,
+        three if three.starts_with("333") => ScrambleKind::Three,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// The csTimer scramble type written for a puzzle.
+fn scramble_type(kind: &ScrambleKind) -> &'static str {
+    match kind {
+        ScrambleKind::Two => "222so",
+        ScrambleKind::Three => "333",
+        ScrambleKind::Four => "444wca",
+        ScrambleKind::Five => "555wca",
+        ScrambleKind::Six => "666wca",
+        ScrambleKind::Seven => "777wca",
+        ScrambleKind::Pyraminx => "pyrso",
+        ScrambleKind::Skewb => "skbso",
+        ScrambleKind::Megaminx => "mgmp",
+        ScrambleKind::SquareOne => "sqrs",
+        ScrambleKind::Clock => "clkwca",
+    }
+}
+
+fn invalid(message: String) -> AppError {
+    field_error("export", message)
+}
+
+/// Parses `[[penalty, millis], scramble, comment, timestamp]`, where the penalty is 0, 2000 for
+/// a +2 or -1 for a DNF, and the timestamp is in seconds. Entries may carry more fields, which
+/// are ignored.
+fn parse_solve(solve: &Value, kind: &ScrambleKind) -> Option<Time> {
+    let fields = solve.as_array()?;
+    let result = fields
+        .first()?
+        .as_array()?;
+    let penalty = match result
+        .first()?
+        .as_i64()?
+    {
+        0 => Penalty::None,
+        2000 => Penalty::PlusTwo,
+        -1 => Penalty::Dnf,
+        _ => return None,
+    };
+    let text = |i: usize| {
+        fields
+            .get(i)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_owned()
+    };
+    let comment = text(2);
+
+    Some(Time {
+        id: Uuid::new(),
+        millis: result
+            .get(1)?
+            .as_u64()?,
+        // Seconds in csTimer, rejected when they don't fit in BSON as milliseconds.
+        recorded_at: fields
+            .get(3)
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+            .checked_mul(1000)
+            .filter(|&millis| i64::try_from(millis).is_ok())?,
+        scramble: Scramble {
+            kind: kind.clone(),
+            sequence: text(1),
+            case: None,
+        },
+        penalty,
+        comment: (!comment.is_empty()).then_some(comment),
+        splits: Vec::new(),
+        solution: None,
+    })
+}
+
+/// Name and scramble type of every session, by session number.
+fn session_data(entries: &Map<String, Value>) -> Result<Map<String, Value>, AppError> {
+    let data = entries
+        .get("properties")
+        .and_then(|properties| properties.get("sessionData"));
+    match data {
+        None => Ok(Map::new()),
+        Some(Value::Object(data)) => Ok(data.clone()),
+        Some(Value::String(data)) => match serde_json::from_str(data) {
+            Ok(Value::Object(data)) => Ok(data),
+            _ => Err(invalid("`sessionData` isn't a JSON object".to_owned())),
+        },
+        Some(_) => Err(invalid("`sessionData` isn't a JSON object".to_owned())),
+    }
+}
+
+/// Creates a session for every session of a csTimer export, in the order of their numbers.
+pub fn import(export: &CsTimerExport, account_id: Uuid) -> Result<Imported, AppError> {
+    let data = session_data(&export.entries)?;
+
+    let mut numbered: Vec<(u64, &Value)> = Vec::new();
+    for (key, solves) in &export.entries {
+        if let Some(number) = key
+            .strip_prefix("session")
+            .and_then(|number| {
+                number
+                    .parse()
+                    .ok()
+            })
+        {
+            numbered.push((number, solves));
+        }
+    }
+    if numbered.is_empty() {
+        return Err(invalid("no session found".to_owned()));
+    }
+    numbered.sort_by_key(|&(number, _)| number);
+
+    let mut imported = Imported {
+        sessions: Vec::new(),
+        skipped_times: 0,
+    };
+    for (number, solves) in numbered {
+        let metadata = data.get(&number.to_string());
+        let name = match metadata.and_then(|metadata| metadata.get("name")) {
+            Some(Value::String(name))
+                if !name
+                    .trim()
+                    .is_empty() =>
+            {
+                name.trim()
+                    .to_owned()
+            }
+            Some(Value::Number(name)) => name.to_string(),
+            _ => format!("Session {}", number),
+        };
+        let scramble_type = metadata
+            .and_then(|metadata| metadata.pointer("/opt/scrType"))
+            .and_then(Value::as_str)
+            .unwrap_or("333");
+        let kind = kind_of(scramble_type).ok_or_else(|| {
+            invalid(format!(
+                "session `{}` uses the unsupported scramble type `{}`",
+                name, scramble_type
+            ))
+        })?;
+
+        let solves = solves
+            .as_array()
+            .ok_or_else(|| invalid(format!("session `{}` isn't a list of solves", name)))?;
+        let mut times = Vec::with_capacity(solves.len());
+        for (i, solve) in solves
+            .iter()
+            .enumerate()
+        {
+            let time = parse_solve(solve, &kind)
+                .ok_or_else(|| invalid(format!("solve {} of session `{}` is invalid", i, name)))?;
+            if time
+                .validate()
+                .is_ok()
+            {
+                times.push(time);
+            } else {
+                imported.skipped_times += 1;
+            }
+        }
+
+        let name: String = name
+            .chars()
+            .take(MAX_NAME_LENGTH)
+            .collect();
+        imported
+            .sessions
+            .push(Session::new(account_id, &name, &times));
+    }
+
+    Ok(imported)
+}
+
+/// Writes the sessions as a csTimer export. The scramble type of a session is the one of its
+/// first time, 3x3x3 for an empty session.
+pub fn export(sessions: &[Session]) -> Value {
+    let mut entries = Map::new();
+    let mut data = Map::new();
+
+    for (i, session) in sessions
+        .iter()
+        .enumerate()
+    {
+        let number = i + 1;
+        let solves: Vec<Value> = session
+            .times
+            .iter()
+            .map(|time| {
+                let penalty = match time.penalty {
+                    Penalty::None => 0,
+                    Penalty::PlusTwo => 2000,
+                    Penalty::Dnf => -1,
+                };
+                json!([
+                    [penalty, time.millis],
+                    time.scramble
+                        .sequence,
+                    time.comment
+                        .as_deref()
+                        .unwrap_or_default(),
+                    time.recorded_at / 1000,
+                ])
+            })
+            .collect();
+        let kind = session
+            .times
+            .first()
+            .map_or(&ScrambleKind::Three, |time| {
+                &time
+                    .scramble
+                    .kind
+            });
+
+        entries.insert(format!("session{}", number), Value::Array(solves));
+        data.insert(
+            number.to_string(),
+            json!({
+                "name": session.name,
+                "opt": { "scrType": scramble_type(kind) },
+                "rank": number,
+            }),
+        );
+    }
+
+    entries.insert(
+        "properties".to_owned(),
+        json!({
+            "sessionData": Value::Object(data).to_string(),
+            "session": 1,
+        }),
+    );
+    Value::Object(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::session::MAX_MILLIS;
+
+    fn parse_export(export: Value) -> Result<Imported, AppError> {
+        let export: CsTimerExport = serde_json::from_value(export).unwrap();
+        import(&export, Uuid::new())
+    }
+
+    #[test]
+    fn test_import() {
+        let session_data = json!({
+            "1": { "name": "OH", "opt": { "scrType": "333oh" } },
+            "2": { "name": 2, "opt": { "scrType": "pyrso" } },
+        });
+        let imported = parse_export(json!({
+            "session2": [[[0, 4210], "U R' L B u l", "", 1700000100]],
+            "session1": [
+                [[0, 15320], "R U R' F2 D'", "nice", 1700000000],
+                [[2000, 16000], "F2 U' L", "", 1700000050, ["solution data"]],
+                [[-1, 20000], "B R", "", 1700000060],
+            ],
+            "properties": { "sessionData": session_data.to_string() },
+        }))
+        .unwrap();
+
+        assert_eq!(imported.skipped_times, 0);
+        let [oh, pyraminx] = &imported.sessions[..] else {
+            panic!("expected 2 sessions");
+        };
+        assert_eq!(oh.name, "OH");
+        assert_eq!(pyraminx.name, "2");
+        assert_eq!(
+            pyraminx.times[0]
+                .scramble
+                .kind,
+            ScrambleKind::Pyraminx
+        );
+
+        let penalties: Vec<Penalty> = oh
+            .times
+            .iter()
+            .map(|time| time.penalty)
+            .collect();
+        assert_eq!(penalties, [Penalty::None, Penalty::PlusTwo, Penalty::Dnf]);
+        assert_eq!(oh.times[0].millis, 15320);
+        assert_eq!(oh.times[0].recorded_at, 1700000000000);
+        assert_eq!(
+            oh.times[0]
+                .comment
+                .as_deref(),
+            Some("nice")
+        );
+        assert_eq!(oh.times[1].comment, None);
+        assert_eq!(oh.times[1].result(), Some(18000));
+    }
+
+    #[test]
+    fn test_import_without_session_data() {
+        let imported = parse_export(json!({
+            "session1": [[[0, 9000], "R U", "", 1]],
+        }))
+        .unwrap();
+
+        assert_eq!(imported.sessions[0].name, "Session 1");
+        assert_eq!(
+            imported.sessions[0].times[0]
+                .scramble
+                .kind,
+            ScrambleKind::Three
+        );
+    }
+
+    #[test]
+    fn test_import_skips_invalid_times() {
+        let imported = parse_export(json!({
+            "session1": [
+                [[0, 9000], "R U", "", 1],
+                [[0, 9000], "R Q", "", 2],
+                [[0, 9000], "R U", "a".repeat(257), 3],
+                [[0, MAX_MILLIS + 1], "R U", "", 4],
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            imported.sessions[0]
+                .times
+                .len(),
+            1
+        );
+        assert_eq!(imported.skipped_times, 3);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert!(parse_export(json!({ "properties": {} })).is_err());
+        assert!(parse_export(json!({ "session1": [[[5, 9000], "R", "", 1]] })).is_err());
+        assert!(parse_export(json!({ "session1": {} })).is_err());
+        assert!(
+            parse_export(json!({ "session1": [[[0, 9000], "R", "", u64::MAX / 100]] })).is_err()
+        );
+        assert!(parse_export(json!({
+            "session1": [],
+            "properties": { "sessionData": "{\"1\":{\"opt\":{\"scrType\":\"ftoso\"}}}" },
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let imported = parse_export(json!({
+            "session1": [
+                [[0, 15320], "R U R' F2 D'", "nice", 1700000000],
+                [[-1, 20000], "B R", "", 1700000060],
+            ],
+            "session2": [[[2000, 4210], "(1,0)/ (3,3)/", "", 1700000100]],
+            "properties": {
+                "sessionData": "{\"1\":{\"name\":\"Main\"},\"2\":{\"name\":\"Sq\",\"opt\":{\"scrType\":\"sqrs\"}}}",
+            },
+        }))
+        .unwrap();
+
+        let exported: CsTimerExport = serde_json::from_value(export(&imported.sessions)).unwrap();
+        let again = import(&exported, imported.sessions[0].account_id).unwrap();
+
+        for (session, other) in imported
+            .sessions
+            .iter()
+            .zip(&again.sessions)
+        {
+            assert_eq!(session.name, other.name);
+            for (time, other) in session
+                .times
+                .iter()
+                .zip(&other.times)
+            {
+                assert_eq!(
+                    Time {
+                        id: other.id,
+                        ..time.clone()
+                    },
+                    *other
+                );
+            }
+        }
+    }
+}
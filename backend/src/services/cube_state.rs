@@ -0,0 +1,404 @@
+use std::fmt;
+
+use crate::{
+    routes::scrambles::Scramble,
+    services::notation::{self, cube::CubeNotation, Invert, ParseError},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+impl Face {
+    /// Faces in the order used by the Kociemba facelet string.
+    pub const ALL: [Face; 6] = [Face::U, Face::R, Face::F, Face::D, Face::L, Face::B];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn opposite(self) -> Face {
+        Face::ALL[(self.index() + 3) % 6]
+    }
+
+    /// Where the face ends up after turning the whole cube around `axis` by `turns`
+    /// clockwise quarter turns.
+    pub fn turned(self, axis: Face, turns: u8) -> Face {
+        let mut direction = self.axis();
+        for _ in 0..turns % 4 {
+            direction = rotate(axis.axis(), direction);
+        }
+        Face::from_axis(direction)
+    }
+
+    // Unit vector pointing out of the face, with x to the right, y up and z to the front.
+    fn axis(self) -> [i32; 3] {
+        match self {
+            Face::U => [0, 1, 0],
+            Face::R => [1, 0, 0],
+            Face::F => [0, 0, 1],
+            Face::D => [0, -1, 0],
+            Face::L => [-1, 0, 0],
+            Face::B => [0, 0, -1],
+        }
+    }
+
+    fn from_axis(axis: [i32; 3]) -> Face {
+        *Face::ALL
+            .iter()
+            .find(|face| face.axis() == axis)
+            .expect("axis should be a unit vector")
+    }
+
+    pub fn from_char(c: char) -> Option<Face> {
+        Face::ALL
+            .into_iter()
+            .find(|face| face.to_char() == c)
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Face::U => 'U',
+            Face::R => 'R',
+            Face::F => 'F',
+            Face::D => 'D',
+            Face::L => 'L',
+            Face::B => 'B',
+        }
+    }
+}
+
+/// A turn of the consecutive layers `from..=to` (counted from `face`, starting at 1)
+/// by `turns` clockwise quarter turns, as seen when looking at `face`.
+/// Face, wide and slice moves as well as cube rotations all map to this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub face: Face,
+    pub from: usize,
+    pub to: usize,
+    pub turns: u8,
+}
+
+impl Invert for Move {
+    fn inverse(&self) -> Move {
+        Move {
+            turns: (4 - self.turns) % 4,
+            ..*self
+        }
+    }
+}
+
+/// Parses a move sequence for a cube of the given size.
+pub fn parse_moves(sequence: &str, size: usize) -> Result<Vec<Move>, ParseError> {
+    Ok(notation::parse_moves(&CubeNotation { size }, sequence)?
+        .iter()
+        .map(|mv| mv.layers(size))
+        .collect())
+}
+
+fn cross(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [i32; 3], b: [i32; 3]) -> i32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Clockwise quarter turn around `axis`, as seen when looking at the origin from its end.
+fn rotate(axis: [i32; 3], v: [i32; 3]) -> [i32; 3] {
+    let c = cross(axis, v);
+    let d = dot(axis, v);
+    [axis[0] * d - c[0], axis[1] * d - c[1], axis[2] * d - c[2]]
+}
+
+/// Sticker state of an NxNxN cube. Every facelet remembers which facelet of the solved cube
+/// it came from, so states can be inverted and not only compared by colour.
+///
+/// Facelets are ordered as in the Kociemba facelet string: faces U, R, F, D, L, B, each read
+/// row by row as seen when looking at the face, with U and D oriented towards F
+/// (U has B on top, D has F on top) and the side faces having U on top.
+#[derive(Clone, Debug)]
+pub struct CubeState {
+    size: usize,
+    facelets: Vec<u16>,
+}
+
+impl CubeState {
+    pub fn solved(size: usize) -> CubeState {
+        CubeState {
+            size,
+            facelets: (0..6 * size * size)
+                .map(|i| i as u16)
+                .collect(),
+        }
+    }
+
+    /// Applies `sequence` to a solved cube.
+    pub fn from_sequence(size: usize, sequence: &str) -> Result<CubeState, ParseError> {
+        let mut cube = CubeState::solved(size);
+        cube.apply_sequence(sequence)?;
+        Ok(cube)
+    }
+
+    /// State of a cube after applying the scramble, `None` if the scramble isn't for a cube.
+    pub fn from_scramble(scramble: &Scramble) -> Option<Result<CubeState, ParseError>> {
+        let size = scramble
+            .kind
+            .cube_size()?;
+        Some(CubeState::from_sequence(size, &scramble.sequence))
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Position of the sticker and the direction it faces, in coordinates
+    // where layers are 2 units apart and the cube centre is the origin.
+    fn geometry(&self, index: usize) -> ([i32; 3], Face) {
+        let n = self.size;
+        let k = n as i32 - 1;
+        let face = Face::ALL[index / (n * n)];
+        let r = ((index % (n * n)) / n) as i32;
+        let c = (index % n) as i32;
+
+        let position = match face {
+            Face::U => [-k + 2 * c, k, -k + 2 * r],
+            Face::R => [k, k - 2 * r, k - 2 * c],
+            Face::F => [-k + 2 * c, k - 2 * r, k],
+            Face::D => [-k + 2 * c, -k, k - 2 * r],
+            Face::L => [-k, k - 2 * r, -k + 2 * c],
+            Face::B => [k - 2 * c, k - 2 * r, -k],
+        };
+        (position, face)
+    }
+
+    fn index_of(&self, position: [i32; 3], face: Face) -> usize {
+        let n = self.size;
+        let k = n as i32 - 1;
+        let [x, y, z] = position;
+        let (r, c) = match face {
+            Face::U => ((z + k) / 2, (x + k) / 2),
+            Face::R => ((k - y) / 2, (k - z) / 2),
+            Face::F => ((k - y) / 2, (x + k) / 2),
+            Face::D => ((k - z) / 2, (x + k) / 2),
+            Face::L => ((k - y) / 2, (z + k) / 2),
+            Face::B => ((k - y) / 2, (k - x) / 2),
+        };
+        face.index() * n * n + r as usize * n + c as usize
+    }
+
+    pub fn apply(&mut self, mv: &Move) {
+        let k = self.size as i32 - 1;
+        let axis = mv
+            .face
+            .axis();
+
+        for _ in 0..mv.turns {
+            let mut next = self
+                .facelets
+                .clone();
+            for (i, &facelet) in self
+                .facelets
+                .iter()
+                .enumerate()
+            {
+                let (position, face) = self.geometry(i);
+                let depth = ((k - dot(axis, position)) / 2 + 1) as usize;
+                if (mv.from..=mv.to).contains(&depth) {
+                    let target = self.index_of(
+                        rotate(axis, position),
+                        Face::from_axis(rotate(axis, face.axis())),
+                    );
+                    next[target] = facelet;
+                }
+            }
+            self.facelets = next;
+        }
+    }
+
+    pub fn apply_moves(&mut self, moves: &[Move]) {
+        for mv in moves {
+            self.apply(mv);
+        }
+    }
+
+    pub fn apply_sequence(&mut self, sequence: &str) -> Result<(), ParseError> {
+        let moves = parse_moves(sequence, self.size)?;
+        self.apply_moves(&moves);
+        Ok(())
+    }
+
+    /// Colour of every sticker, named after the face it belongs to on the solved cube.
+    pub fn colors(&self) -> Vec<Face> {
+        let n2 = self.size * self.size;
+        self.facelets
+            .iter()
+            .map(|&f| Face::ALL[f as usize / n2])
+            .collect()
+    }
+
+    /// The state which undoes this one, i.e. the state reached by the inverse sequence.
+    pub fn inverse(&self) -> CubeState {
+        let mut facelets = vec![
+            0;
+            self.facelets
+                .len()
+        ];
+        for (i, &f) in self
+            .facelets
+            .iter()
+            .enumerate()
+        {
+            facelets[f as usize] = i as u16;
+        }
+        CubeState {
+            size: self.size,
+            facelets,
+        }
+    }
+
+    /// Whether every face shows a single colour, regardless of the cube orientation.
+    pub fn is_solved(&self) -> bool {
+        let n2 = self.size * self.size;
+        self.colors()
+            .chunks(n2)
+            .all(|face| {
+                face.iter()
+                    .all(|&c| c == face[0])
+            })
+    }
+}
+
+/// Two cubes are equal when they show the same colours on every sticker.
+impl PartialEq for CubeState {
+    fn eq(&self, other: &CubeState) -> bool {
+        self.size == other.size && self.colors() == other.colors()
+    }
+}
+
+/// Facelet string of the cube (the 54-character Kociemba facelet string for a 3x3x3).
+impl fmt::Display for CubeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for color in self.colors() {
+            write!(f, "{}", color.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::scrambles::ScrambleKind;
+
+    const SOLVED_3X3: &str = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+
+    fn cube(sequence: &str) -> CubeState {
+        CubeState::from_sequence(3, sequence).unwrap()
+    }
+
+    #[test]
+    fn test_solved_facelet_string() {
+        assert_eq!(CubeState::solved(3).to_string(), SOLVED_3X3);
+    }
+
+    #[test]
+    fn test_face_turn_facelet_strings() {
+        assert_eq!(
+            cube("R").to_string(),
+            "UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB"
+        );
+        assert_eq!(
+            cube("U").to_string(),
+            "UUUUUUUUUBBBRRRRRRRRRFFFFFFDDDDDDDDDFFFLLLLLLLLLBBBBBB"
+        );
+    }
+
+    #[test]
+    fn test_slices_and_wide_moves() {
+        assert_eq!(cube("r"), cube("R M'"));
+        assert_eq!(cube("Rw"), cube("R M'"));
+        assert_eq!(cube("u"), cube("U E'"));
+        assert_eq!(cube("f"), cube("F S"));
+        assert_eq!(cube("x"), cube("R M' L'"));
+        assert_eq!(cube("y"), cube("U E' D'"));
+        assert_eq!(cube("z"), cube("F S B'"));
+    }
+
+    #[test]
+    fn test_rotations_keep_cube_solved() {
+        let rotated = cube("x y2 z'");
+
+        assert!(rotated.is_solved());
+        assert_ne!(rotated.to_string(), SOLVED_3X3);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let scramble = "R U R' U' F2 D L' B M E2 S'";
+        let state = cube(scramble);
+        let moves = parse_moves(scramble, 3).unwrap();
+
+        let mut undone = state.clone();
+        undone.apply_moves(&notation::invert(&moves));
+        assert!(undone.is_solved());
+
+        let mut inverse = CubeState::solved(3);
+        inverse.apply_moves(&notation::invert(&moves));
+        assert_eq!(state.inverse(), inverse);
+    }
+
+    #[test]
+    fn test_sexy_move_order() {
+        assert_eq!(cube(&"R U R' U' ".repeat(6)), CubeState::solved(3));
+        assert_ne!(cube(&"R U R' U' ".repeat(3)), CubeState::solved(3));
+    }
+
+    #[test]
+    fn test_big_cube_moves() {
+        let cube = CubeState::from_sequence(5, "3Rw 3R' Rw2 3Fw' 2L").unwrap();
+        let moves = parse_moves("3Rw 3R' Rw2 3Fw' 2L", 5).unwrap();
+        let mut undone = cube.clone();
+        undone.apply_moves(&notation::invert(&moves));
+
+        assert!(!cube.is_solved());
+        assert!(undone.is_solved());
+        assert!(CubeState::from_sequence(3, "4Rw").is_err());
+    }
+
+    #[test]
+    fn test_parse_error_offset() {
+        let error = parse_moves("R U  Q2 F", 3).unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::InvalidMove {
+                token: "Q2".to_owned(),
+                offset: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_scramble() {
+        let scramble = Scramble {
+            kind: ScrambleKind::Two,
+            sequence: "R U2 F'".to_owned(),
+            case: None,
+        };
+
+        assert!(CubeState::from_scramble(&scramble)
+            .unwrap()
+            .is_ok());
+    }
+}
@@ -0,0 +1,518 @@
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, to_bson, Document, Uuid},
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Collection,
+};
+
+use crate::{
+    error::AppError,
+    models::{
+        account::{Account, Role},
+        event::{Event, Registration, UpdateEventDto},
+    },
+    AppState,
+};
+
+use super::{get_collection, validation_services::field_error, Collections};
+
+/// Ids of the events the account was made a moderator of through its roles.
+fn moderated_event_ids(account: &Account) -> Vec<Uuid> {
+    account
+        .roles
+        .iter()
+        .filter_map(|role| match role {
+            Role::EventModerator(id) => Some(*id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Condition for an event to be seen by the account: public events are seen by everyone,
+/// private ones only by their creator, moderators and participants. Admins see every event.
+fn visible_to(account: &Account) -> Document {
+    if account.has_role(Role::Admin) {
+        return Document::new();
+    }
+
+    doc! { "$or": [
+        { "is_private": false },
+        { "creator_id": account.id },
+        { "moderators": account.id },
+        { "participants": account.id },
+        { "waitlist": account.id },
+        { "_id": { "$in": moderated_event_ids(account) } },
+    ] }
+}
+
+/// Whether the account may change the event, delete it and manage its registrations and
+/// moderators. Moderators of events created before roles were granted are only in the event.
+pub fn can_manage(account: &Account, event: &Event) -> bool {
+    account.has_role(Role::Admin)
+        || account.is_event_moderator(event.id)
+        || event
+            .moderators
+            .contains(&account.id)
+}
+
+/// Checks that registration to the event is open at `now`, in UNIX milliseconds.
+pub fn check_registration_window(event: &Event, now: i64) -> Result<(), &'static str> {
+    let window = &event.registration;
+    if let Some(opens) = window.registration_opens {
+        if now
+            < event
+                .date_timestamp
+                .saturating_sub(opens)
+        {
+            return Err("registration isn't open yet");
+        }
+    }
+    if now
+        >= event
+            .date_timestamp
+            .saturating_sub(window.registration_closes)
+    {
+        return Err("registration is closed");
+    }
+    Ok(())
+}
+
+/// Condition for an event to take one more participant.
+fn with_room() -> Document {
+    doc! { "$or": [
+        { "capacity": null },
+        { "$expr": { "$lt": [{ "$size": "$participants" }, "$capacity"] } },
+    ] }
+}
+
+/// Condition for the account not to be registered to an event in any way.
+fn not_registered(account_id: Uuid) -> Document {
+    doc! {
+        "participants": { "$ne": account_id },
+        "waitlist": { "$ne": account_id },
+        "pending": { "$ne": account_id },
+    }
+}
+
+pub async fn insert(state: &Arc<AppState>, event: Event) -> Result<InsertOneResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .insert_one(event)
+        .await?;
+
+    Ok(result)
+}
+
+/// A page of the events the account can see, starting at 1, earliest events first.
+pub async fn find_visible(
+    state: &Arc<AppState>,
+    account: &Account,
+    page: u64,
+    limit: u32,
+) -> Result<Vec<Event>, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .find(visible_to(account))
+        .sort(doc! { "date_timestamp": 1, "_id": 1 })
+        .skip((page - 1) * u64::from(limit))
+        .limit(i64::from(limit))
+        .await?
+        .try_collect()
+        .await?;
+
+    Ok(result)
+}
+
+/// The event, `None` when there is no such event or the account can't see it.
+pub async fn find_visible_by_id(
+    state: &Arc<AppState>,
+    account: &Account,
+    id: Uuid,
+) -> Result<Option<Event>, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .find_one(doc! { "$and": [{ "_id": id }, visible_to(account)] })
+        .await?;
+
+    Ok(result)
+}
+
+/// The event, whether the account can see it or not.
+pub async fn find_by_id(state: &Arc<AppState>, id: Uuid) -> Result<Option<Event>, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .find_one(doc! { "_id": id })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn update_by_id(
+    state: &Arc<AppState>,
+    id: Uuid,
+    update: &UpdateEventDto,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$set": {
+                "title": &update.title,
+                "description": &update.description,
+                "date_timestamp": update.date_timestamp,
+                "is_private": update.is_private,
+                "capacity": update.capacity,
+                "registration_opens": update.registration.registration_opens,
+                "registration_closes": update.registration.registration_closes,
+            } },
+        )
+        .await?;
+    // The capacity may have been raised.
+    promote(state, id).await?;
+
+    Ok(result)
+}
+
+/// Deletes the event along with the moderator roles on it.
+pub async fn delete_by_id(state: &Arc<AppState>, id: Uuid) -> Result<DeleteResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .delete_one(doc! { "_id": id })
+        .await?;
+
+    let role = to_bson(&Role::EventModerator(id))?;
+    let accounts: Collection<Account> = get_collection(state, Collections::ACCOUNTS);
+    accounts
+        .update_many(
+            doc! { "roles": &role },
+            doc! { "$pull": { "roles": &role } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+/// Makes the account a moderator of the event, in its roles and in the moderators of the event.
+/// Matches nothing when there is no such account.
+pub async fn grant_moderator(
+    state: &Arc<AppState>,
+    id: Uuid,
+    account_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let accounts: Collection<Account> = get_collection(state, Collections::ACCOUNTS);
+    let result = accounts
+        .update_one(
+            doc! { "_id": account_id },
+            doc! { "$addToSet": { "roles": to_bson(&Role::EventModerator(id))? } },
+        )
+        .await?;
+    if result.matched_count == 0 {
+        return Ok(result);
+    }
+
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$addToSet": { "moderators": account_id } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+/// Takes the moderator rights on the event from the account, unless it is the last moderator.
+/// Whether the account was a moderator.
+pub async fn revoke_moderator(
+    state: &Arc<AppState>,
+    id: Uuid,
+    account_id: Uuid,
+) -> Result<bool, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let event_result = events
+        .update_one(
+            doc! { "_id": id, "$or": [
+                { "moderators": { "$ne": account_id } },
+                { "moderators.1": { "$exists": true } },
+            ] },
+            doc! { "$pull": { "moderators": account_id } },
+        )
+        .await?;
+    if event_result.matched_count == 0 {
+        return Err(field_error(
+            "account_id",
+            "the event must keep at least one moderator",
+        ));
+    }
+
+    let accounts: Collection<Account> = get_collection(state, Collections::ACCOUNTS);
+    let account_result = accounts
+        .update_one(
+            doc! { "_id": account_id },
+            doc! { "$pull": { "roles": to_bson(&Role::EventModerator(id))? } },
+        )
+        .await?;
+
+    Ok(event_result.modified_count > 0 || account_result.modified_count > 0)
+}
+
+/// Registers the account to the event: as a participant while there is room, on the waitlist
+/// after that, and only pending approval to a private event unless `approved`. `None` when the
+/// account is already registered.
+pub async fn register(
+    state: &Arc<AppState>,
+    event: &Event,
+    account_id: Uuid,
+    approved: bool,
+) -> Result<Option<Registration>, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let filter = doc! { "$and": [{ "_id": event.id }, not_registered(account_id)] };
+
+    if event.is_private && !approved {
+        let result = events
+            .update_one(filter, doc! { "$push": { "pending": account_id } })
+            .await?;
+        return Ok((result.matched_count > 0).then_some(Registration::Pending));
+    }
+
+    let result = events
+        .update_one(
+            doc! { "$and": [filter.clone(), with_room()] },
+            doc! { "$push": { "participants": account_id } },
+        )
+        .await?;
+    if result.matched_count > 0 {
+        return Ok(Some(Registration::Participant));
+    }
+
+    let result = events
+        .update_one(filter, doc! { "$push": { "waitlist": account_id } })
+        .await?;
+    if result.matched_count == 0 {
+        return Ok(None);
+    }
+    // A place may have been freed since the event was found full.
+    promote(state, event.id).await?;
+    Ok(Some(Registration::Waitlisted))
+}
+
+/// Moves the accounts at the head of the waitlist to the participants while there is room.
+pub async fn promote(state: &Arc<AppState>, id: Uuid) -> Result<(), AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    while let Some(event) = events
+        .find_one(doc! { "_id": id })
+        .await?
+    {
+        let Some(&next) = event
+            .waitlist
+            .first()
+        else {
+            break;
+        };
+        if !event.has_room() {
+            break;
+        }
+        events
+            .update_one(
+                doc! { "$and": [{ "_id": id, "waitlist.0": next }, with_room()] },
+                doc! {
+                    "$pop": { "waitlist": -1 },
+                    "$push": { "participants": next },
+                },
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Removes the account from the participants, the waitlist and the pending registrations of the
+/// event, the waitlist moving up if it freed a place.
+pub async fn withdraw(
+    state: &Arc<AppState>,
+    id: Uuid,
+    account_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$pull": {
+                "participants": account_id,
+                "waitlist": account_id,
+                "pending": account_id,
+            } },
+        )
+        .await?;
+    if result.modified_count > 0 {
+        promote(state, id).await?;
+    }
+
+    Ok(result)
+}
+
+/// Approves a pending registration, making the account a participant or putting it on the
+/// waitlist when the event is full. `None` when the account wasn't pending approval.
+pub async fn approve(
+    state: &Arc<AppState>,
+    id: Uuid,
+    account_id: Uuid,
+) -> Result<Option<Registration>, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let filter = doc! { "_id": id, "pending": account_id };
+
+    let result = events
+        .update_one(
+            doc! { "$and": [filter.clone(), with_room()] },
+            doc! {
+                "$pull": { "pending": account_id },
+                "$push": { "participants": account_id },
+            },
+        )
+        .await?;
+    if result.matched_count > 0 {
+        return Ok(Some(Registration::Participant));
+    }
+
+    let result = events
+        .update_one(
+            filter,
+            doc! {
+                "$pull": { "pending": account_id },
+                "$push": { "waitlist": account_id },
+            },
+        )
+        .await?;
+    if result.matched_count == 0 {
+        return Ok(None);
+    }
+    promote(state, id).await?;
+    Ok(Some(Registration::Waitlisted))
+}
+
+pub async fn reject(
+    state: &Arc<AppState>,
+    id: Uuid,
+    account_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$pull": { "pending": account_id } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Bson;
+
+    use super::*;
+    use crate::models::event::RegistrationWindow;
+
+    const DATE: i64 = 1_700_000_000_000;
+    const HOUR: i64 = 3_600_000;
+
+    fn account(roles: &[Role]) -> Account {
+        Account::new("user", "hash", roles)
+    }
+
+    fn event(creator: &Account, is_private: bool) -> Event {
+        Event::new("Open", "", DATE, creator.id, is_private)
+    }
+
+    #[test]
+    fn test_can_manage() {
+        let creator = account(&[Role::User]);
+        let event = event(&creator, false);
+
+        assert!(can_manage(&creator, &event));
+        assert!(can_manage(&account(&[Role::Admin]), &event));
+        assert!(can_manage(
+            &account(&[Role::User, Role::EventModerator(event.id)]),
+            &event
+        ));
+
+        let participant = account(&[Role::User]);
+        let mut joined = event.clone();
+        joined
+            .participants
+            .push(participant.id);
+        assert!(!can_manage(&participant, &joined));
+
+        let other = self::event(&creator, false);
+        assert!(!can_manage(
+            &account(&[Role::EventModerator(other.id)]),
+            &event
+        ));
+    }
+
+    #[test]
+    fn test_moderator_role() {
+        // Roles are pulled from accounts by their exact BSON value.
+        let id = Uuid::new();
+        assert_eq!(
+            to_bson(&Role::EventModerator(id)).unwrap(),
+            Bson::Document(doc! { "EventModerator": id })
+        );
+    }
+
+    #[test]
+    fn test_visible_to() {
+        assert_eq!(visible_to(&account(&[Role::Admin])), doc! {});
+
+        let moderated = event(&account(&[Role::User]), true);
+        let user = account(&[Role::User, Role::EventModerator(moderated.id)]);
+        let filter = visible_to(&user);
+        let conditions = filter
+            .get_array("$or")
+            .unwrap();
+
+        assert_eq!(conditions.len(), 6);
+        assert!(conditions.contains(&Bson::Document(doc! { "is_private": false })));
+        assert!(conditions.contains(&Bson::Document(doc! { "participants": user.id })));
+        assert!(conditions.contains(&Bson::Document(doc! { "_id": { "$in": [moderated.id] } })));
+    }
+
+    #[test]
+    fn test_check_registration_window() {
+        let mut event = event(&account(&[Role::User]), false);
+        assert!(check_registration_window(&event, 0).is_ok());
+        assert!(check_registration_window(&event, DATE - 1).is_ok());
+        assert!(check_registration_window(&event, DATE).is_err());
+
+        event.registration = RegistrationWindow {
+            registration_opens: Some(24 * HOUR),
+            registration_closes: HOUR,
+        };
+        assert!(check_registration_window(&event, DATE - 24 * HOUR - 1).is_err());
+        assert!(check_registration_window(&event, DATE - 24 * HOUR).is_ok());
+        assert!(check_registration_window(&event, DATE - HOUR - 1).is_ok());
+        assert!(check_registration_window(&event, DATE - HOUR).is_err());
+
+        event.date_timestamp = i64::MIN;
+        assert!(check_registration_window(&event, i64::MIN).is_err());
+    }
+
+    #[test]
+    fn test_has_room() {
+        let mut event = event(&account(&[Role::User]), false);
+        event.capacity = Some(2);
+
+        event
+            .participants
+            .push(Uuid::new());
+        assert!(event.has_room());
+        event
+            .participants
+            .push(Uuid::new());
+        assert!(!event.has_room());
+        event.capacity = None;
+        assert!(event.has_room());
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::{Cursor, Write};
+
+use chrono::SecondsFormat;
+use serde::Serialize;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    error::AppError,
+    models::session::{Penalty, Session, Time},
+    routes::sessions::ExportFormat,
+};
+
+use super::utils::time_utils::UtcFromMillis;
+
+const CSV_HEADER: &str = "solve,time,penalty,scramble,recorded_at,comment\r\n";
+
+/// A time as exported, `time` being the recorded milliseconds without the penalty.
+#[derive(Debug, Serialize, PartialEq)]
+struct ExportRow {
+    solve: usize,
+    time: u64,
+    penalty: Penalty,
+    scramble: String,
+    recorded_at: String,
+    comment: Option<String>,
+}
+
+impl ExportRow {
+    /// The row of the `index`th time of a session, numbered from 1.
+    fn new(index: usize, time: Time) -> ExportRow {
+        ExportRow {
+            solve: index + 1,
+            time: time.millis,
+            penalty: time.penalty,
+            scramble: time
+                .scramble
+                .sequence,
+            recorded_at: time
+                .recorded_at
+                .utc_from_millis()
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            comment: time.comment,
+        }
+    }
+
+    fn csv_line(&self) -> String {
+        let penalty = match self.penalty {
+            Penalty::None => "None",
+            Penalty::PlusTwo => "PlusTwo",
+            Penalty::Dnf => "Dnf",
+        };
+        format!(
+            "{},{},{},{},{},{}\r\n",
+            self.solve,
+            self.time,
+            penalty,
+            csv_field(&self.scramble),
+            self.recorded_at,
+            csv_field(
+                self.comment
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        )
+    }
+}
+
+/// Quotes a field which holds a separator, a quote or a line break, as RFC 4180 does.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+/// The export of the times of a session, cut in chunks of one row each so that it can be
+/// streamed. Chunks joined together make a CSV file with a header, or a JSON array of rows.
+pub fn export_chunks(
+    format: ExportFormat,
+    times: Vec<Time>,
+) -> impl Iterator<Item = String> + Send + 'static {
+    let rows = times
+        .into_iter()
+        .enumerate()
+        .map(|(index, time)| ExportRow::new(index, time));
+
+    let (first, last) = match format {
+        ExportFormat::Csv => (CSV_HEADER, ""),
+        ExportFormat::Json => ("[", "]"),
+    };
+    std::iter::once(first.to_owned())
+        .chain(rows.map(move |row| match format {
+            ExportFormat::Csv => row.csv_line(),
+            ExportFormat::Json => {
+                let json = serde_json::to_string(&row).expect("a row always serializes");
+                if row.solve == 1 {
+                    json
+                } else {
+                    format!(",{}", json)
+                }
+            }
+        }))
+        .chain(std::iter::once(last.to_owned()))
+}
+
+/// Name of the file of a session in a zip export, the session id keeping names unique.
+fn file_name(session: &Session, format: ExportFormat) -> String {
+    let name: String = session
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}.{}", name, session.id, format.extension())
+}
+
+/// A zip archive holding the export of every session, one file each.
+pub fn export_zip(format: ExportFormat, sessions: Vec<Session>) -> Result<Vec<u8>, AppError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    for session in sessions {
+        zip.start_file(file_name(&session, format), options)?;
+        for chunk in export_chunks(format, session.times) {
+            zip.write_all(chunk.as_bytes())?;
+        }
+    }
+
+    Ok(zip
+        .finish()?
+        .into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use mongodb::bson::Uuid;
+    use serde_json::{json, Value};
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::routes::scrambles::{Scramble, ScrambleKind};
+
+    fn times() -> Vec<Time> {
+        let time = |millis, sequence: &str, penalty, comment: Option<&str>| Time {
+            id: Uuid::new(),
+            millis,
+            recorded_at: 1700000000123,
+            scramble: Scramble {
+                kind: ScrambleKind::Three,
+                sequence: sequence.to_owned(),
+                case: None,
+            },
+            penalty,
+            comment: comment.map(str::to_owned),
+            splits: Vec::new(),
+            solution: None,
+        };
+        vec![
+            time(12345, "R U R' U'", Penalty::None, None),
+            time(
+                9870,
+                "[R, U] // \"sexy\"",
+                Penalty::PlusTwo,
+                Some("lucky, again"),
+            ),
+        ]
+    }
+
+    fn export(format: ExportFormat, times: Vec<Time>) -> String {
+        export_chunks(format, times).collect()
+    }
+
+    #[test]
+    fn test_csv_export() {
+        assert_eq!(
+            export(ExportFormat::Csv, times()),
+            "solve,time,penalty,scramble,recorded_at,comment\r\n\
+             1,12345,None,R U R' U',2023-11-14T22:13:20.123Z,\r\n\
+             2,9870,PlusTwo,\"[R, U] // \"\"sexy\"\"\",2023-11-14T22:13:20.123Z,\"lucky, again\"\r\n"
+        );
+        assert_eq!(export(ExportFormat::Csv, Vec::new()), CSV_HEADER);
+    }
+
+    #[test]
+    fn test_json_export() {
+        let exported: Value = serde_json::from_str(&export(ExportFormat::Json, times())).unwrap();
+        assert_eq!(
+            exported,
+            json!([
+                {
+                    "solve": 1,
+                    "time": 12345,
+                    "penalty": "None",
+                    "scramble": "R U R' U'",
+                    "recorded_at": "2023-11-14T22:13:20.123Z",
+                    "comment": null,
+                },
+                {
+                    "solve": 2,
+                    "time": 9870,
+                    "penalty": "PlusTwo",
+                    "scramble": "[R, U] // \"sexy\"",
+                    "recorded_at": "2023-11-14T22:13:20.123Z",
+                    "comment": "lucky, again",
+                },
+            ])
+        );
+        assert_eq!(export(ExportFormat::Json, Vec::new()), "[]");
+    }
+
+    #[test]
+    fn test_zip_export() {
+        let account_id = Uuid::new();
+        let sessions = vec![
+            Session::new(account_id, "3x3/OH", &times()),
+            Session::new(account_id, "Empty", &[]),
+        ];
+        let names: Vec<String> = sessions
+            .iter()
+            .map(|session| file_name(session, ExportFormat::Csv))
+            .collect();
+        assert!(names[0].starts_with("3x3_OH-"));
+
+        let zip = export_zip(ExportFormat::Csv, sessions).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 2);
+
+        let mut content = String::new();
+        archive
+            .by_name(&names[0])
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, export(ExportFormat::Csv, times()));
+    }
+}
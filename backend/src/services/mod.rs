@@ -5,10 +5,20 @@ use mongodb::Collection;
 use crate::AppState;
 
 pub mod account_services;
+pub mod alg_services;
 pub mod auth_services;
+pub mod cstimer_services;
+pub mod cube_state;
+pub mod event_services;
+pub mod export_services;
 pub mod jwt_services;
+pub mod notation;
+pub mod preview_services;
+pub mod round_services;
 pub mod scramble_services;
 pub mod session_services;
+pub mod solution_services;
+pub mod stats_services;
 pub mod utils;
 pub mod validation_services;
 
@@ -16,6 +26,7 @@ pub struct Collections;
 
 impl Collections {
     pub const ACCOUNTS: &'static str = "accounts";
+    pub const EVENTS: &'static str = "events";
     pub const REFRESH_TOKENS: &'static str = "refresh_tokens";
     pub const SESSIONS: &'static str = "sessions";
 }
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use super::{Invert, Notation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pin {
+    UL,
+    UR,
+    DL,
+    DR,
+}
+
+impl Pin {
+    const ALL: [Pin; 4] = [Pin::UL, Pin::UR, Pin::DL, Pin::DR];
+}
+
+/// The pins pushed up for a dial turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockPins {
+    UL,
+    UR,
+    DL,
+    DR,
+    U,
+    D,
+    L,
+    R,
+    All,
+}
+
+impl ClockPins {
+    const ALL: [ClockPins; 9] = [
+        ClockPins::UL,
+        ClockPins::UR,
+        ClockPins::DL,
+        ClockPins::DR,
+        ClockPins::U,
+        ClockPins::D,
+        ClockPins::L,
+        ClockPins::R,
+        ClockPins::All,
+    ];
+
+    pub fn pins(self) -> &'static [Pin] {
+        match self {
+            ClockPins::UL => &[Pin::UL],
+            ClockPins::UR => &[Pin::UR],
+            ClockPins::DL => &[Pin::DL],
+            ClockPins::DR => &[Pin::DR],
+            ClockPins::U => &[Pin::UL, Pin::UR],
+            ClockPins::D => &[Pin::DL, Pin::DR],
+            ClockPins::L => &[Pin::UL, Pin::DL],
+            ClockPins::R => &[Pin::UR, Pin::DR],
+            ClockPins::All => &Pin::ALL,
+        }
+    }
+
+    fn name(self) -> String {
+        format!("{:?}", self).to_uppercase()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockMove {
+    /// `UR3+`, the dials next to the pins turned by `amount` hours, negative amounts are
+    /// written with a `-` and turn counterclockwise.
+    Turn { pins: ClockPins, amount: i32 },
+    /// `y2`, turning the puzzle over.
+    Flip,
+    /// A pin left up at the end of the scramble, `UR`.
+    PinUp(Pin),
+}
+
+impl Invert for ClockMove {
+    fn inverse(&self) -> ClockMove {
+        match *self {
+            ClockMove::Turn { pins, amount } => ClockMove::Turn {
+                pins,
+                amount: -amount,
+            },
+            mv => mv,
+        }
+    }
+}
+
+impl fmt::Display for ClockMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockMove::Turn { pins, amount } => {
+                let sign = if *amount < 0 { '-' } else { '+' };
+                write!(f, "{}{}{}", pins.name(), amount.unsigned_abs(), sign)
+            }
+            ClockMove::Flip => write!(f, "y2"),
+            ClockMove::PinUp(pin) => write!(f, "{:?}", pin),
+        }
+    }
+}
+
+pub struct ClockNotation;
+
+impl Notation for ClockNotation {
+    type Move = ClockMove;
+
+    fn parse_move(&self, input: &str) -> Option<(ClockMove, usize)> {
+        if input.starts_with("y2") {
+            return Some((ClockMove::Flip, 2));
+        }
+
+        let letters = input
+            .find(|c: char| !c.is_ascii_uppercase())
+            .unwrap_or(input.len());
+        let name = &input[..letters];
+        let rest = &input[letters..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        if digits == 0 {
+            let pin = Pin::ALL
+                .into_iter()
+                .find(|pin| format!("{:?}", pin) == name)?;
+            return Some((ClockMove::PinUp(pin), letters));
+        }
+
+        let pins = ClockPins::ALL
+            .into_iter()
+            .find(|pins| pins.name() == name)?;
+        let hours: i32 = rest[..digits]
+            .parse()
+            .ok()?;
+        let amount = match rest[digits..]
+            .chars()
+            .next()?
+        {
+            '+' => hours,
+            '-' => -hours,
+            _ => return None,
+        };
+        Some((ClockMove::Turn { pins, amount }, letters + digits + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::notation::parse_moves;
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&ClockNotation, "UR3+ ALL6- y2 DL0+ UR").unwrap();
+
+        assert_eq!(
+            moves,
+            vec![
+                ClockMove::Turn {
+                    pins: ClockPins::UR,
+                    amount: 3
+                },
+                ClockMove::Turn {
+                    pins: ClockPins::All,
+                    amount: -6
+                },
+                ClockMove::Flip,
+                ClockMove::Turn {
+                    pins: ClockPins::DL,
+                    amount: 0
+                },
+                ClockMove::PinUp(Pin::UR),
+            ]
+        );
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "UR3+ ALL6- y2 DL0+ UR"
+        );
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        for sequence in ["UR3", "X3+", "R", "U R"] {
+            assert!(
+                parse_moves(&ClockNotation, sequence).is_err(),
+                "{}",
+                sequence
+            );
+        }
+    }
+}
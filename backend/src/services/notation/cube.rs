@@ -0,0 +1,565 @@
+use std::{collections::VecDeque, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::cube_state::{Face, Move};
+
+use super::{parse_amount, write_amount, Invert, Notation};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Slice {
+    M,
+    E,
+    S,
+}
+
+impl Slice {
+    /// The face whose direction the slice turns in.
+    pub fn face(self) -> Face {
+        match self {
+            Slice::M => Face::L,
+            Slice::E => Face::D,
+            Slice::S => Face::F,
+        }
+    }
+
+    /// The slice turning with `face`, and whether it turns the opposite way.
+    fn from_face(face: Face) -> (Slice, bool) {
+        let slice = [Slice::M, Slice::E, Slice::S]
+            .into_iter()
+            .find(|slice| slice.face() == face || slice.face() == face.opposite())
+            .expect("every face is next to a slice");
+        (slice, slice.face() != face)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    X,
+    Y,
+    Z,
+}
+
+impl Rotation {
+    /// The face whose direction the whole cube turns in.
+    pub fn face(self) -> Face {
+        match self {
+            Rotation::X => Face::R,
+            Rotation::Y => Face::U,
+            Rotation::Z => Face::F,
+        }
+    }
+
+    /// The rotation turning with `face`, and whether it turns the opposite way.
+    fn from_face(face: Face) -> (Rotation, bool) {
+        let rotation = [Rotation::X, Rotation::Y, Rotation::Z]
+            .into_iter()
+            .find(|rotation| rotation.face() == face || rotation.face() == face.opposite())
+            .expect("every face is on an axis");
+        (rotation, rotation.face() != face)
+    }
+}
+
+/// How the faces of moves are relabelled after turning the whole cube, one of its 24
+/// orientations. However many rotations it is made of, it is applied as a single mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Orientation([Face; 6]);
+
+impl Orientation {
+    pub const IDENTITY: Orientation = Orientation(Face::ALL);
+
+    /// The orientation relabelling moves as this one does, then as `rotated` does for the
+    /// cube turned by `rotation` `turns` times.
+    pub fn then(self, rotation: Rotation, turns: i32) -> Orientation {
+        Orientation(
+            self.0
+                .map(|face| face.turned(rotation.face(), (-turns).rem_euclid(4) as u8)),
+        )
+    }
+
+    /// The face a move turning `face` turns once relabelled.
+    pub fn face(self, face: Face) -> Face {
+        self.0[face.index()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeTurn {
+    /// A single layer counted from the face starting at 1, `R` or `3R`.
+    Layer(Face, usize),
+    /// The given number of outer layers, `Rw`, `r` or `3Rw`.
+    Wide(Face, usize),
+    /// Every layer between two opposite faces, `M`, `E` or `S`.
+    Slice(Slice),
+    /// The whole cube, `x`, `y` or `z`.
+    Rotation(Rotation),
+}
+
+/// A turn by `amount` clockwise quarter turns, negative amounts are counterclockwise.
+/// Amounts are kept as written, so `R2'` stays apart from `R2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubeMove {
+    pub turn: CubeTurn,
+    pub amount: i32,
+}
+
+impl CubeMove {
+    /// The layers the move turns on a cube of the given size.
+    pub fn layers(&self, size: usize) -> Move {
+        let (from, to) = match self.turn {
+            CubeTurn::Layer(_, layer) => (layer, layer),
+            CubeTurn::Wide(_, width) => (1, width),
+            CubeTurn::Slice(_) => (2, size - 1),
+            CubeTurn::Rotation(_) => (1, size),
+        };
+        Move {
+            face: self.face(),
+            from,
+            to,
+            turns: self
+                .amount
+                .rem_euclid(4) as u8,
+        }
+    }
+
+    /// The face whose direction the move turns in.
+    pub fn face(&self) -> Face {
+        match self.turn {
+            CubeTurn::Layer(face, _) | CubeTurn::Wide(face, _) => face,
+            CubeTurn::Slice(slice) => slice.face(),
+            CubeTurn::Rotation(rotation) => rotation.face(),
+        }
+    }
+
+    fn with_face(&self, face: Face, amount: i32) -> CubeMove {
+        let (turn, flipped) = match self.turn {
+            CubeTurn::Layer(_, layer) => (CubeTurn::Layer(face, layer), false),
+            CubeTurn::Wide(_, width) => (CubeTurn::Wide(face, width), false),
+            CubeTurn::Slice(_) => {
+                let (slice, flipped) = Slice::from_face(face);
+                (CubeTurn::Slice(slice), flipped)
+            }
+            CubeTurn::Rotation(_) => {
+                let (rotation, flipped) = Rotation::from_face(face);
+                (CubeTurn::Rotation(rotation), flipped)
+            }
+        };
+        CubeMove {
+            turn,
+            amount: if flipped { -amount } else { amount },
+        }
+    }
+
+    /// The move seen in a mirror standing on the plane of `plane`, e.g. `R` becomes `L'`
+    /// and `U` becomes `U'` in the M plane.
+    pub fn mirrored(&self, plane: Slice) -> CubeMove {
+        let normal = plane.face();
+        let face = match self.face() {
+            face if face == normal || face == normal.opposite() => face.opposite(),
+            face => face,
+        };
+        self.with_face(face, -self.amount)
+    }
+
+    /// The move to make instead of this one after the cube was turned by `rotation`
+    /// `turns` times, so that `[y: R]` is the same as `B`.
+    pub fn rotated(&self, rotation: Rotation, turns: i32) -> CubeMove {
+        self.oriented(Orientation::IDENTITY.then(rotation, turns))
+    }
+
+    /// The move relabelled by `orientation`.
+    pub fn oriented(&self, orientation: Orientation) -> CubeMove {
+        self.with_face(orientation.face(self.face()), self.amount)
+    }
+
+    fn axis(&self) -> usize {
+        self.face()
+            .index()
+            % 3
+    }
+}
+
+/// Merges moves of the same layers and drops the ones adding up to nothing, looking past
+/// moves on the same axis as those commute. Amounts end up as 1, 2 or -1.
+pub fn simplify(moves: Vec<(usize, CubeMove)>) -> Vec<(usize, CubeMove)> {
+    let normalize = |amount: i32| match amount.rem_euclid(4) {
+        3 => -1,
+        amount => amount,
+    };
+    let mut result: Vec<(usize, CubeMove)> = Vec::with_capacity(moves.len());
+
+    for (offset, mv) in moves {
+        let same_layers = result
+            .iter()
+            .rev()
+            .take_while(|(_, previous)| previous.axis() == mv.axis())
+            .position(|(_, previous)| previous.turn == mv.turn)
+            .map(|back| result.len() - 1 - back);
+
+        match same_layers {
+            Some(i) => {
+                let amount = normalize(
+                    result[i]
+                        .1
+                        .amount
+                        + mv.amount,
+                );
+                if amount == 0 {
+                    result.remove(i);
+                } else {
+                    result[i]
+                        .1
+                        .amount = amount;
+                }
+            }
+            None => {
+                let amount = normalize(mv.amount);
+                if amount != 0 {
+                    result.push((offset, CubeMove { amount, ..mv }));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Rewrites a 3x3x3 sequence with outer face turns only, as seen relative to the centres.
+/// Inner layers become a rotation and face turns, and rotations just relabel the moves
+/// after them, so the net rotation of the sequence is dropped.
+pub fn face_turns(moves: &[CubeMove]) -> Vec<(Face, u8)> {
+    // Rotations made so far, the first one applying first.
+    let mut rotations: VecDeque<(Rotation, i32)> = VecDeque::new();
+    let mut turns = Vec::new();
+
+    for mv in moves {
+        // After a rotation `r`, the move is `[r: mv] r`, and `[r: mv]` has no rotation.
+        let mv = rotations
+            .iter()
+            .rev()
+            .fold(*mv, |mv, &(rotation, amount)| mv.rotated(rotation, amount));
+        let Move {
+            face,
+            from,
+            to,
+            turns: quarters,
+        } = mv.layers(3);
+        if quarters == 0 {
+            continue;
+        }
+        let layers = from..=to;
+        let back = (4 - quarters) % 4;
+
+        // Turning the middle layer is turning the whole cube, and the other layers back.
+        if layers.contains(&2) {
+            let (rotation, flipped) = Rotation::from_face(face);
+            let amount = quarters as i32;
+            rotations.push_front((rotation, if flipped { -amount } else { amount }));
+            if !layers.contains(&1) {
+                turns.push((face, back));
+            }
+            if !layers.contains(&3) {
+                turns.push((face.opposite(), quarters));
+            }
+        } else {
+            if layers.contains(&1) {
+                turns.push((face, quarters));
+            }
+            if layers.contains(&3) {
+                turns.push((face.opposite(), back));
+            }
+        }
+    }
+    turns
+}
+
+impl Invert for CubeMove {
+    fn inverse(&self) -> CubeMove {
+        CubeMove {
+            amount: -self.amount,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for CubeMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.turn {
+            CubeTurn::Layer(face, 1) => write!(f, "{}", face.to_char())?,
+            CubeTurn::Layer(face, layer) => write!(f, "{}{}", layer, face.to_char())?,
+            CubeTurn::Wide(face, 2) => write!(f, "{}w", face.to_char())?,
+            CubeTurn::Wide(face, width) => write!(f, "{}{}w", width, face.to_char())?,
+            CubeTurn::Slice(slice) => write!(f, "{:?}", slice)?,
+            CubeTurn::Rotation(Rotation::X) => write!(f, "x")?,
+            CubeTurn::Rotation(Rotation::Y) => write!(f, "y")?,
+            CubeTurn::Rotation(Rotation::Z) => write!(f, "z")?,
+        }
+        write_amount(f, self.amount)
+    }
+}
+
+/// Outer block turn notation for a cube of the given size.
+pub struct CubeNotation {
+    pub size: usize,
+}
+
+impl Notation for CubeNotation {
+    type Move = CubeMove;
+
+    fn parse_move(&self, input: &str) -> Option<(CubeMove, usize)> {
+        let digits = input
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len());
+        let prefix: Option<usize> = match digits {
+            0 => None,
+            _ => Some(
+                input[..digits]
+                    .parse()
+                    .ok()?,
+            ),
+        };
+
+        let letter = input[digits..]
+            .chars()
+            .next()?;
+        let mut end = digits + letter.len_utf8();
+        let wide = input[end..].starts_with('w');
+
+        let turn = match (letter, Face::from_char(letter), prefix, wide) {
+            (_, Some(face), None, false) => CubeTurn::Layer(face, 1),
+            (_, Some(face), Some(layer), false) => CubeTurn::Layer(face, layer),
+            (_, Some(face), width, true) => {
+                end += 1;
+                CubeTurn::Wide(face, width.unwrap_or(2))
+            }
+            ('u' | 'r' | 'f' | 'd' | 'l' | 'b', _, None, false) => {
+                CubeTurn::Wide(Face::from_char(letter.to_ascii_uppercase())?, 2)
+            }
+            ('M', _, None, false) => CubeTurn::Slice(Slice::M),
+            ('E', _, None, false) => CubeTurn::Slice(Slice::E),
+            ('S', _, None, false) => CubeTurn::Slice(Slice::S),
+            ('x', _, None, false) => CubeTurn::Rotation(Rotation::X),
+            ('y', _, None, false) => CubeTurn::Rotation(Rotation::Y),
+            ('z', _, None, false) => CubeTurn::Rotation(Rotation::Z),
+            _ => return None,
+        };
+
+        let (amount, length) = parse_amount(&input[end..])?;
+        let mv = CubeMove { turn, amount };
+        let Move { from, to, .. } = mv.layers(self.size);
+        if from == 0 || from > to || to > self.size {
+            return None;
+        }
+
+        Some((mv, end + length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::cube_state::CubeState;
+    use crate::services::notation::{format, parse, parse_moves, Node, ParseError};
+
+    const THREE: CubeNotation = CubeNotation { size: 3 };
+
+    fn cube_move(turn: CubeTurn, amount: i32) -> CubeMove {
+        CubeMove { turn, amount }
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&THREE, "R U2' r M' x2 2L Fw").unwrap();
+
+        assert_eq!(
+            moves,
+            vec![
+                cube_move(CubeTurn::Layer(Face::R, 1), 1),
+                cube_move(CubeTurn::Layer(Face::U, 1), -2),
+                cube_move(CubeTurn::Wide(Face::R, 2), 1),
+                cube_move(CubeTurn::Slice(Slice::M), -1),
+                cube_move(CubeTurn::Rotation(Rotation::X), 2),
+                cube_move(CubeTurn::Layer(Face::L, 2), 1),
+                cube_move(CubeTurn::Wide(Face::F, 2), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moves_without_spaces() {
+        assert_eq!(
+            parse_moves(&THREE, "RU'R'").unwrap(),
+            parse_moves(&THREE, "R U' R'").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layer_counts_depend_on_size() {
+        let five = CubeNotation { size: 5 };
+
+        assert_eq!(
+            parse_moves(&five, "3Rw'").unwrap(),
+            vec![cube_move(CubeTurn::Wide(Face::R, 3), -1)]
+        );
+        assert!(parse_moves(&five, "5Rw").is_ok());
+        assert!(parse_moves(&five, "6Rw").is_err());
+        assert!(parse_moves(&THREE, "4R").is_err());
+        assert!(parse_moves(&CubeNotation { size: 2 }, "M").is_err());
+    }
+
+    #[test]
+    fn test_brackets_and_repetitions() {
+        let nodes = parse(&THREE, "[R, U] [F: R2] (R U)3 (R U)'").unwrap();
+
+        assert!(matches!(nodes[0], Node::Commutator { amount: 1, .. }));
+        assert!(matches!(nodes[1], Node::Conjugate { amount: 1, .. }));
+        assert!(matches!(nodes[2], Node::Group { amount: 3, .. }));
+        assert!(matches!(nodes[3], Node::Group { amount: -1, .. }));
+
+        let expanded = |sequence| {
+            parse_moves(&THREE, sequence)
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(expanded("[R, U]"), "R U R' U'");
+        assert_eq!(expanded("[F: [R, U]]"), "F R U R' U' F'");
+        assert_eq!(expanded("[R, U]'"), "U R U' R'");
+        assert_eq!(expanded("(R U2')2"), "R U2' R U2'");
+        assert_eq!(expanded("(R U)2'"), "U' R' U' R'");
+    }
+
+    #[test]
+    fn test_comments() {
+        let sequence = "y2 // inspection\nR U R' // pair\n";
+
+        assert_eq!(
+            parse_moves(&THREE, sequence).unwrap(),
+            parse_moves(&THREE, "y2 R U R'").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let sequence = "[R, U]2 [Rw: (U' 3R2)'] x y' M2 (R U)0";
+        let five = CubeNotation { size: 5 };
+
+        assert_eq!(format(&parse(&five, sequence).unwrap()), sequence);
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let cases = [
+            (
+                "R U  Q2 F",
+                ParseError::InvalidMove {
+                    token: "Q2".to_owned(),
+                    offset: 5,
+                },
+            ),
+            (
+                "R (U R",
+                ParseError::Unclosed {
+                    bracket: '(',
+                    offset: 2,
+                },
+            ),
+            (
+                "[R U]",
+                ParseError::Unexpected {
+                    expected: "`,` or `:`",
+                    found: ']',
+                    offset: 4,
+                },
+            ),
+            (
+                "R U)",
+                ParseError::Unexpected {
+                    expected: "a move",
+                    found: ')',
+                    offset: 3,
+                },
+            ),
+            // Offsets count characters, so the multi-byte quote only counts once.
+            (
+                "R’ U",
+                ParseError::InvalidMove {
+                    token: "’".to_owned(),
+                    offset: 1,
+                },
+            ),
+            (
+                "R ’ U",
+                ParseError::InvalidMove {
+                    token: "’".to_owned(),
+                    offset: 2,
+                },
+            ),
+        ];
+
+        for (sequence, error) in cases {
+            assert_eq!(parse(&THREE, sequence).unwrap_err(), error, "{}", sequence);
+        }
+        assert_eq!(
+            parse(&THREE, "U ’ R")
+                .unwrap_err()
+                .to_string(),
+            "invalid move `’` at offset 2"
+        );
+    }
+
+    #[test]
+    fn test_expansion_limits() {
+        assert!(matches!(
+            parse(&THREE, "((((R U)10)10)10)10"),
+            Err(ParseError::TooLong { .. })
+        ));
+        assert!(matches!(
+            parse(&THREE, "(R)99999999999"),
+            Err(ParseError::InvalidMove { .. })
+        ));
+        assert!(matches!(
+            parse(&THREE, &"(".repeat(100)),
+            Err(ParseError::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn test_face_turns() {
+        let turns = |sequence| {
+            let moves = parse_moves(&THREE, sequence).unwrap();
+            face_turns(&moves)
+                .into_iter()
+                .map(|(face, quarters)| format!("{}{}", face.to_char(), quarters))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        assert_eq!(turns("R U2 F'"), "R1 U2 F3");
+        assert_eq!(turns("x R y' U"), "R1 F1");
+        assert_eq!(turns("r U"), "L1 F1");
+        assert_eq!(turns("M2 U"), "L2 R2 D1");
+
+        // Undoing the face turns before the sequence leaves the cube solved, up to a rotation.
+        for sequence in ["r U r' F M' E2 S x z' L", "x R' U R' D2 R U' R' D2 R2 x'"] {
+            let moves = parse_moves(&THREE, sequence).unwrap();
+            let mut state = CubeState::solved(3);
+            for (face, quarters) in face_turns(&moves)
+                .into_iter()
+                .rev()
+            {
+                state.apply(&Move {
+                    face,
+                    from: 1,
+                    to: 1,
+                    turns: (4 - quarters) % 4,
+                });
+            }
+            state
+                .apply_sequence(sequence)
+                .unwrap();
+            assert!(state.is_solved(), "{}", sequence);
+        }
+    }
+}
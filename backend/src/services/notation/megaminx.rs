@@ -0,0 +1,92 @@
+use std::fmt;
+
+use super::{Invert, Notation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MegaminxAxis {
+    R,
+    D,
+    U,
+}
+
+/// A move in Pochmann notation: `R++` and `D++` turn everything but the opposite layer by
+/// two fifths, `U` turns the U face by one fifth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MegaminxMove {
+    pub axis: MegaminxAxis,
+    pub prime: bool,
+}
+
+impl Invert for MegaminxMove {
+    fn inverse(&self) -> MegaminxMove {
+        MegaminxMove {
+            prime: !self.prime,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for MegaminxMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match (self.axis, self.prime) {
+            (MegaminxAxis::U, false) => "",
+            (MegaminxAxis::U, true) => "'",
+            (_, false) => "++",
+            (_, true) => "--",
+        };
+        write!(f, "{:?}{}", self.axis, suffix)
+    }
+}
+
+pub struct MegaminxNotation;
+
+impl Notation for MegaminxNotation {
+    type Move = MegaminxMove;
+
+    fn parse_move(&self, input: &str) -> Option<(MegaminxMove, usize)> {
+        let (axis, prime, length) = if let Some(rest) = input.strip_prefix('U') {
+            let prime = rest.starts_with('\'');
+            (MegaminxAxis::U, prime, 1 + prime as usize)
+        } else {
+            let axis = match input
+                .chars()
+                .next()?
+            {
+                'R' => MegaminxAxis::R,
+                'D' => MegaminxAxis::D,
+                _ => return None,
+            };
+            let prime = match input.get(1..3)? {
+                "++" => false,
+                "--" => true,
+                _ => return None,
+            };
+            (axis, prime, 3)
+        };
+
+        Some((MegaminxMove { axis, prime }, length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::notation::parse_moves;
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&MegaminxNotation, "R++ D-- U'\nR-- (D++ U)'").unwrap();
+
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "R++ D-- U' R-- U' D--"
+        );
+        assert!(parse_moves(&MegaminxNotation, "R+").is_err());
+        assert!(parse_moves(&MegaminxNotation, "R").is_err());
+        assert!(parse_moves(&MegaminxNotation, "F++").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use std::fmt;
+
+use crate::routes::scrambles::{Scramble, ScrambleKind};
+
+use super::scramble_services::square_one::SquareOne;
+
+pub mod clock;
+pub mod cube;
+pub mod megaminx;
+pub mod pyraminx;
+pub mod skewb;
+pub mod square_one;
+
+/// Upper bound on the number of moves a sequence may expand to, so that nested
+/// repetitions can't be used to blow up the server.
+pub const MAX_MOVES: usize = 10_000;
+const MAX_DEPTH: usize = 16;
+
+pub trait Invert {
+    fn inverse(&self) -> Self;
+}
+
+/// The puzzle specific part of the notation, i.e. how a single move is written.
+/// Brackets, repetitions and comments are shared by every puzzle.
+pub trait Notation {
+    type Move: Clone + Invert + fmt::Display;
+
+    /// Parses the move at the start of `input`, returning it with its length in bytes.
+    fn parse_move(&self, input: &str) -> Option<(Self::Move, usize)>;
+}
+
+/// A parsed sequence is a list of nodes. Amounts repeat a bracket that many times,
+/// negative amounts (written with a `'`) repeat its inverse.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node<M> {
+    /// A single move and the character offset it was written at.
+    Move(M, usize),
+    /// `(A)n`
+    Group { body: Vec<Node<M>>, amount: i32 },
+    /// `[A, B]`, short for `A B A' B'`.
+    Commutator {
+        a: Vec<Node<M>>,
+        b: Vec<Node<M>>,
+        amount: i32,
+    },
+    /// `[A: B]`, short for `A B A'`.
+    Conjugate {
+        a: Vec<Node<M>>,
+        b: Vec<Node<M>>,
+        amount: i32,
+    },
+}
+
+/// Offsets count characters, not bytes.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ParseError {
+    #[error("invalid move `{token}` at offset {offset}")]
+    InvalidMove { token: String, offset: usize },
+    #[error("expected {expected} but found `{found}` at offset {offset}")]
+    Unexpected {
+        expected: &'static str,
+        found: char,
+        offset: usize,
+    },
+    #[error("unclosed `{bracket}` at offset {offset}")]
+    Unclosed { bracket: char, offset: usize },
+    #[error("brackets nested too deeply at offset {offset}")]
+    TooDeep { offset: usize },
+    #[error("sequence expands to more than {} moves at offset {offset}", MAX_MOVES)]
+    TooLong { offset: usize },
+    #[error("move `{token}` can't be made at offset {offset}")]
+    IllegalMove { token: String, offset: usize },
+}
+
+/// Parses the amount written after a move or a closing bracket: an optional count followed
+/// by an optional `'`. Returns the signed amount and its length in bytes.
+fn parse_amount(input: &str) -> Option<(i32, usize)> {
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let count: i32 = match digits {
+        0 => 1,
+        _ => input[..digits]
+            .parse()
+            .ok()?,
+    };
+
+    match input[digits..].starts_with('\'') {
+        true => Some((-count, digits + 1)),
+        false => Some((count, digits)),
+    }
+}
+
+fn write_amount(f: &mut fmt::Formatter<'_>, amount: i32) -> fmt::Result {
+    match amount {
+        1 => Ok(()),
+        -1 => write!(f, "'"),
+        a if a < 0 => write!(f, "{}'", a.unsigned_abs()),
+        a => write!(f, "{}", a),
+    }
+}
+
+struct Parser<'a, N: Notation> {
+    notation: &'a N,
+    input: &'a str,
+    // Byte position of the next character.
+    position: usize,
+    depth: usize,
+}
+
+// Parsed nodes along with the number of moves they expand to.
+type Parsed<M> = Result<(Node<M>, usize), ParseError>;
+type ParsedNodes<M> = Result<(Vec<Node<M>>, usize), ParseError>;
+
+impl<'a, N: Notation> Parser<'a, N> {
+    fn offset(&self, position: usize) -> usize {
+        self.input[..position]
+            .chars()
+            .count()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest()
+            .chars()
+            .next()
+    }
+
+    /// Skips whitespace and `//` comments, which run to the end of the line.
+    fn skip_blank(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if !trimmed.starts_with("//") {
+                return;
+            }
+            self.position += trimmed
+                .find('\n')
+                .unwrap_or(trimmed.len());
+        }
+    }
+
+    fn invalid_move(&self) -> ParseError {
+        let rest = self.rest();
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c.is_whitespace() || "()[],:".contains(c))
+            .map_or(rest.len(), |(i, _)| i);
+        ParseError::InvalidMove {
+            token: rest[..end].to_owned(),
+            offset: self.offset(self.position),
+        }
+    }
+
+    /// Parses nodes up to the end of the input, a closing bracket or a separator.
+    fn nodes(&mut self) -> ParsedNodes<N::Move> {
+        let mut nodes = Vec::new();
+        let mut length: usize = 0;
+
+        loop {
+            self.skip_blank();
+            let start = self.position;
+            let (node, node_length) = match self.peek() {
+                None | Some(')' | ']' | ',' | ':') => return Ok((nodes, length)),
+                Some(c) => match self
+                    .notation
+                    .parse_move(self.rest())
+                {
+                    Some((mv, len)) => {
+                        self.position += len;
+                        (Node::Move(mv, self.offset(start)), 1)
+                    }
+                    None if c == '(' => self.group()?,
+                    None if c == '[' => self.brackets()?,
+                    None => return Err(self.invalid_move()),
+                },
+            };
+
+            length = length.saturating_add(node_length);
+            if length > MAX_MOVES {
+                return Err(ParseError::TooLong {
+                    offset: self.offset(start),
+                });
+            }
+            nodes.push(node);
+        }
+    }
+
+    fn open(&mut self) -> Result<usize, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ParseError::TooDeep {
+                offset: self.offset(self.position),
+            });
+        }
+        self.depth += 1;
+        self.position += 1;
+        Ok(self.position - 1)
+    }
+
+    fn close(&mut self, start: usize, bracket: char, closing: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == closing => {
+                self.position += 1;
+                self.depth -= 1;
+                Ok(())
+            }
+            Some(found) => Err(ParseError::Unexpected {
+                expected: if closing == ')' { "`)`" } else { "`]`" },
+                found,
+                offset: self.offset(self.position),
+            }),
+            None => Err(ParseError::Unclosed {
+                bracket,
+                offset: self.offset(start),
+            }),
+        }
+    }
+
+    fn amount(&mut self) -> Result<i32, ParseError> {
+        let (amount, len) = parse_amount(self.rest()).ok_or_else(|| self.invalid_move())?;
+        self.position += len;
+        Ok(amount)
+    }
+
+    fn group(&mut self) -> Parsed<N::Move> {
+        let start = self.open()?;
+        let (body, length) = self.nodes()?;
+        self.close(start, '(', ')')?;
+        let amount = self.amount()?;
+
+        let length = length.saturating_mul(amount.unsigned_abs() as usize);
+        Ok((Node::Group { body, amount }, length))
+    }
+
+    fn brackets(&mut self) -> Parsed<N::Move> {
+        let start = self.open()?;
+        let (a, a_length) = self.nodes()?;
+        let separator = match self.peek() {
+            Some(c @ (',' | ':')) => {
+                self.position += 1;
+                c
+            }
+            Some(found) => {
+                return Err(ParseError::Unexpected {
+                    expected: "`,` or `:`",
+                    found,
+                    offset: self.offset(self.position),
+                })
+            }
+            None => {
+                return Err(ParseError::Unclosed {
+                    bracket: '[',
+                    offset: self.offset(start),
+                })
+            }
+        };
+        let (b, b_length) = self.nodes()?;
+        self.close(start, '[', ']')?;
+        let amount = self.amount()?;
+
+        let (node, length) = match separator {
+            ',' => (Node::Commutator { a, b, amount }, 2 * (a_length + b_length)),
+            _ => (Node::Conjugate { a, b, amount }, 2 * a_length + b_length),
+        };
+        Ok((node, length.saturating_mul(amount.unsigned_abs() as usize)))
+    }
+}
+
+/// Parses `sequence` into its syntax tree, keeping brackets and repetitions as written.
+pub fn parse<N: Notation>(notation: &N, sequence: &str) -> Result<Vec<Node<N::Move>>, ParseError> {
+    let mut parser = Parser {
+        notation,
+        input: sequence,
+        position: 0,
+        depth: 0,
+    };
+
+    let (nodes, _) = parser.nodes()?;
+    match parser.peek() {
+        Some(found) => Err(ParseError::Unexpected {
+            expected: "a move",
+            found,
+            offset: parser.offset(parser.position),
+        }),
+        None => Ok(nodes),
+    }
+}
+
+fn invert_located<M: Invert>(moves: &[(usize, M)]) -> Vec<(usize, M)> {
+    moves
+        .iter()
+        .rev()
+        .map(|(offset, mv)| (*offset, mv.inverse()))
+        .collect()
+}
+
+fn repeat<M: Clone + Invert>(moves: Vec<(usize, M)>, amount: i32) -> Vec<(usize, M)> {
+    let unit = match amount < 0 {
+        true => invert_located(&moves),
+        false => moves,
+    };
+    let count = amount.unsigned_abs() as usize;
+    let mut result = Vec::with_capacity(unit.len() * count);
+    for _ in 0..count {
+        result.extend_from_slice(&unit);
+    }
+    result
+}
+
+/// Flattens the nodes into the moves they stand for, each with the offset it was
+/// written at.
+pub fn expand_with_offsets<M: Clone + Invert>(nodes: &[Node<M>]) -> Vec<(usize, M)> {
+    let mut moves = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Move(mv, offset) => moves.push((*offset, mv.clone())),
+            Node::Group { body, amount } => {
+                moves.extend(repeat(expand_with_offsets(body), *amount));
+            }
+            Node::Commutator { a, b, amount } => {
+                let (a, b) = (expand_with_offsets(a), expand_with_offsets(b));
+                let commutator =
+                    [a.clone(), b.clone(), invert_located(&a), invert_located(&b)].concat();
+                moves.extend(repeat(commutator, *amount));
+            }
+            Node::Conjugate { a, b, amount } => {
+                let a = expand_with_offsets(a);
+                let conjugate = [a.clone(), expand_with_offsets(b), invert_located(&a)].concat();
+                moves.extend(repeat(conjugate, *amount));
+            }
+        }
+    }
+    moves
+}
+
+/// Flattens the nodes into the moves they stand for.
+pub fn expand<M: Clone + Invert>(nodes: &[Node<M>]) -> Vec<M> {
+    expand_with_offsets(nodes)
+        .into_iter()
+        .map(|(_, mv)| mv)
+        .collect()
+}
+
+/// Flattens the nodes into plain move nodes.
+pub fn expand_nodes<M: Clone + Invert>(nodes: &[Node<M>]) -> Vec<Node<M>> {
+    expand_with_offsets(nodes)
+        .into_iter()
+        .map(|(offset, mv)| Node::Move(mv, offset))
+        .collect()
+}
+
+/// The inverse of a sequence, keeping its brackets: `(A)n` becomes `(A')n`, `[A, B]`
+/// becomes `[B, A]` and `[A: B]` becomes `[A: B']`.
+pub fn invert_nodes<M: Clone + Invert>(nodes: &[Node<M>]) -> Vec<Node<M>> {
+    nodes
+        .iter()
+        .rev()
+        .map(|node| match node {
+            Node::Move(mv, offset) => Node::Move(mv.inverse(), *offset),
+            Node::Group { body, amount } => Node::Group {
+                body: invert_nodes(body),
+                amount: *amount,
+            },
+            Node::Commutator { a, b, amount } => Node::Commutator {
+                a: b.clone(),
+                b: a.clone(),
+                amount: *amount,
+            },
+            Node::Conjugate { a, b, amount } => Node::Conjugate {
+                a: a.clone(),
+                b: invert_nodes(b),
+                amount: *amount,
+            },
+        })
+        .collect()
+}
+
+/// Replaces every move of the sequence, keeping its brackets.
+pub fn map_moves<M, T>(nodes: &[Node<M>], f: &impl Fn(&M) -> T) -> Vec<Node<T>> {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Move(mv, offset) => Node::Move(f(mv), *offset),
+            Node::Group { body, amount } => Node::Group {
+                body: map_moves(body, f),
+                amount: *amount,
+            },
+            Node::Commutator { a, b, amount } => Node::Commutator {
+                a: map_moves(a, f),
+                b: map_moves(b, f),
+                amount: *amount,
+            },
+            Node::Conjugate { a, b, amount } => Node::Conjugate {
+                a: map_moves(a, f),
+                b: map_moves(b, f),
+                amount: *amount,
+            },
+        })
+        .collect()
+}
+
+/// Parses `sequence` straight into the moves it stands for.
+pub fn parse_moves<N: Notation>(notation: &N, sequence: &str) -> Result<Vec<N::Move>, ParseError> {
+    Ok(expand(&parse(notation, sequence)?))
+}
+
+pub fn invert<M: Invert>(moves: &[M]) -> Vec<M> {
+    moves
+        .iter()
+        .rev()
+        .map(Invert::inverse)
+        .collect()
+}
+
+/// Writes the nodes back in the notation they were parsed from, one space between them.
+pub fn format<M: fmt::Display>(nodes: &[Node<M>]) -> String {
+    nodes
+        .iter()
+        .map(|node| node.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl<M: fmt::Display> fmt::Display for Node<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let amount = match self {
+            Node::Move(mv, _) => return write!(f, "{}", mv),
+            Node::Group { body, amount } => {
+                write!(f, "({})", format(body))?;
+                amount
+            }
+            Node::Commutator { a, b, amount } => {
+                write!(f, "[{}, {}]", format(a), format(b))?;
+                amount
+            }
+            Node::Conjugate { a, b, amount } => {
+                write!(f, "[{}: {}]", format(a), format(b))?;
+                amount
+            }
+        };
+        write_amount(f, *amount)
+    }
+}
+
+/// Checks that the sequence of `scramble` is written in the notation of its puzzle.
+/// Square-1 scrambles are also checked for slice turns blocked by a corner.
+pub fn validate(scramble: &Scramble) -> Result<(), ParseError> {
+    let sequence = &scramble.sequence;
+    match &scramble.kind {
+        ScrambleKind::Pyraminx => parse(&pyraminx::PyraminxNotation, sequence).map(drop),
+        ScrambleKind::Skewb => parse(&skewb::SkewbNotation, sequence).map(drop),
+        ScrambleKind::Megaminx => parse(&megaminx::MegaminxNotation, sequence).map(drop),
+        ScrambleKind::SquareOne => SquareOne::from_sequence(sequence).map(drop),
+        ScrambleKind::Clock => parse(&clock::ClockNotation, sequence).map(drop),
+        kind => {
+            let size = kind
+                .cube_size()
+                .expect("every other kind is a cube");
+            parse(&cube::CubeNotation { size }, sequence).map(drop)
+        }
+    }
+}
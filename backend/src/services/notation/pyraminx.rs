@@ -0,0 +1,107 @@
+use std::fmt;
+
+use super::{Invert, Notation};
+
+/// Corners in the order U (top), L (front left), R (front right) and B (back).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PyraminxCorner {
+    U,
+    L,
+    R,
+    B,
+}
+
+/// A third of a turn around a corner, of the whole layer (`R`) or only the tip (`r`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PyraminxMove {
+    pub corner: PyraminxCorner,
+    pub tip: bool,
+    pub prime: bool,
+}
+
+impl Invert for PyraminxMove {
+    fn inverse(&self) -> PyraminxMove {
+        PyraminxMove {
+            prime: !self.prime,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for PyraminxMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = format!("{:?}", self.corner);
+        match self.tip {
+            true => write!(f, "{}", letter.to_lowercase())?,
+            false => write!(f, "{}", letter)?,
+        }
+        if self.prime {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct PyraminxNotation;
+
+impl Notation for PyraminxNotation {
+    type Move = PyraminxMove;
+
+    fn parse_move(&self, input: &str) -> Option<(PyraminxMove, usize)> {
+        let letter = input
+            .chars()
+            .next()?;
+        let corner = match letter.to_ascii_uppercase() {
+            'U' => PyraminxCorner::U,
+            'L' => PyraminxCorner::L,
+            'R' => PyraminxCorner::R,
+            'B' => PyraminxCorner::B,
+            _ => return None,
+        };
+        let prime = input[1..].starts_with('\'');
+
+        let mv = PyraminxMove {
+            corner,
+            tip: letter.is_ascii_lowercase(),
+            prime,
+        };
+        Some((mv, 1 + prime as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::notation::parse_moves;
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&PyraminxNotation, "U b'").unwrap();
+
+        assert_eq!(
+            moves,
+            vec![
+                PyraminxMove {
+                    corner: PyraminxCorner::U,
+                    tip: false,
+                    prime: false,
+                },
+                PyraminxMove {
+                    corner: PyraminxCorner::B,
+                    tip: true,
+                    prime: true,
+                },
+            ]
+        );
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "U b'"
+        );
+        assert!(parse_moves(&PyraminxNotation, "F").is_err());
+        assert!(parse_moves(&PyraminxNotation, "R2").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use super::{Invert, Notation};
+
+/// Corners turned by each move in the WCA notation (R: DRB, U: ULB, L: DLF, B: DLB).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkewbCorner {
+    R,
+    U,
+    L,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SkewbMove {
+    pub corner: SkewbCorner,
+    pub prime: bool,
+}
+
+impl Invert for SkewbMove {
+    fn inverse(&self) -> SkewbMove {
+        SkewbMove {
+            prime: !self.prime,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for SkewbMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.corner)?;
+        if self.prime {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SkewbNotation;
+
+impl Notation for SkewbNotation {
+    type Move = SkewbMove;
+
+    fn parse_move(&self, input: &str) -> Option<(SkewbMove, usize)> {
+        let corner = match input
+            .chars()
+            .next()?
+        {
+            'R' => SkewbCorner::R,
+            'U' => SkewbCorner::U,
+            'L' => SkewbCorner::L,
+            'B' => SkewbCorner::B,
+            _ => return None,
+        };
+        let prime = input[1..].starts_with('\'');
+
+        Some((SkewbMove { corner, prime }, 1 + prime as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::notation::parse_moves;
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&SkewbNotation, "[R, U']").unwrap();
+
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "R U' R' U"
+        );
+        assert!(parse_moves(&SkewbNotation, "F").is_err());
+        assert!(parse_moves(&SkewbNotation, "r").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use super::{Invert, Notation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SquareOneMove {
+    /// `(top,bottom)`, both layers turned clockwise as seen from their side, in twelfths.
+    Turn { top: i32, bottom: i32 },
+    /// `/`, a half turn of the right half.
+    Slash,
+}
+
+impl Invert for SquareOneMove {
+    fn inverse(&self) -> SquareOneMove {
+        match *self {
+            SquareOneMove::Turn { top, bottom } => SquareOneMove::Turn {
+                top: -top,
+                bottom: -bottom,
+            },
+            SquareOneMove::Slash => SquareOneMove::Slash,
+        }
+    }
+}
+
+impl fmt::Display for SquareOneMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SquareOneMove::Turn { top, bottom } => write!(f, "({},{})", top, bottom),
+            SquareOneMove::Slash => write!(f, "/"),
+        }
+    }
+}
+
+pub struct SquareOneNotation;
+
+impl Notation for SquareOneNotation {
+    type Move = SquareOneMove;
+
+    /// Layer turns use parentheses as well, anything in them which isn't a pair of numbers
+    /// is left to be parsed as a group.
+    fn parse_move(&self, input: &str) -> Option<(SquareOneMove, usize)> {
+        if input.starts_with('/') {
+            return Some((SquareOneMove::Slash, 1));
+        }
+
+        let body = input.strip_prefix('(')?;
+        let end = body.find(')')?;
+        let (top, bottom) = body[..end].split_once(',')?;
+        let turn = SquareOneMove::Turn {
+            top: top
+                .trim()
+                .parse()
+                .ok()?,
+            bottom: bottom
+                .trim()
+                .parse()
+                .ok()?,
+        };
+        Some((turn, end + 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::notation::parse_moves;
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves(&SquareOneNotation, "(1,0)/ (-1, -1) / ((3,0)/)2").unwrap();
+
+        assert_eq!(
+            moves,
+            vec![
+                SquareOneMove::Turn { top: 1, bottom: 0 },
+                SquareOneMove::Slash,
+                SquareOneMove::Turn {
+                    top: -1,
+                    bottom: -1
+                },
+                SquareOneMove::Slash,
+                SquareOneMove::Turn { top: 3, bottom: 0 },
+                SquareOneMove::Slash,
+                SquareOneMove::Turn { top: 3, bottom: 0 },
+                SquareOneMove::Slash,
+            ]
+        );
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "(1,0) / (-1,-1) / (3,0) / (3,0) /"
+        );
+        assert!(parse_moves(&SquareOneNotation, "(1,0)/ (a,1)").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::services::notation::{
+    self,
+    clock::{ClockMove, ClockNotation, Pin},
+    ParseError,
+};
+
+use super::{Point, Svg};
+
+/// Colours of the front and the back of the puzzle.
+pub const DEFAULT_SCHEME: [&str; 2] = ["#1e3a8a", "#93c5fd"];
+
+// Pins are indexed in the order of `Pin`, as seen from the side being looked at. Dials are
+// numbered row by row from 0 (top left) to 8 (bottom right), again as seen from that side.
+const PIN_DIALS: [[usize; 4]; 4] = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]];
+const PIN_CORNERS: [usize; 4] = [0, 2, 6, 8];
+
+const DIAL_RADIUS: f64 = 18.0;
+const DIAL_SPACING: f64 = 48.0;
+const FACE_SIZE: f64 = 3.0 * DIAL_SPACING;
+const GAP: f64 = 10.0;
+
+/// Dial positions in hours and pin states of both sides. Side 0 is the side that faces the
+/// solver at the start of the scramble.
+#[derive(Debug, Default, PartialEq)]
+struct Clock {
+    dials: [[i32; 9]; 2],
+    // Pins pushed towards side 0, i.e. up when looking at side 0.
+    pins: [bool; 4],
+    front: usize,
+}
+
+// The dial or pin at the same place, as seen from the other side.
+fn mirror_dial(dial: usize) -> usize {
+    dial / 3 * 3 + 2 - dial % 3
+}
+
+fn mirror_pin(pin: usize) -> usize {
+    pin ^ 1
+}
+
+impl Clock {
+    /// Turns a dial next to the `pins` (which are up) by `amount` hours clockwise. This turns
+    /// every front dial next to one of those pins, and the back corner dials behind them
+    /// the other way around.
+    fn turn(&mut self, pins: &[Pin], amount: i32) {
+        let (front, back) = (self.front, 1 - self.front);
+        let mut dials: Vec<usize> = pins
+            .iter()
+            .flat_map(|&pin| PIN_DIALS[pin as usize])
+            .collect();
+        dials.sort();
+        dials.dedup();
+
+        for dial in dials {
+            self.dials[front][dial] += amount;
+        }
+        for &pin in pins {
+            self.dials[back][mirror_dial(PIN_CORNERS[pin as usize])] -= amount;
+        }
+    }
+
+    /// Records a pin left up at the end of the scramble, as seen from the current front.
+    fn pin_up(&mut self, pin: Pin) {
+        let pin = pin as usize;
+        match self.front {
+            0 => self.pins[pin] = true,
+            _ => self.pins[mirror_pin(pin)] = false,
+        }
+    }
+
+    fn apply_sequence(&mut self, sequence: &str) -> Result<(), ParseError> {
+        // Pins are down unless the scramble ends by listing them.
+        self.pins = [self.front == 1; 4];
+
+        for mv in notation::parse_moves(&ClockNotation, sequence)? {
+            match mv {
+                ClockMove::Turn { pins, amount } => self.turn(pins.pins(), amount),
+                ClockMove::Flip => {
+                    self.front = 1 - self.front;
+                    self.pins = [self.front == 1; 4];
+                }
+                ClockMove::PinUp(pin) => self.pin_up(pin),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn draw_side(svg: &mut Svg, origin: Point, dials: &[i32; 9], pins: [bool; 4], colors: [&str; 2]) {
+    let (x, y) = origin;
+    svg.polygon(
+        &[
+            (x, y),
+            (x + FACE_SIZE, y),
+            (x + FACE_SIZE, y + FACE_SIZE),
+            (x, y + FACE_SIZE),
+        ],
+        colors[0],
+    );
+
+    for (i, &hours) in dials
+        .iter()
+        .enumerate()
+    {
+        let center = (
+            x + (i % 3) as f64 * DIAL_SPACING + DIAL_SPACING / 2.0,
+            y + (i / 3) as f64 * DIAL_SPACING + DIAL_SPACING / 2.0,
+        );
+        svg.circle(center, DIAL_RADIUS, "white");
+        let (sin, cos) = (hours.rem_euclid(12) as f64 * 30.0)
+            .to_radians()
+            .sin_cos();
+        let hand = (
+            center.0 + DIAL_RADIUS * 0.8 * sin,
+            center.1 - DIAL_RADIUS * 0.8 * cos,
+        );
+        svg.line(center, hand, 3.0);
+    }
+
+    for (pin, up) in pins
+        .into_iter()
+        .enumerate()
+    {
+        let center = (
+            x + DIAL_SPACING * (1 + pin % 2) as f64,
+            y + DIAL_SPACING * (1 + pin / 2) as f64,
+        );
+        svg.circle(center, 5.0, if up { colors[1] } else { "black" });
+    }
+}
+
+pub fn render(sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let mut clock = Clock::default();
+    clock.apply_sequence(sequence)?;
+
+    // Both sides are drawn as seen when looking at them, pins up on one side are down on the other.
+    let back_pins: [bool; 4] = std::array::from_fn(|pin| !clock.pins[mirror_pin(pin)]);
+    let mut svg = Svg::new(2.0 * FACE_SIZE + 3.0 * GAP, FACE_SIZE + 2.0 * GAP);
+    draw_side(
+        &mut svg,
+        (GAP, GAP),
+        &clock.dials[0],
+        clock.pins,
+        [&colors[0], &colors[1]],
+    );
+    draw_side(
+        &mut svg,
+        (2.0 * GAP + FACE_SIZE, GAP),
+        &clock.dials[1],
+        back_pins,
+        [&colors[1], &colors[0]],
+    );
+
+    Ok(svg.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(sequence: &str) -> Clock {
+        let mut clock = Clock::default();
+        clock
+            .apply_sequence(sequence)
+            .unwrap();
+        clock
+    }
+
+    #[test]
+    fn test_dial_turns() {
+        let clock = apply("UR3+ ALL1-");
+
+        assert_eq!(clock.dials[0], [-1, 2, 2, -1, 2, 2, -1, -1, -1]);
+        // UR is behind the back UL corner, and all corners turn back by 1.
+        assert_eq!(clock.dials[1], [-2, 0, 1, 0, 0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_flip_and_pins() {
+        let clock = apply("y2 U2+ UR");
+
+        assert_eq!(clock.front, 1);
+        assert_eq!(clock.dials[1], [2, 2, 2, 2, 2, 2, 0, 0, 0]);
+        assert_eq!(clock.dials[0], [-2, 0, -2, 0, 0, 0, 0, 0, 0]);
+        // Only the pin at the back UR (front UL) is up on the back, so the others are up on the front.
+        assert_eq!(clock.pins, [false, true, true, true]);
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let mut clock = Clock::default();
+
+        assert!(clock
+            .apply_sequence("UR3")
+            .is_err());
+        assert!(clock
+            .apply_sequence("X3+")
+            .is_err());
+        assert!(clock
+            .apply_sequence("R")
+            .is_err());
+    }
+}
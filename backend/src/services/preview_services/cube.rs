@@ -0,0 +1,49 @@
+use crate::services::{cube_state::CubeState, notation::ParseError};
+
+use super::Svg;
+
+/// Colours of the U, R, F, D, L and B faces.
+pub const DEFAULT_SCHEME: [&str; 6] = ["white", "red", "green", "yellow", "orange", "blue"];
+
+pub const FACE_SIZE: f64 = 90.0;
+pub const GAP: f64 = 6.0;
+
+// Column and row of every face in the net, in the order of `Face::ALL`:
+//       U
+//     L F R B
+//       D
+pub const NET: [(f64, f64); 6] = [
+    (1.0, 0.0),
+    (2.0, 1.0),
+    (1.0, 1.0),
+    (1.0, 2.0),
+    (0.0, 1.0),
+    (3.0, 1.0),
+];
+
+pub fn render(size: usize, sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let cube = CubeState::from_sequence(size, sequence)?;
+    let sticker = FACE_SIZE / size as f64;
+    let mut svg = Svg::new(4.0 * FACE_SIZE + 5.0 * GAP, 3.0 * FACE_SIZE + 4.0 * GAP);
+
+    for (i, color) in cube
+        .colors()
+        .into_iter()
+        .enumerate()
+    {
+        let (column, row) = NET[i / (size * size)];
+        let x = GAP + column * (FACE_SIZE + GAP) + (i % size) as f64 * sticker;
+        let y = GAP + row * (FACE_SIZE + GAP) + (i % (size * size) / size) as f64 * sticker;
+        svg.polygon(
+            &[
+                (x, y),
+                (x + sticker, y),
+                (x + sticker, y + sticker),
+                (x, y + sticker),
+            ],
+            &colors[color.index()],
+        );
+    }
+
+    Ok(svg.finish())
+}
@@ -0,0 +1,105 @@
+pub type Vector = [f64; 3];
+
+pub fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: Vector, factor: f64) -> Vector {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+pub fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn normalize(a: Vector) -> Vector {
+    scale(a, 1.0 / dot(a, a).sqrt())
+}
+
+pub fn distance(a: Vector, b: Vector) -> f64 {
+    let d = sub(a, b);
+    dot(d, d).sqrt()
+}
+
+/// Rotates `v` by `angle` radians clockwise around the unit vector `axis`,
+/// as seen when looking at the origin from the end of `axis`.
+pub fn rotate(v: Vector, axis: Vector, angle: f64) -> Vector {
+    let (sin, cos) = (-angle).sin_cos();
+    add(
+        add(scale(v, cos), scale(cross(axis, v), sin)),
+        scale(axis, dot(axis, v) * (1.0 - cos)),
+    )
+}
+
+/// Stickers of a puzzle whose turns rotate everything on one side of a plane, each
+/// sticker being identified by the position of its centre on the solved puzzle.
+pub struct StickerPuzzle {
+    centers: Vec<Vector>,
+    colors: Vec<usize>,
+}
+
+impl StickerPuzzle {
+    pub fn new(centers: Vec<Vector>, colors: Vec<usize>) -> StickerPuzzle {
+        StickerPuzzle { centers, colors }
+    }
+
+    /// Rotates all stickers lying further than `depth` along the unit vector `axis`
+    /// by `angle` radians clockwise, as seen from the end of `axis`.
+    pub fn turn(&mut self, axis: Vector, depth: f64, angle: f64) {
+        let mut next = self
+            .colors
+            .clone();
+        for (center, &color) in self
+            .centers
+            .iter()
+            .zip(&self.colors)
+        {
+            if dot(*center, axis) > depth {
+                let moved = rotate(*center, axis, angle);
+                next[self.nearest(moved)] = color;
+            }
+        }
+        self.colors = next;
+    }
+
+    fn nearest(&self, point: Vector) -> usize {
+        (0..self
+            .centers
+            .len())
+            .min_by(|&a, &b| {
+                distance(self.centers[a], point).total_cmp(&distance(self.centers[b], point))
+            })
+            .expect("puzzle should have stickers")
+    }
+
+    pub fn colors(&self) -> &[usize] {
+        &self.colors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_rotate_clockwise() {
+        let rotated = rotate([0.0, 0.0, 1.0], [0.0, 1.0, 0.0], FRAC_PI_2);
+
+        // A clockwise quarter turn seen from above (a U move) moves the front to the left.
+        assert!(distance(rotated, [-1.0, 0.0, 0.0]) < 1e-9);
+    }
+}
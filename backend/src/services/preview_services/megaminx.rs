@@ -0,0 +1,435 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::services::notation::{
+    self,
+    megaminx::{MegaminxAxis, MegaminxMove, MegaminxNotation},
+    ParseError,
+};
+
+use super::{
+    geometry::{self, StickerPuzzle, Vector},
+    Point, Svg,
+};
+
+/// Colours of the U, F, R, BR, BL, L, D, DR, DBR, B, DBL and DL faces.
+pub const DEFAULT_SCHEME: [&str; 12] = [
+    "white",
+    "green",
+    "red",
+    "purple",
+    "yellow",
+    "blue",
+    "gray",
+    "pink",
+    "lightblue",
+    "lime",
+    "orange",
+    "beige",
+];
+
+const U: usize = 0;
+const F: usize = 1;
+const R: usize = 2;
+const BR: usize = 3;
+const BL: usize = 4;
+const L: usize = 5;
+const D: usize = 6;
+const DR: usize = 7;
+const DBR: usize = 8;
+const B: usize = 9;
+const DBL: usize = 10;
+const DL: usize = 11;
+
+// Every face of the net is unfolded from an already placed face: one flower of faces around U,
+// and another one around D hanging off the F face.
+const NET: [(usize, usize); 11] = [
+    (F, U),
+    (R, U),
+    (BR, U),
+    (BL, U),
+    (L, U),
+    (DR, F),
+    (D, DR),
+    (DBR, D),
+    (B, D),
+    (DBL, D),
+    (DL, D),
+];
+
+// Distance of the cuts from the edges of a face, relative to the face's circumradius.
+const INSET: f64 = 0.45;
+const FACE_RADIUS: f64 = 40.0;
+const MARGIN: f64 = 6.0;
+
+struct Face {
+    normal: Vector,
+    center: Vector,
+    axes: [Vector; 2],
+    radius: f64,
+    vertices: Vec<Vector>,
+}
+
+impl Face {
+    /// Point of the face given in coordinates of a pentagon of circumradius 1,
+    /// whose `k`-th vertex is at an angle of `k` fifths of a full turn.
+    fn point(&self, (x, y): Point) -> Vector {
+        let [e1, e2] = self.axes;
+        geometry::add(
+            self.center,
+            geometry::scale(
+                geometry::add(geometry::scale(e1, x), geometry::scale(e2, y)),
+                self.radius,
+            ),
+        )
+    }
+}
+
+fn pentagon_vertex(k: usize) -> Point {
+    let angle = 2.0 * PI * k as f64 / 5.0;
+    (angle.cos(), angle.sin())
+}
+
+/// Sorts `points` counterclockwise around `normal`, as seen from outside.
+fn sort_around(normal: Vector, points: &mut [Vector]) {
+    let e1 = geometry::normalize(geometry::sub(
+        points[0],
+        geometry::scale(normal, geometry::dot(points[0], normal)),
+    ));
+    let e2 = geometry::cross(normal, e1);
+    points.sort_by(|&a, &b| {
+        let angle = |p| geometry::dot(p, e2).atan2(geometry::dot(p, e1));
+        angle(a).total_cmp(&angle(b))
+    });
+}
+
+/// Faces of a dodecahedron with circumradius 1, U on top and F in front. Face normals are the
+/// vertices of an icosahedron: U, a ring of five faces above and below the equator, and D.
+fn faces() -> Vec<Face> {
+    let elevation = 0.5f64.atan();
+    let ring = |azimuth: f64, height: f64| {
+        let azimuth = azimuth.to_radians();
+        [
+            azimuth.sin() * elevation.cos(),
+            height * elevation.sin(),
+            azimuth.cos() * elevation.cos(),
+        ]
+    };
+    let mut normals = vec![[0.0, 1.0, 0.0]];
+    normals.extend((0..5).map(|k| ring(72.0 * k as f64, 1.0)));
+    normals.push([0.0, -1.0, 0.0]);
+    normals.extend((0..5).map(|k| ring(36.0 + 72.0 * k as f64, -1.0)));
+
+    normals
+        .iter()
+        .map(|&normal| {
+            let mut neighbours: Vec<Vector> = normals
+                .iter()
+                .copied()
+                .filter(|&n| (geometry::dot(n, normal) - 1.0 / 5f64.sqrt()).abs() < 1e-9)
+                .collect();
+            sort_around(normal, &mut neighbours);
+            let vertices: Vec<Vector> = (0..5)
+                .map(|k| {
+                    geometry::normalize(geometry::add(
+                        normal,
+                        geometry::add(neighbours[k], neighbours[(k + 1) % 5]),
+                    ))
+                })
+                .collect();
+
+            let center = geometry::scale(normal, geometry::dot(vertices[0], normal));
+            let radius = geometry::distance(vertices[0], center);
+            let e1 = geometry::scale(geometry::sub(vertices[0], center), 1.0 / radius);
+            Face {
+                normal,
+                center,
+                axes: [e1, geometry::cross(normal, e1)],
+                radius,
+                vertices,
+            }
+        })
+        .collect()
+}
+
+// Intersection of the lines `p · a = a_offset` and `p · b = b_offset`.
+fn intersect((a, a_offset): (Point, f64), (b, b_offset): (Point, f64)) -> Point {
+    let det = a.0 * b.1 - a.1 * b.0;
+    (
+        (a_offset * b.1 - b_offset * a.1) / det,
+        (a.0 * b_offset - b.0 * a_offset) / det,
+    )
+}
+
+/// Stickers of a face as polygons in pentagon coordinates (see `Face::point`): the centre,
+/// then the corner at vertex `k` followed by the edge from vertex `k` to `k + 1`.
+fn stickers() -> Vec<Vec<Point>> {
+    let inradius = (PI / 5.0).cos();
+    let normal = |k: usize| {
+        let angle = (2.0 * k as f64 + 1.0) * PI / 5.0;
+        (angle.cos(), angle.sin())
+    };
+    let edge = |k: usize| (normal(k % 5), inradius);
+    let cut = |k: usize| (normal(k % 5), inradius - INSET);
+
+    let mut stickers = vec![(0..5)
+        .map(|k| intersect(cut(k), cut(k + 1)))
+        .collect()];
+    for k in 5..10 {
+        stickers.push(vec![
+            pentagon_vertex(k),
+            intersect(cut(k - 1), edge(k)),
+            intersect(cut(k - 1), cut(k)),
+            intersect(cut(k), edge(k - 1)),
+        ]);
+        stickers.push(vec![
+            intersect(cut(k - 1), edge(k)),
+            intersect(cut(k + 1), edge(k)),
+            intersect(cut(k + 1), cut(k)),
+            intersect(cut(k - 1), cut(k)),
+        ]);
+    }
+    stickers
+}
+
+fn solved(faces: &[Face]) -> StickerPuzzle {
+    let mut centers = Vec::new();
+    let mut colors = Vec::new();
+    for (f, face) in faces
+        .iter()
+        .enumerate()
+    {
+        for sticker in stickers() {
+            let n = sticker.len() as f64;
+            let x = sticker
+                .iter()
+                .map(|p| p.0)
+                .sum::<f64>();
+            let y = sticker
+                .iter()
+                .map(|p| p.1)
+                .sum::<f64>();
+            centers.push(face.point((x / n, y / n)));
+            colors.push(f);
+        }
+    }
+    StickerPuzzle::new(centers, colors)
+}
+
+/// Distance from the centre of the puzzle to the cut below a face, along its normal.
+fn layer_depth(faces: &[Face]) -> f64 {
+    let (top, side) = (&faces[U], &faces[F]);
+    let shared = (0..5)
+        .find(|&k| {
+            [k, (k + 1) % 5]
+                .iter()
+                .all(|&v| {
+                    top.vertices
+                        .iter()
+                        .any(|&t| geometry::distance(t, side.vertices[v]) < 1e-9)
+                })
+        })
+        .expect("U and F share an edge");
+
+    // Middle of the cut running along the shared edge on the F face.
+    let angle = (2.0 * shared as f64 + 1.0) * PI / 5.0;
+    let distance = (PI / 5.0).cos() - INSET;
+    let point = side.point((distance * angle.cos(), distance * angle.sin()));
+    geometry::dot(point, top.normal)
+}
+
+fn apply_sequence(
+    faces: &[Face],
+    puzzle: &mut StickerPuzzle,
+    sequence: &str,
+) -> Result<(), ParseError> {
+    let depth = layer_depth(faces);
+    let fifth = 2.0 * PI / 5.0;
+
+    for MegaminxMove { axis, prime } in notation::parse_moves(&MegaminxNotation, sequence)? {
+        // R++ and D++ turn everything but the opposite face, U turns only the U face.
+        let (face, depth, angle) = match axis {
+            MegaminxAxis::R => (R, -depth, 2.0 * fifth),
+            MegaminxAxis::D => (D, -depth, 2.0 * fifth),
+            MegaminxAxis::U => (U, depth, fifth),
+        };
+        let angle = if prime { -angle } else { angle };
+        puzzle.turn(faces[face].normal, depth, angle);
+    }
+    Ok(())
+}
+
+fn multiply(a: Point, b: Point) -> Point {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn divide(a: Point, b: Point) -> Point {
+    let norm = b.0 * b.0 + b.1 * b.1;
+    let (x, y) = multiply(a, (b.0, -b.1));
+    (x / norm, y / norm)
+}
+
+/// Similarity transforms (`z -> a * z + b` on complex numbers) placing every face's pentagon
+/// coordinates in the plane, with y pointing up.
+fn net(faces: &[Face]) -> Vec<(Point, Point)> {
+    let mut transforms = vec![((FACE_RADIUS, 0.0), (0.0, 0.0)); faces.len()];
+    let apply = |(a, b): (Point, Point), z: Point| {
+        let (x, y) = multiply(a, z);
+        (x + b.0, y + b.1)
+    };
+
+    for (child, parent) in NET {
+        let (j, a, b) = (0..5)
+            .find_map(|j| {
+                let find = |v: Vector| {
+                    faces[parent]
+                        .vertices
+                        .iter()
+                        .position(|&p| geometry::distance(p, v) < 1e-9)
+                };
+                let vertices = &faces[child].vertices;
+                Some((j, find(vertices[j])?, find(vertices[(j + 1) % 5])?))
+            })
+            .expect("faces of the net should share an edge");
+
+        // The shared edge runs the other way around the child face, which unfolds it
+        // on the other side of the edge.
+        let (from, to) = (
+            apply(transforms[parent], pentagon_vertex(a)),
+            apply(transforms[parent], pentagon_vertex(b)),
+        );
+        let (zj, zk) = (pentagon_vertex(j), pentagon_vertex(j + 1));
+        let scale = divide((to.0 - from.0, to.1 - from.1), (zk.0 - zj.0, zk.1 - zj.1));
+        let (x, y) = multiply(scale, zj);
+        transforms[child] = (scale, (from.0 - x, from.1 - y));
+    }
+
+    // Turn the net so that F is right below U.
+    let (fx, fy) = transforms[F].1;
+    let angle = -FRAC_PI_2 - fy.atan2(fx);
+    let rotation = (angle.cos(), angle.sin());
+    transforms
+        .into_iter()
+        .map(|(a, b)| (multiply(rotation, a), multiply(rotation, b)))
+        .collect()
+}
+
+pub fn render(sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let faces = faces();
+    let mut puzzle = solved(&faces);
+    apply_sequence(&faces, &mut puzzle, sequence)?;
+
+    // Faces are drawn slightly smaller than they are to leave gaps between them.
+    let polygons: Vec<Vec<Point>> = net(&faces)
+        .into_iter()
+        .flat_map(|(a, b)| {
+            stickers()
+                .into_iter()
+                .map(move |sticker| {
+                    sticker
+                        .into_iter()
+                        .map(|z| {
+                            let (x, y) = multiply(a, (z.0 * 0.95, z.1 * 0.95));
+                            (x + b.0, -(y + b.1))
+                        })
+                        .collect()
+                })
+        })
+        .collect();
+
+    let points = polygons
+        .iter()
+        .flatten();
+    let min_x = points
+        .clone()
+        .map(|p| p.0)
+        .fold(f64::INFINITY, f64::min);
+    let min_y = points
+        .clone()
+        .map(|p| p.1)
+        .fold(f64::INFINITY, f64::min);
+    let max_x = points
+        .clone()
+        .map(|p| p.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let max_y = points
+        .map(|p| p.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let mut svg = Svg::new(max_x - min_x + 2.0 * MARGIN, max_y - min_y + 2.0 * MARGIN);
+    for (polygon, &color) in polygons
+        .iter()
+        .zip(puzzle.colors())
+    {
+        let shifted: Vec<Point> = polygon
+            .iter()
+            .map(|(x, y)| (x - min_x + MARGIN, y - min_y + MARGIN))
+            .collect();
+        svg.polygon(&shifted, &colors[color]);
+    }
+
+    Ok(svg.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed_stickers(sequence: &str) -> usize {
+        let faces = faces();
+        let mut puzzle = solved(&faces);
+        apply_sequence(&faces, &mut puzzle, sequence).unwrap();
+        puzzle
+            .colors()
+            .iter()
+            .zip(solved(&faces).colors())
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    #[test]
+    fn test_faces() {
+        let faces = faces();
+
+        assert_eq!(faces.len(), 12);
+        for face in &faces {
+            assert_eq!(
+                face.vertices
+                    .len(),
+                5
+            );
+        }
+        assert!(faces[F].normal[0].abs() < 1e-9 && faces[F].normal[2] > 0.0);
+        assert!(faces[R].normal[0] > 0.0 && faces[R].normal[1] > 0.0);
+    }
+
+    #[test]
+    fn test_turns() {
+        // The U face turns in place, moving 3 stickers on each of the 5 faces around it.
+        assert_eq!(changed_stickers("U"), 15);
+        // Everything but the R face turning in place and the opposite face layer.
+        assert_eq!(changed_stickers("R++"), 12 * 11 - 11 - (11 + 15));
+        assert_eq!(changed_stickers("U U U U U"), 0);
+        assert_eq!(changed_stickers("R++ R++ R++ R++ R++"), 0);
+        assert_eq!(changed_stickers("D++ R-- R++ D--"), 0);
+        assert_ne!(changed_stickers("R++ D++"), 0);
+    }
+
+    #[test]
+    fn test_net_faces_do_not_overlap() {
+        let centers: Vec<Point> = net(&faces())
+            .into_iter()
+            .map(|(_, b)| b)
+            .collect();
+
+        for (i, a) in centers
+            .iter()
+            .enumerate()
+        {
+            for b in &centers[i + 1..] {
+                // Adjacent pentagons are two inradii apart.
+                let distance = (a.0 - b.0).hypot(a.1 - b.1);
+                assert!(distance > 1.9 * FACE_RADIUS * (PI / 5.0).cos());
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt::Write;
+
+use crate::{
+    error::AppError, routes::scrambles::ScrambleKind, services::validation_services::field_error,
+};
+
+use super::notation::ParseError;
+
+mod clock;
+mod cube;
+mod geometry;
+mod megaminx;
+mod pyraminx;
+mod skewb;
+mod square_one;
+
+type Point = (f64, f64);
+
+/// Minimal SVG document builder, every shape is outlined in black.
+struct Svg {
+    width: f64,
+    height: f64,
+    body: String,
+}
+
+impl Svg {
+    fn new(width: f64, height: f64) -> Svg {
+        Svg {
+            width,
+            height,
+            body: String::new(),
+        }
+    }
+
+    fn polygon(&mut self, points: &[Point], fill: &str) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+            .collect();
+        let _ = write!(
+            self.body,
+            r#"<polygon points="{}" fill="{}" stroke="black" stroke-width="1"/>"#,
+            points.join(" "),
+            fill
+        );
+    }
+
+    fn circle(&mut self, (x, y): Point, radius: f64, fill: &str) {
+        let _ = write!(
+            self.body,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}" stroke="black" stroke-width="1"/>"#,
+            x, y, radius, fill
+        );
+    }
+
+    fn line(&mut self, (x1, y1): Point, (x2, y2): Point, width: f64) {
+        let _ = write!(
+            self.body,
+            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="black" stroke-width="{}" stroke-linecap="round"/>"#,
+            x1, y1, x2, y2, width
+        );
+    }
+
+    fn finish(self) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">{}</svg>"#,
+            self.body,
+            w = self
+                .width
+                .ceil(),
+            h = self
+                .height
+                .ceil()
+        )
+    }
+}
+
+/// Colours are put into the SVG as they are, so only hex codes and plain colour names
+/// are accepted.
+fn is_valid_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => {
+            matches!(hex.len(), 3 | 6)
+                && hex
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit())
+        }
+        None => {
+            (1..=32).contains(&color.len())
+                && color
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic())
+        }
+    }
+}
+
+/// Parses a comma separated colour scheme, falling back to `default` when none is given.
+fn color_scheme(scheme: Option<&str>, default: &[&str]) -> Result<Vec<String>, AppError> {
+    let Some(scheme) = scheme else {
+        return Ok(default
+            .iter()
+            .map(|c| c.to_string())
+            .collect());
+    };
+
+    let colors: Vec<String> = scheme
+        .split(',')
+        .map(|c| {
+            c.trim()
+                .to_owned()
+        })
+        .collect();
+    if colors.len() != default.len() {
+        return Err(field_error(
+            "scheme",
+            format!("must contain {} colors", default.len()),
+        ));
+    }
+    if let Some(color) = colors
+        .iter()
+        .find(|c| !is_valid_color(c))
+    {
+        return Err(field_error("scheme", format!("invalid color `{}`", color)));
+    }
+
+    Ok(colors)
+}
+
+/// Renders the puzzle scrambled by `sequence` as an SVG image.
+///
+/// `scheme` is a comma separated list of colours, one per face, in the order used by the
+/// puzzle's renderer (U, R, F, D, L, B for cubes, Skewb and Square-1; F, L, R, D for Pyraminx;
+/// U, F, R, BR, BL, L, D, DR, DBR, B, DBL, DL for Megaminx; front, back for Clock).
+pub fn render(
+    kind: &ScrambleKind,
+    sequence: &str,
+    scheme: Option<&str>,
+) -> Result<String, AppError> {
+    let result = match kind {
+        ScrambleKind::Pyraminx => {
+            pyraminx::render(sequence, &color_scheme(scheme, &pyraminx::DEFAULT_SCHEME)?)
+        }
+        ScrambleKind::Skewb => {
+            skewb::render(sequence, &color_scheme(scheme, &cube::DEFAULT_SCHEME)?)
+        }
+        ScrambleKind::Megaminx => {
+            megaminx::render(sequence, &color_scheme(scheme, &megaminx::DEFAULT_SCHEME)?)
+        }
+        ScrambleKind::SquareOne => {
+            square_one::render(sequence, &color_scheme(scheme, &cube::DEFAULT_SCHEME)?)
+        }
+        ScrambleKind::Clock => {
+            clock::render(sequence, &color_scheme(scheme, &clock::DEFAULT_SCHEME)?)
+        }
+        cube_kind => {
+            let size = cube_kind
+                .cube_size()
+                .expect("every other kind is a cube");
+            cube::render(
+                size,
+                sequence,
+                &color_scheme(scheme, &cube::DEFAULT_SCHEME)?,
+            )
+        }
+    };
+
+    result.map_err(|err: ParseError| field_error("sequence", err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_every_kind() {
+        let sequences = [
+            (ScrambleKind::Two, "R U2 F'"),
+            (ScrambleKind::Three, "R U R' U' M2 x"),
+            (ScrambleKind::Four, "Rw U 2R'"),
+            (ScrambleKind::Five, "3Fw2 D"),
+            (ScrambleKind::Six, "3Uw' L2"),
+            (ScrambleKind::Seven, "3Bw 2D"),
+            (ScrambleKind::Pyraminx, "U L' R B u' l"),
+            (ScrambleKind::Skewb, "R U' L B"),
+            (ScrambleKind::Megaminx, "R++ D-- R-- D++ U'"),
+            (ScrambleKind::SquareOne, "(1,0)/ (-1,-1)/ (0,3)/"),
+            (ScrambleKind::Clock, "UR3+ DL2- y2 ALL6+ UR DL"),
+        ];
+
+        for (kind, sequence) in sequences {
+            let svg = render(&kind, sequence, None).unwrap();
+            assert!(svg.starts_with("<svg"), "{:?}", kind);
+            assert!(svg.ends_with("</svg>"), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_render_invalid_sequence() {
+        for kind in [
+            ScrambleKind::Three,
+            ScrambleKind::Pyraminx,
+            ScrambleKind::Skewb,
+            ScrambleKind::Megaminx,
+            ScrambleKind::SquareOne,
+            ScrambleKind::Clock,
+        ] {
+            assert!(matches!(
+                render(&kind, "R Q", None),
+                Err(AppError::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_color_scheme() {
+        let scheme = "#fff, red, #00ff00, yellow, orange, blue";
+        let colors = color_scheme(Some(scheme), &cube::DEFAULT_SCHEME).unwrap();
+        assert_eq!(colors[0], "#fff");
+        assert_eq!(colors[2], "#00ff00");
+
+        assert!(color_scheme(Some("red,blue"), &cube::DEFAULT_SCHEME).is_err());
+        assert!(color_scheme(Some("red\"/><script>,b,c,d,e,f"), &cube::DEFAULT_SCHEME).is_err());
+        assert!(color_scheme(Some("#ggg,b,c,d,e,f"), &cube::DEFAULT_SCHEME).is_err());
+    }
+
+    #[test]
+    fn test_scheme_colors_are_used() {
+        let svg = render(
+            &ScrambleKind::Two,
+            "",
+            Some("#111,#222,#333,#444,#555,#666"),
+        )
+        .unwrap();
+
+        for color in ["#111", "#222", "#333", "#444", "#555", "#666"] {
+            assert_eq!(
+                svg.matches(&format!("fill=\"{}\"", color))
+                    .count(),
+                4
+            );
+        }
+    }
+}
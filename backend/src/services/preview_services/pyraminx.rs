@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+use crate::services::notation::{
+    self,
+    pyraminx::{PyraminxMove, PyraminxNotation},
+    ParseError,
+};
+
+use super::{
+    geometry::{self, StickerPuzzle, Vector},
+    Point, Svg,
+};
+
+/// Colours of the F, L, R and D faces.
+pub const DEFAULT_SCHEME: [&str; 4] = ["green", "red", "blue", "yellow"];
+
+// Tetrahedron corners U (top), L (front left), R (front right) and B (back).
+const CORNERS: [Vector; 4] = [
+    [1.0, 1.0, 1.0],
+    [1.0, -1.0, -1.0],
+    [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0],
+];
+const U: usize = 0;
+const L: usize = 1;
+const R: usize = 2;
+const B: usize = 3;
+
+// Corners of the F, L, R and D faces, counterclockwise as seen from outside.
+const FACES: [[usize; 3]; 4] = [[U, L, R], [U, B, L], [U, R, B], [L, B, R]];
+
+const SIDE: f64 = 120.0;
+const GAP: f64 = 6.0;
+
+// Every face is split into 9 triangles, given in thirds along the edges from the first
+// corner of the face to the second and third ones.
+fn triangles() -> Vec<[(f64, f64); 3]> {
+    let mut triangles = Vec::new();
+    for i in 0..3 {
+        for j in 0..3 - i {
+            let (i, j) = (i as f64, j as f64);
+            triangles.push([(i, j), (i + 1.0, j), (i, j + 1.0)]);
+            if i + j < 2.0 {
+                triangles.push([(i + 1.0, j), (i + 1.0, j + 1.0), (i, j + 1.0)]);
+            }
+        }
+    }
+    triangles
+}
+
+fn on_face(corners: [Vector; 3], (i, j): (f64, f64)) -> Vector {
+    let [a, b, c] = corners;
+    geometry::add(
+        a,
+        geometry::add(
+            geometry::scale(geometry::sub(b, a), i / 3.0),
+            geometry::scale(geometry::sub(c, a), j / 3.0),
+        ),
+    )
+}
+
+fn reflect(p: Point, (ax, ay): Point, (bx, by): Point) -> Point {
+    let (dx, dy) = (bx - ax, by - ay);
+    let t = ((p.0 - ax) * dx + (p.1 - ay) * dy) / (dx * dx + dy * dy);
+    (2.0 * (ax + t * dx) - p.0, 2.0 * (ay + t * dy) - p.1)
+}
+
+/// Corners of every face in the net: F points up in the middle, and the other faces are
+/// folded out around it, so that D ends up below F.
+fn net() -> [[Point; 3]; 4] {
+    let height = SIDE * 3f64.sqrt() / 2.0;
+    let mut corners = [(0.0, 0.0); 4];
+    corners[U] = (SIDE + GAP, GAP);
+    corners[L] = (SIDE / 2.0 + GAP, GAP + height);
+    corners[R] = (1.5 * SIDE + GAP, GAP + height);
+    let center = (SIDE + GAP, GAP + height * 2.0 / 3.0);
+
+    FACES.map(|face| {
+        let mut points = face.map(|c| corners[c]);
+        let Some(b) = face
+            .iter()
+            .position(|&c| c == B)
+        else {
+            return points;
+        };
+
+        let (first, second) = (face[(b + 1) % 3], face[(b + 2) % 3]);
+        let opposite = [U, L, R]
+            .into_iter()
+            .find(|&c| c != first && c != second)
+            .expect("a face shares two corners with F");
+        points[b] = reflect(corners[opposite], corners[first], corners[second]);
+
+        // Leave a small gap between the folded out face and F.
+        let (dx, dy) = (
+            (corners[first].0 + corners[second].0) / 2.0 - center.0,
+            (corners[first].1 + corners[second].1) / 2.0 - center.1,
+        );
+        let length = (dx * dx + dy * dy).sqrt();
+        points.map(|(x, y)| (x + GAP * dx / length, y + GAP * dy / length))
+    })
+}
+
+fn solved() -> StickerPuzzle {
+    let mut centers = Vec::new();
+    let mut colors = Vec::new();
+    for (f, face) in FACES
+        .iter()
+        .enumerate()
+    {
+        let corners = face.map(|c| CORNERS[c]);
+        for triangle in triangles() {
+            let [a, b, c] = triangle.map(|p| on_face(corners, p));
+            centers.push(geometry::scale(
+                geometry::add(a, geometry::add(b, c)),
+                1.0 / 3.0,
+            ));
+            colors.push(f);
+        }
+    }
+    StickerPuzzle::new(centers, colors)
+}
+
+fn apply_sequence(puzzle: &mut StickerPuzzle, sequence: &str) -> Result<(), ParseError> {
+    // The cuts are at a third and two thirds of the way from a corner to the opposite face.
+    let tip_depth = 5.0 * 3f64.sqrt() / 9.0;
+    let layer_depth = 3f64.sqrt() / 9.0;
+
+    for PyraminxMove { corner, tip, prime } in notation::parse_moves(&PyraminxNotation, sequence)? {
+        let angle = if prime {
+            -2.0 * PI / 3.0
+        } else {
+            2.0 * PI / 3.0
+        };
+        let depth = if tip { tip_depth } else { layer_depth };
+        puzzle.turn(geometry::normalize(CORNERS[corner as usize]), depth, angle);
+    }
+    Ok(())
+}
+
+pub fn render(sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let mut puzzle = solved();
+    apply_sequence(&mut puzzle, sequence)?;
+
+    let height = SIDE * 3f64.sqrt() / 2.0;
+    let mut svg = Svg::new(2.0 * SIDE + 3.0 * GAP, 2.0 * height + 3.0 * GAP);
+    let stickers = net()
+        .into_iter()
+        .flat_map(|[a, b, c]| {
+            triangles()
+                .into_iter()
+                .map(move |triangle| {
+                    triangle.map(|(i, j)| {
+                        (
+                            a.0 + (b.0 - a.0) * i / 3.0 + (c.0 - a.0) * j / 3.0,
+                            a.1 + (b.1 - a.1) * i / 3.0 + (c.1 - a.1) * j / 3.0,
+                        )
+                    })
+                })
+        });
+    for (points, &color) in stickers.zip(puzzle.colors()) {
+        svg.polygon(&points, &colors[color]);
+    }
+
+    Ok(svg.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed_stickers(sequence: &str) -> usize {
+        let mut puzzle = solved();
+        apply_sequence(&mut puzzle, sequence).unwrap();
+        puzzle
+            .colors()
+            .iter()
+            .zip(solved().colors())
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    #[test]
+    fn test_turns() {
+        assert_eq!(changed_stickers("u"), 3);
+        assert_eq!(changed_stickers("U"), 12);
+        assert_eq!(changed_stickers("R R R"), 0);
+        assert_eq!(changed_stickers("L B' B L'"), 0);
+        assert_ne!(changed_stickers("R U R' U'"), 0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::f64::consts::PI;
+
+use crate::services::notation::{
+    self,
+    skewb::{SkewbCorner, SkewbMove, SkewbNotation},
+    ParseError,
+};
+
+use super::{
+    cube::{FACE_SIZE, GAP, NET},
+    geometry::{self, StickerPuzzle, Vector},
+    Svg,
+};
+
+// Outward normal, right and down directions of the U, R, F, D, L and B faces, oriented the
+// same way as the faces of the cube net.
+const FRAMES: [[Vector; 3]; 6] = [
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]],
+    [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+    [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+];
+
+// Stickers of a face in face coordinates from (-1, -1) at the top left to (1, 1) at the
+// bottom right: the centre square and the four corners.
+const STICKERS: [&[(f64, f64)]; 5] = [
+    &[(0.0, -1.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)],
+    &[(-1.0, -1.0), (0.0, -1.0), (-1.0, 0.0)],
+    &[(1.0, -1.0), (1.0, 0.0), (0.0, -1.0)],
+    &[(1.0, 1.0), (0.0, 1.0), (1.0, 0.0)],
+    &[(-1.0, 1.0), (-1.0, 0.0), (0.0, 1.0)],
+];
+
+/// Position of the corner each move turns around, following the WCA notation
+/// (R: DRB, U: ULB, L: DLF, B: DLB).
+fn corner_position(corner: SkewbCorner) -> Vector {
+    match corner {
+        SkewbCorner::R => [1.0, -1.0, -1.0],
+        SkewbCorner::U => [-1.0, 1.0, -1.0],
+        SkewbCorner::L => [-1.0, -1.0, 1.0],
+        SkewbCorner::B => [-1.0, -1.0, -1.0],
+    }
+}
+
+fn solved() -> StickerPuzzle {
+    let mut centers = Vec::new();
+    let mut colors = Vec::new();
+    for (f, [normal, right, down]) in FRAMES
+        .into_iter()
+        .enumerate()
+    {
+        for sticker in STICKERS {
+            let n = sticker.len() as f64;
+            let u: f64 = sticker
+                .iter()
+                .map(|p| p.0)
+                .sum::<f64>()
+                / n;
+            let v: f64 = sticker
+                .iter()
+                .map(|p| p.1)
+                .sum::<f64>()
+                / n;
+            centers.push(geometry::add(
+                normal,
+                geometry::add(geometry::scale(right, u), geometry::scale(down, v)),
+            ));
+            colors.push(f);
+        }
+    }
+    StickerPuzzle::new(centers, colors)
+}
+
+fn apply_sequence(puzzle: &mut StickerPuzzle, sequence: &str) -> Result<(), ParseError> {
+    for SkewbMove { corner, prime } in notation::parse_moves(&SkewbNotation, sequence)? {
+        let angle = if prime {
+            -2.0 * PI / 3.0
+        } else {
+            2.0 * PI / 3.0
+        };
+        puzzle.turn(geometry::normalize(corner_position(corner)), 0.0, angle);
+    }
+    Ok(())
+}
+
+pub fn render(sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let mut puzzle = solved();
+    apply_sequence(&mut puzzle, sequence)?;
+
+    let mut svg = Svg::new(4.0 * FACE_SIZE + 5.0 * GAP, 3.0 * FACE_SIZE + 4.0 * GAP);
+    let stickers = NET
+        .iter()
+        .flat_map(|&(column, row)| {
+            let x = GAP + column * (FACE_SIZE + GAP);
+            let y = GAP + row * (FACE_SIZE + GAP);
+            STICKERS.map(|sticker| {
+                sticker
+                    .iter()
+                    .map(|(u, v)| {
+                        (
+                            x + (u + 1.0) / 2.0 * FACE_SIZE,
+                            y + (v + 1.0) / 2.0 * FACE_SIZE,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+        });
+    for (points, &color) in stickers.zip(puzzle.colors()) {
+        svg.polygon(&points, &colors[color]);
+    }
+
+    Ok(svg.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed_stickers(sequence: &str) -> usize {
+        let mut puzzle = solved();
+        apply_sequence(&mut puzzle, sequence).unwrap();
+        puzzle
+            .colors()
+            .iter()
+            .zip(solved().colors())
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    #[test]
+    fn test_turns() {
+        assert_eq!(changed_stickers("R"), 15);
+        assert_eq!(changed_stickers("U U U"), 0);
+        assert_eq!(changed_stickers("R L' L R'"), 0);
+        assert_ne!(changed_stickers("R U R' U'"), 0);
+        assert!(apply_sequence(&mut solved(), "F").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::services::{
+    notation::ParseError,
+    scramble_services::square_one::{is_cut, Piece, SquareOne, LAYER_SLOTS},
+};
+
+use super::{Point, Svg};
+
+// Indices of the colour scheme, which is shared with the cubes.
+const U: usize = 0;
+const R: usize = 1;
+const F: usize = 2;
+const D: usize = 3;
+const L: usize = 4;
+const B: usize = 5;
+
+// Side colours of the pieces (see `Piece`), clockwise as seen from the top.
+const CORNER_SIDES: [[usize; 2]; 4] = [[B, R], [R, F], [F, L], [L, B]];
+const EDGE_SIDES: [usize; 4] = [R, F, L, B];
+
+// Half the width of a layer, and how far the side stickers stick out of it.
+const HALF_WIDTH: f64 = 60.0;
+const SIDE_SCALE: f64 = 1.2;
+const GAP: f64 = 10.0;
+const MIDDLE_HEIGHT: f64 = 24.0;
+
+fn polar((x, y): Point, radius: f64, degrees: f64) -> Point {
+    let (sin, cos) = degrees
+        .to_radians()
+        .sin_cos();
+    (x + radius * sin, y - radius * cos)
+}
+
+/// Draws a layer with the back at the top and the right on the right. The bottom layer is
+/// seen from below, as if the puzzle was tilted towards the viewer.
+fn draw_layer(
+    svg: &mut Svg,
+    center: Point,
+    layer: &[Piece; LAYER_SLOTS],
+    bottom: bool,
+    colors: &[String],
+) {
+    // Slots start 15 degrees off the back face, at the end of the slice.
+    let angle = |slot: usize| {
+        let degrees = slot as f64 * 30.0 + 15.0;
+        if bottom {
+            180.0 - degrees
+        } else {
+            degrees
+        }
+    };
+    let edge_radius = HALF_WIDTH
+        / 15f64
+            .to_radians()
+            .cos();
+    let corner_radius = HALF_WIDTH * 2f64.sqrt();
+    let side = |points: [(f64, f64); 2]| {
+        let [(r1, a1), (r2, a2)] = points;
+        [
+            polar(center, r1, a1),
+            polar(center, r2, a2),
+            polar(center, r2 * SIDE_SCALE, a2),
+            polar(center, r1 * SIDE_SCALE, a1),
+        ]
+    };
+
+    for slot in (0..LAYER_SLOTS).filter(|&slot| is_cut(layer, slot)) {
+        let (a0, a1, a2) = (angle(slot), angle(slot + 1), angle(slot + 2));
+        match layer[slot] {
+            Piece::Edge(id) => {
+                let cap = if id < 4 { U } else { D };
+                svg.polygon(
+                    &[
+                        center,
+                        polar(center, edge_radius, a0),
+                        polar(center, edge_radius, a1),
+                    ],
+                    &colors[cap],
+                );
+                svg.polygon(
+                    &side([(edge_radius, a0), (edge_radius, a1)]),
+                    &colors[EDGE_SIDES[id as usize % 4]],
+                );
+            }
+            Piece::Corner(id) => {
+                let cap = if id < 4 { U } else { D };
+                let [first, second] = CORNER_SIDES[id as usize % 4];
+                svg.polygon(
+                    &[
+                        center,
+                        polar(center, edge_radius, a0),
+                        polar(center, corner_radius, a1),
+                        polar(center, edge_radius, a2),
+                    ],
+                    &colors[cap],
+                );
+                svg.polygon(
+                    &side([(edge_radius, a0), (corner_radius, a1)]),
+                    &colors[first],
+                );
+                svg.polygon(
+                    &side([(corner_radius, a1), (edge_radius, a2)]),
+                    &colors[second],
+                );
+            }
+        }
+    }
+}
+
+pub fn render(sequence: &str, colors: &[String]) -> Result<String, ParseError> {
+    let puzzle = SquareOne::from_sequence(sequence)?;
+    let extent = HALF_WIDTH * 2f64.sqrt() * SIDE_SCALE;
+    let mut svg = Svg::new(
+        4.0 * extent + 3.0 * GAP,
+        2.0 * extent + MIDDLE_HEIGHT + 3.0 * GAP,
+    );
+
+    let y = GAP + extent;
+    draw_layer(&mut svg, (GAP + extent, y), puzzle.top(), false, colors);
+    draw_layer(
+        &mut svg,
+        (2.0 * GAP + 3.0 * extent, y),
+        puzzle.bottom(),
+        true,
+        colors,
+    );
+
+    // The middle layer seen from the front, its right half shows the back colour when flipped.
+    let (left, top) = (GAP + extent, 2.0 * GAP + 2.0 * extent);
+    let width = GAP + 2.0 * extent;
+    let right = if puzzle.middle_flipped() { B } else { F };
+    for (x, color) in [(left, F), (left + width / 2.0, right)] {
+        svg.polygon(
+            &[
+                (x, top),
+                (x + width / 2.0, top),
+                (x + width / 2.0, top + MIDDLE_HEIGHT),
+                (x, top + MIDDLE_HEIGHT),
+            ],
+            &colors[color],
+        );
+    }
+
+    Ok(svg.finish())
+}
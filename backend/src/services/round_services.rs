@@ -0,0 +1,637 @@
+use std::sync::Arc;
+
+use mongodb::{
+    bson::{doc, to_bson, Uuid},
+    results::UpdateResult,
+    Collection,
+};
+use serde::Serialize;
+
+use crate::{
+    error::AppError,
+    models::{
+        event::{Advancement, Attempt, Event, ResultChange, Round, RoundResult},
+        session::Penalty,
+    },
+    AppState,
+};
+
+use super::{
+    get_collection,
+    stats_services::{self, SolveResult},
+    Collections,
+};
+
+/// Result of a competitor in a round, ranked the way the WCA does.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RankedResult {
+    /// Competitors with the same results share a rank.
+    pub rank: usize,
+    pub account_id: Uuid,
+    /// The attempts as they count, with their penalty and the time limit applied.
+    pub attempts: Vec<SolveResult>,
+    pub best: SolveResult,
+    /// Mean or average of the round, `None` for a best of format, a competitor who didn't make
+    /// the cutoff, or until every attempt is in.
+    pub average: Option<SolveResult>,
+    pub made_cutoff: bool,
+    /// Whether the competitor goes on to the next round of the puzzle.
+    pub advances: bool,
+}
+
+/// Results of the attempts of a round, the ones which reach the time limit being DNFs. A
+/// cumulative limit counts the time of DNFs too.
+pub fn counted_attempts(round: &Round, attempts: &[Attempt]) -> Vec<SolveResult> {
+    let mut total: u64 = 0;
+    attempts
+        .iter()
+        .map(|attempt| {
+            let result = attempt.result();
+            let Some(limit) = round.time_limit else {
+                return result.map_or(SolveResult::Dnf, SolveResult::Millis);
+            };
+            total = total.saturating_add(result.unwrap_or(attempt.millis));
+            let spent = if limit.cumulative {
+                total
+            } else {
+                result.unwrap_or(attempt.millis)
+            };
+            match result {
+                Some(millis) if spent < limit.millis => SolveResult::Millis(millis),
+                _ => SolveResult::Dnf,
+            }
+        })
+        .collect()
+}
+
+/// Ranks the competitors of a round: by average for means and averages, those who made the
+/// cutoff first, then by best attempt.
+pub fn rank(round: &Round) -> Vec<RankedResult> {
+    let attempt_count = round
+        .format
+        .attempt_count();
+
+    let mut ranked: Vec<RankedResult> = round
+        .results
+        .iter()
+        .map(|result| {
+            let mut attempts = counted_attempts(round, &result.attempts);
+            attempts.truncate(attempt_count);
+
+            let made_cutoff = round
+                .cutoff
+                .is_none_or(|cutoff| {
+                    attempts
+                        .iter()
+                        .take(cutoff.attempts)
+                        .any(|&attempt| attempt < SolveResult::Millis(cutoff.millis))
+                });
+            if let (false, Some(cutoff)) = (made_cutoff, round.cutoff) {
+                attempts.truncate(cutoff.attempts);
+            }
+
+            let complete = made_cutoff && attempts.len() == attempt_count;
+            let average = match round.format {
+                _ if !complete => None,
+                format if !format.is_average() => None,
+                format if format.attempt_count() == 3 => Some(stats_services::mean(&attempts)),
+                _ => Some(stats_services::average(&attempts)),
+            };
+
+            RankedResult {
+                rank: 0,
+                account_id: result.account_id,
+                best: attempts
+                    .iter()
+                    .min()
+                    .copied()
+                    .unwrap_or(SolveResult::Dnf),
+                attempts,
+                average,
+                made_cutoff,
+                advances: false,
+            }
+        })
+        .collect();
+
+    let key = |result: &RankedResult| {
+        (
+            !result.made_cutoff,
+            result
+                .average
+                .is_none(),
+            result.average,
+            result.best,
+        )
+    };
+    ranked.sort_by_key(key);
+
+    // The WCA lets at most 75% of the competitors advance, however many the round asks for.
+    let advancing = match round.advancement {
+        Some(Advancement::Top(count)) => count,
+        Some(Advancement::Percent(percent)) => ranked.len() * percent / 100,
+        None => 0,
+    }
+    .min(ranked.len() * 3 / 4);
+    let mut previous = None;
+    for (i, result) in ranked
+        .iter_mut()
+        .enumerate()
+    {
+        let current = key(result);
+        result.rank = match previous {
+            Some((rank, previous)) if previous == current => rank,
+            _ => i + 1,
+        };
+        result.advances = result.rank <= advancing && result.best != SolveResult::Dnf;
+        previous = Some((result.rank, current));
+    }
+    ranked
+}
+
+/// Checks that the attempts can be entered for a round: no more than its format has, none past
+/// the cutoff unless it was made, and the ones reaching the time limit entered as DNFs.
+pub fn check_attempts(round: &Round, attempts: &[Attempt]) -> Result<(), String> {
+    let attempt_count = round
+        .format
+        .attempt_count();
+    if attempts.len() > attempt_count {
+        return Err(format!("the round has {} attempts", attempt_count));
+    }
+
+    let counted = counted_attempts(round, attempts);
+    for (i, (attempt, result)) in attempts
+        .iter()
+        .zip(&counted)
+        .enumerate()
+    {
+        if attempt.penalty != Penalty::Dnf {
+            if attempt.millis == 0 {
+                return Err(format!("attempt {} has no time", i + 1));
+            }
+            if *result == SolveResult::Dnf {
+                return Err(format!(
+                    "attempt {} reaches the time limit, it must be a DNF",
+                    i + 1
+                ));
+            }
+        }
+    }
+
+    if let Some(cutoff) = round.cutoff {
+        let made_cutoff = counted
+            .iter()
+            .take(cutoff.attempts)
+            .any(|&result| result < SolveResult::Millis(cutoff.millis));
+        if !made_cutoff && attempts.len() > cutoff.attempts {
+            return Err(format!(
+                "the cutoff wasn't made in the first {} attempts",
+                cutoff.attempts
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the account may compete in the round: participants of the event in the first round
+/// of a puzzle, those who advanced from the previous round in the next ones.
+pub fn may_compete(event: &Event, round: &Round, account_id: Uuid) -> bool {
+    let previous = event
+        .rounds
+        .iter()
+        .take_while(|other| other.id != round.id)
+        .filter(|other| other.kind == round.kind)
+        .last();
+    match previous {
+        None => event
+            .participants
+            .contains(&account_id),
+        Some(previous) => rank(previous)
+            .iter()
+            .any(|result| result.account_id == account_id && result.advances),
+    }
+}
+
+fn change(account_id: Uuid, moderator_id: Uuid, attempts: Option<&[Attempt]>) -> ResultChange {
+    ResultChange {
+        account_id,
+        moderator_id,
+        changed_at: chrono::Utc::now().timestamp_millis(),
+        attempts: attempts.map(<[Attempt]>::to_vec),
+    }
+}
+
+/// Enters the attempts of a competitor, recording the change in the audit of the round. A result
+/// already entered is only replaced when it still has the attempts given as `replaced`, and a new
+/// one is only added when there is none, matching nothing otherwise.
+pub async fn set_result(
+    state: &Arc<AppState>,
+    id: Uuid,
+    round_id: Uuid,
+    account_id: Uuid,
+    moderator_id: Uuid,
+    attempts: &[Attempt],
+    replaced: Option<&[Attempt]>,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let change = to_bson(&change(account_id, moderator_id, Some(attempts)))?;
+
+    let result = if let Some(replaced) = replaced {
+        events
+            .update_one(
+                doc! { "_id": id, "rounds": { "$elemMatch": {
+                    "id": round_id,
+                    "results": { "$elemMatch": {
+                        "account_id": account_id,
+                        "attempts": to_bson(replaced)?,
+                    } },
+                } } },
+                doc! {
+                    "$set": { "rounds.$[round].results.$[result].attempts": to_bson(attempts)? },
+                    "$push": { "rounds.$[round].audit": change },
+                },
+            )
+            .array_filters(vec![
+                doc! { "round.id": round_id },
+                doc! { "result.account_id": account_id },
+            ])
+            .await?
+    } else {
+        let result = RoundResult {
+            account_id,
+            attempts: attempts.to_vec(),
+        };
+        events
+            .update_one(
+                doc! { "_id": id, "rounds": { "$elemMatch": {
+                    "id": round_id,
+                    "results.account_id": { "$ne": account_id },
+                } } },
+                doc! { "$push": {
+                    "rounds.$[round].results": to_bson(&result)?,
+                    "rounds.$[round].audit": change,
+                } },
+            )
+            .array_filters(vec![doc! { "round.id": round_id }])
+            .await?
+    };
+
+    Ok(result)
+}
+
+/// Deletes the result of a competitor, recording the change in the audit of the round.
+pub async fn delete_result(
+    state: &Arc<AppState>,
+    id: Uuid,
+    round_id: Uuid,
+    account_id: Uuid,
+    moderator_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id, "rounds": { "$elemMatch": {
+                "id": round_id,
+                "results.account_id": account_id,
+            } } },
+            doc! {
+                "$pull": { "rounds.$[round].results": { "account_id": account_id } },
+                "$push": { "rounds.$[round].audit": to_bson(&change(account_id, moderator_id, None))? },
+            },
+        )
+        .array_filters(vec![doc! { "round.id": round_id }])
+        .await?;
+
+    Ok(result)
+}
+
+/// Adds the round after the rounds of its puzzle already in the event.
+pub async fn insert(
+    state: &Arc<AppState>,
+    id: Uuid,
+    round: &Round,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$push": { "rounds": to_bson(round)? } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_by_id(
+    state: &Arc<AppState>,
+    id: Uuid,
+    round_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let events: Collection<Event> = get_collection(state, Collections::EVENTS);
+    let result = events
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$pull": { "rounds": { "id": round_id } } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use validator::Validate;
+
+    use super::*;
+    use crate::{
+        models::event::{Cutoff, RoundFormat, TimeLimit},
+        routes::scrambles::ScrambleKind,
+    };
+
+    fn attempt(millis: u64) -> Attempt {
+        Attempt {
+            millis,
+            penalty: Penalty::None,
+        }
+    }
+
+    fn dnf() -> Attempt {
+        Attempt {
+            millis: 30000,
+            penalty: Penalty::Dnf,
+        }
+    }
+
+    fn round(format: RoundFormat, results: Vec<Vec<Attempt>>) -> Round {
+        Round {
+            id: Uuid::new(),
+            kind: ScrambleKind::Three,
+            format,
+            cutoff: None,
+            time_limit: None,
+            advancement: None,
+            audit: Vec::new(),
+            results: results
+                .into_iter()
+                .map(|attempts| RoundResult {
+                    account_id: Uuid::new(),
+                    attempts,
+                })
+                .collect(),
+        }
+    }
+
+    fn summary(ranked: &[RankedResult]) -> Vec<(usize, Option<SolveResult>, SolveResult)> {
+        ranked
+            .iter()
+            .map(|result| (result.rank, result.average, result.best))
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_average_of_5() {
+        let ms = SolveResult::Millis;
+        let round = round(
+            RoundFormat::Ao5,
+            vec![
+                vec![
+                    attempt(10000),
+                    attempt(11000),
+                    attempt(12000),
+                    attempt(13000),
+                    dnf(),
+                ],
+                vec![attempt(9000), attempt(12000), attempt(12000), dnf(), dnf()],
+                vec![
+                    attempt(8000),
+                    attempt(14000),
+                    attempt(12000),
+                    attempt(13000),
+                    attempt(7000),
+                ],
+                vec![attempt(10000), attempt(12000)],
+            ],
+        );
+
+        assert_eq!(
+            summary(&rank(&round)),
+            vec![
+                (1, Some(ms(11000)), ms(7000)),
+                (2, Some(ms(12000)), ms(10000)),
+                (3, Some(SolveResult::Dnf), ms(9000)),
+                (4, None, ms(10000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_mean_and_best_of() {
+        let ms = SolveResult::Millis;
+        let mean = round(
+            RoundFormat::Mo3,
+            vec![
+                vec![attempt(5000), attempt(6000), dnf()],
+                vec![attempt(10000), attempt(11000), attempt(12001)],
+            ],
+        );
+        assert_eq!(
+            summary(&rank(&mean)),
+            vec![
+                (1, Some(ms(11000)), ms(10000)),
+                (2, Some(SolveResult::Dnf), ms(5000)),
+            ]
+        );
+
+        let best = round(
+            RoundFormat::Bo3,
+            vec![
+                vec![dnf(), attempt(9000), attempt(12000)],
+                vec![attempt(8000)],
+                vec![attempt(9000), dnf(), dnf()],
+                vec![dnf()],
+            ],
+        );
+        assert_eq!(
+            summary(&rank(&best)),
+            vec![
+                (1, None, ms(8000)),
+                (2, None, ms(9000)),
+                (2, None, ms(9000)),
+                (4, None, SolveResult::Dnf),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cutoff_and_advancement() {
+        let mut round = round(
+            RoundFormat::Ao5,
+            vec![
+                vec![
+                    attempt(25000),
+                    attempt(19000),
+                    attempt(21000),
+                    attempt(22000),
+                    attempt(23000),
+                ],
+                vec![attempt(21000), attempt(20000), attempt(9000)],
+                vec![
+                    attempt(18000),
+                    attempt(30000),
+                    attempt(30000),
+                    attempt(30000),
+                    attempt(30000),
+                ],
+                vec![dnf(), dnf()],
+            ],
+        );
+        round.cutoff = Some(Cutoff {
+            attempts: 2,
+            millis: 20000,
+        });
+        round.advancement = Some(Advancement::Percent(75));
+
+        let ranked = rank(&round);
+        assert!(ranked[0].made_cutoff);
+        assert_eq!(ranked[0].average, Some(SolveResult::Millis(22000)));
+        assert_eq!(ranked[1].average, Some(SolveResult::Millis(30000)));
+        // Attempts past the cutoff don't count when it wasn't made.
+        assert!(!ranked[2].made_cutoff);
+        assert_eq!(
+            ranked[2]
+                .attempts
+                .len(),
+            2
+        );
+        assert_eq!(ranked[2].best, SolveResult::Millis(20000));
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|result| result.advances)
+                .collect::<Vec<_>>(),
+            vec![true, true, true, false]
+        );
+
+        round.advancement = Some(Advancement::Top(1));
+        let ranked = rank(&round);
+        assert!(ranked[0].advances && !ranked[1].advances);
+
+        round.advancement = Some(Advancement::Top(4));
+        round.results[3].attempts = vec![attempt(10000), attempt(10000)];
+        assert_eq!(
+            rank(&round)
+                .iter()
+                .filter(|result| result.advances)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_time_limits() {
+        let mut round = round(RoundFormat::Mo3, vec![]);
+        let attempts = [
+            attempt(50000),
+            Attempt {
+                millis: 59000,
+                penalty: Penalty::PlusTwo,
+            },
+            attempt(30000),
+        ];
+
+        round.time_limit = Some(TimeLimit {
+            millis: 60000,
+            cumulative: false,
+        });
+        assert_eq!(
+            counted_attempts(&round, &attempts),
+            vec![
+                SolveResult::Millis(50000),
+                SolveResult::Dnf,
+                SolveResult::Millis(30000)
+            ]
+        );
+
+        round.time_limit = Some(TimeLimit {
+            millis: 120000,
+            cumulative: true,
+        });
+        assert_eq!(
+            counted_attempts(
+                &round,
+                &[attempt(50000), dnf(), attempt(30000), attempt(20000)]
+            ),
+            vec![
+                SolveResult::Millis(50000),
+                SolveResult::Dnf,
+                SolveResult::Millis(30000),
+                SolveResult::Dnf
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_attempts() {
+        let mut round = round(RoundFormat::Ao5, vec![]);
+        round.cutoff = Some(Cutoff {
+            attempts: 2,
+            millis: 20000,
+        });
+        round.time_limit = Some(TimeLimit {
+            millis: 60000,
+            cumulative: false,
+        });
+
+        assert!(check_attempts(&round, &[attempt(25000), attempt(19000), attempt(21000)]).is_ok());
+        assert!(check_attempts(&round, &[attempt(25000), attempt(21000)]).is_ok());
+        assert!(check_attempts(&round, &[attempt(25000), attempt(21000), attempt(9000)]).is_err());
+        assert!(check_attempts(&round, &[attempt(10000), attempt(60000)]).is_err());
+        assert!(check_attempts(&round, &[attempt(10000), dnf()]).is_ok());
+        assert!(check_attempts(&round, &[attempt(0)]).is_err());
+        assert!(check_attempts(&round, &[attempt(10000); 6]).is_err());
+    }
+
+    #[test]
+    fn test_may_compete() {
+        let creator = Uuid::new();
+        let mut event = Event::new("Open", "", 0, creator, false);
+        let mut first = round(
+            RoundFormat::Bo1,
+            vec![vec![attempt(10000)], vec![attempt(12000)]],
+        );
+        first.advancement = Some(Advancement::Top(1));
+        let (winner, loser) = (first.results[0].account_id, first.results[1].account_id);
+        event.participants = vec![winner, loser];
+        let second = round(RoundFormat::Bo1, vec![]);
+        let mut other = round(RoundFormat::Bo1, vec![]);
+        other.kind = ScrambleKind::Two;
+        event.rounds = vec![first.clone(), other.clone(), second.clone()];
+
+        assert!(may_compete(&event, &first, loser));
+        assert!(!may_compete(&event, &first, creator));
+        assert!(may_compete(&event, &other, loser));
+        assert!(may_compete(&event, &second, winner));
+        assert!(!may_compete(&event, &second, loser));
+    }
+
+    #[test]
+    fn test_attempts_out_of_range() {
+        let huge = Attempt {
+            millis: u64::MAX,
+            penalty: Penalty::PlusTwo,
+        };
+        assert!(huge
+            .validate()
+            .is_err());
+
+        let mut round = round(RoundFormat::Ao5, vec![]);
+        round.time_limit = Some(TimeLimit {
+            millis: 600000,
+            cumulative: true,
+        });
+        assert_eq!(
+            counted_attempts(&round, &[huge, attempt(u64::MAX)]),
+            vec![SolveResult::Dnf, SolveResult::Dnf]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use rand::Rng;
+
+// Pin configurations in the order their dials are turned, first on the front side, then after
+// a y2 flip on the back side.
+const FRONT_DIALS: [&str; 9] = ["UR", "DR", "DL", "UL", "U", "R", "D", "L", "ALL"];
+const BACK_DIALS: [&str; 5] = ["U", "R", "D", "L", "ALL"];
+const PINS: [&str; 4] = ["UR", "DR", "DL", "UL"];
+
+fn dial_turn<R: Rng + ?Sized>(rng: &mut R) -> String {
+    match rng.gen_range(-5..=6) {
+        amount if amount >= 0 => format!("{}+", amount),
+        amount => format!("{}-", -amount),
+    }
+}
+
+/// Generates a WCA Clock scramble: a dial turn for every pin configuration on both sides,
+/// followed by the pins that should be left up.
+pub fn generate<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let mut scramble = Vec::new();
+
+    for pins in FRONT_DIALS {
+        scramble.push(format!("{}{}", pins, dial_turn(rng)));
+    }
+    scramble.push("y2".to_owned());
+    for pins in BACK_DIALS {
+        scramble.push(format!("{}{}", pins, dial_turn(rng)));
+    }
+
+    for pin in PINS {
+        if rng.gen_bool(0.5) {
+            scramble.push(pin.to_owned());
+        }
+    }
+
+    scramble.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_dial_turn(move_str: &str, pins: &str) {
+        let turn = move_str
+            .strip_prefix(pins)
+            .unwrap();
+        let (amount, direction) = turn.split_at(turn.len() - 1);
+        let amount: i32 = amount
+            .parse()
+            .unwrap();
+
+        match direction {
+            "+" => assert!((0..=6).contains(&amount)),
+            "-" => assert!((1..=5).contains(&amount)),
+            _ => panic!("invalid dial turn: {}", move_str),
+        }
+    }
+
+    #[test]
+    fn test_generate_clock_valid_notation() {
+        let scramble = generate(&mut rand::thread_rng());
+        let moves: Vec<&str> = scramble
+            .split_whitespace()
+            .collect();
+        let dials = FRONT_DIALS.len() + 1 + BACK_DIALS.len();
+
+        for (move_str, pins) in moves
+            .iter()
+            .zip(FRONT_DIALS)
+        {
+            assert_dial_turn(move_str, pins);
+        }
+        assert_eq!(moves[FRONT_DIALS.len()], "y2");
+        for (move_str, pins) in moves[FRONT_DIALS.len() + 1..dials]
+            .iter()
+            .zip(BACK_DIALS)
+        {
+            assert_dial_turn(move_str, pins);
+        }
+
+        let pins = &moves[dials..];
+        let expected: Vec<&str> = PINS
+            .into_iter()
+            .filter(|pin| pins.contains(pin))
+            .collect();
+        assert_eq!(pins, expected);
+    }
+}
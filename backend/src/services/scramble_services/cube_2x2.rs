@@ -0,0 +1,324 @@
+use rand::Rng;
+
+// Corner order: URF, UFL, ULB, UBR, DFR, DLF, DRB, DBL.
+// The DBL corner is never moved (only R, U and F turns are used),
+// so every state is fully described by the first 7 corners.
+const N_CORNERS: usize = 8;
+const N_PERM: usize = 5040; // 7!
+const N_TWIST: usize = 729; // 3^6
+const N_MOVES: usize = 9;
+
+const FACES: [&str; 3] = ["R", "U", "F"];
+const POWERS: [&str; 3] = ["", "2", "'"];
+
+// WCA Regulation 4b3: 2x2x2 scrambles must require at least 4 moves to solve.
+const MIN_SOLUTION_LENGTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CornerCube {
+    cp: [u8; N_CORNERS],
+    co: [u8; N_CORNERS],
+}
+
+const SOLVED: CornerCube = CornerCube {
+    cp: [0, 1, 2, 3, 4, 5, 6, 7],
+    co: [0; N_CORNERS],
+};
+
+const BASIC_MOVES: [CornerCube; 3] = [
+    // R
+    CornerCube {
+        cp: [4, 1, 2, 0, 6, 5, 3, 7],
+        co: [2, 0, 0, 1, 1, 0, 2, 0],
+    },
+    // U
+    CornerCube {
+        cp: [3, 0, 1, 2, 4, 5, 6, 7],
+        co: [0; N_CORNERS],
+    },
+    // F
+    CornerCube {
+        cp: [1, 5, 2, 3, 0, 4, 6, 7],
+        co: [1, 2, 0, 0, 2, 1, 0, 0],
+    },
+];
+
+impl CornerCube {
+    fn multiply(&self, other: &CornerCube) -> CornerCube {
+        let mut result = SOLVED;
+        for i in 0..N_CORNERS {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        result
+    }
+
+    fn perm_coord(&self) -> usize {
+        let mut coord = 0;
+        for i in 0..7 {
+            let smaller = (i + 1..7)
+                .filter(|&j| self.cp[j] < self.cp[i])
+                .count();
+            coord = coord * (7 - i) + smaller;
+        }
+        coord
+    }
+
+    fn twist_coord(&self) -> usize {
+        self.co[..6]
+            .iter()
+            .fold(0, |acc, &o| acc * 3 + o as usize)
+    }
+
+    fn from_coords(perm: usize, twist: usize) -> CornerCube {
+        let mut cube = SOLVED;
+
+        let mut digits = [0; 7];
+        let mut rest = perm;
+        for i in (0..7).rev() {
+            digits[i] = rest % (7 - i);
+            rest /= 7 - i;
+        }
+        let mut pool: Vec<u8> = (0..7).collect();
+        for (i, digit) in digits
+            .into_iter()
+            .enumerate()
+        {
+            cube.cp[i] = pool.remove(digit);
+        }
+
+        let mut rest = twist;
+        let mut sum = 0;
+        for i in (0..6).rev() {
+            cube.co[i] = (rest % 3) as u8;
+            sum += cube.co[i];
+            rest /= 3;
+        }
+        cube.co[6] = (3 - sum % 3) % 3;
+        cube
+    }
+}
+
+/// Move and pruning tables for the permutation and orientation of the corners.
+pub struct Tables {
+    perm_move: Vec<[u16; N_MOVES]>,
+    twist_move: Vec<[u16; N_MOVES]>,
+    perm_prune: Vec<u8>,
+    twist_prune: Vec<u8>,
+}
+
+impl Tables {
+    pub fn build() -> Tables {
+        let mut moves = Vec::with_capacity(N_MOVES);
+        for basic in BASIC_MOVES {
+            let mut power = basic;
+            for _ in 0..3 {
+                moves.push(power);
+                power = power.multiply(&basic);
+            }
+        }
+
+        let mut perm_move = vec![[0; N_MOVES]; N_PERM];
+        for (perm, row) in perm_move
+            .iter_mut()
+            .enumerate()
+        {
+            let cube = CornerCube::from_coords(perm, 0);
+            for (m, mv) in moves
+                .iter()
+                .enumerate()
+            {
+                row[m] = cube
+                    .multiply(mv)
+                    .perm_coord() as u16;
+            }
+        }
+
+        let mut twist_move = vec![[0; N_MOVES]; N_TWIST];
+        for (twist, row) in twist_move
+            .iter_mut()
+            .enumerate()
+        {
+            let cube = CornerCube::from_coords(0, twist);
+            for (m, mv) in moves
+                .iter()
+                .enumerate()
+            {
+                row[m] = cube
+                    .multiply(mv)
+                    .twist_coord() as u16;
+            }
+        }
+
+        let perm_prune = prune_table(&perm_move);
+        let twist_prune = prune_table(&twist_move);
+
+        Tables {
+            perm_move,
+            twist_move,
+            perm_prune,
+            twist_prune,
+        }
+    }
+}
+
+fn prune_table(move_table: &[[u16; N_MOVES]]) -> Vec<u8> {
+    let mut distances = vec![u8::MAX; move_table.len()];
+    distances[0] = 0;
+    let mut frontier = vec![0];
+    let mut depth = 0;
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for coord in frontier {
+            for &target in &move_table[coord] {
+                let target = target as usize;
+                if distances[target] == u8::MAX {
+                    distances[target] = depth + 1;
+                    next.push(target);
+                }
+            }
+        }
+        frontier = next;
+        depth += 1;
+    }
+
+    distances
+}
+
+fn search(
+    tables: &Tables,
+    perm: usize,
+    twist: usize,
+    depth: usize,
+    solution: &mut Vec<usize>,
+) -> bool {
+    if depth == 0 {
+        return perm == 0 && twist == 0;
+    }
+    let bound = tables.perm_prune[perm].max(tables.twist_prune[twist]) as usize;
+    if bound > depth {
+        return false;
+    }
+
+    for m in 0..N_MOVES {
+        if solution
+            .last()
+            .is_some_and(|last| last / 3 == m / 3)
+        {
+            continue;
+        }
+        solution.push(m);
+        let next_perm = tables.perm_move[perm][m] as usize;
+        let next_twist = tables.twist_move[twist][m] as usize;
+        if search(tables, next_perm, next_twist, depth - 1, solution) {
+            return true;
+        }
+        solution.pop();
+    }
+
+    false
+}
+
+/// Finds an optimal solution (in the half turn metric) for the given coordinates.
+/// Moves are indexed as `face * 3 + power`, following `FACES` and `POWERS`.
+fn solve(tables: &Tables, perm: usize, twist: usize) -> Vec<usize> {
+    let mut solution = Vec::new();
+    for depth in 0.. {
+        if search(tables, perm, twist, depth, &mut solution) {
+            break;
+        }
+    }
+    solution
+}
+
+fn format_moves(moves: impl Iterator<Item = usize>) -> String {
+    moves
+        .map(|m| format!("{}{}", FACES[m / 3], POWERS[m % 3]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Picks a uniformly random 2x2x2 state and returns the inverse of its optimal solution,
+/// so the scramble brings a solved cube into that state.
+pub fn generate<R: Rng + ?Sized>(tables: &Tables, rng: &mut R) -> String {
+    loop {
+        let perm = rng.gen_range(0..N_PERM);
+        let twist = rng.gen_range(0..N_TWIST);
+        let solution = solve(tables, perm, twist);
+
+        if solution.len() >= MIN_SOLUTION_LENGTH {
+            let inverse = solution
+                .iter()
+                .rev()
+                .map(|m| m / 3 * 3 + (2 - m % 3));
+            return format_moves(inverse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+
+    static TABLES: LazyLock<Tables> = LazyLock::new(Tables::build);
+
+    fn apply(sequence: &str) -> CornerCube {
+        sequence
+            .split_whitespace()
+            .fold(SOLVED, |cube, token| {
+                let face = FACES
+                    .iter()
+                    .position(|f| token.starts_with(f))
+                    .unwrap();
+                let power = POWERS
+                    .iter()
+                    .position(|p| &token[1..] == *p)
+                    .unwrap();
+                (0..=power).fold(cube, |acc, _| acc.multiply(&BASIC_MOVES[face]))
+            })
+    }
+
+    #[test]
+    fn test_coords_roundtrip() {
+        for (perm, twist) in [(0, 0), (1234, 56), (N_PERM - 1, N_TWIST - 1)] {
+            let cube = CornerCube::from_coords(perm, twist);
+            assert_eq!(cube.perm_coord(), perm);
+            assert_eq!(cube.twist_coord(), twist);
+        }
+    }
+
+    #[test]
+    fn test_basic_moves_have_order_four() {
+        for basic in BASIC_MOVES {
+            let cube = (0..4).fold(SOLVED, |acc, _| acc.multiply(&basic));
+            assert_eq!(cube, SOLVED);
+        }
+    }
+
+    #[test]
+    fn test_solution_solves_state() {
+        let state = apply("R U2 F' R' U F2 R2 U'");
+        let solution = solve(&TABLES, state.perm_coord(), state.twist_coord());
+        let solved = apply(&format!(
+            "R U2 F' R' U F2 R2 U' {}",
+            format_moves(solution.into_iter())
+        ));
+
+        assert_eq!(solved, SOLVED);
+    }
+
+    #[test]
+    fn test_generate_scramble_reaches_unsolved_state() {
+        let scramble = generate(&TABLES, &mut rand::thread_rng());
+        let moves = scramble
+            .split_whitespace()
+            .count();
+
+        assert!((MIN_SOLUTION_LENGTH..=11).contains(&moves));
+        assert_ne!(apply(&scramble), SOLVED);
+    }
+}
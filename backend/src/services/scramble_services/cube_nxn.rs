@@ -0,0 +1,168 @@
+use rand::{seq::SliceRandom, Rng};
+
+// Faces are grouped in pairs sharing the same axis: (U, D), (R, L), (F, B).
+const FACES: [&str; 6] = ["U", "D", "R", "L", "F", "B"];
+const MODIFIERS: [&str; 3] = ["", "'", "2"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Turn {
+    face: usize,
+    width: usize,
+}
+
+impl Turn {
+    fn axis(&self) -> usize {
+        self.face / 2
+    }
+
+    fn notation(&self) -> String {
+        match self.width {
+            1 => FACES[self.face].to_owned(),
+            2 => format!("{}w", FACES[self.face]),
+            w => format!("{}{}w", w, FACES[self.face]),
+        }
+    }
+}
+
+/// All turns available on an `size`x`size`x`size` cube. Wide turns go up to half of the cube,
+/// and on even cubes the middle-deep turn only exists for U, R and F, since turning it from the
+/// opposite face would be the same as a cube rotation followed by the other turn.
+fn available_turns(size: usize) -> Vec<Turn> {
+    let max_width = size / 2;
+    let mut turns = Vec::new();
+
+    for face in 0..FACES.len() {
+        for width in 1..=max_width {
+            if size.is_multiple_of(2) && width == max_width && face % 2 == 1 {
+                continue;
+            }
+            turns.push(Turn { face, width });
+        }
+    }
+
+    turns
+}
+
+/// Generates a random move scramble, rejecting turns that could be merged or cancelled with
+/// earlier turns on the same axis (`R R'`, `R L R`, `Rw L Rw2`...).
+pub fn generate<R: Rng + ?Sized>(size: usize, length: usize, rng: &mut R) -> String {
+    let turns = available_turns(size);
+    let mut scramble = Vec::with_capacity(length);
+    let mut axis_turns: Vec<Turn> = Vec::new();
+
+    while scramble.len() < length {
+        let turn = *turns
+            .choose(rng)
+            .unwrap();
+
+        if axis_turns
+            .first()
+            .is_some_and(|t| t.axis() == turn.axis())
+        {
+            if axis_turns.contains(&turn) {
+                continue;
+            }
+        } else {
+            axis_turns.clear();
+        }
+        axis_turns.push(turn);
+
+        let modifier = MODIFIERS
+            .choose(rng)
+            .unwrap();
+        scramble.push(format!("{}{}", turn.notation(), modifier));
+    }
+
+    scramble.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(move_str: &str) -> Turn {
+        let body = move_str.trim_end_matches(['\'', '2']);
+        let (width, body) = match body.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(0) => (None, body),
+            Some(i) => (
+                Some(
+                    body[..i]
+                        .parse()
+                        .unwrap(),
+                ),
+                &body[i..],
+            ),
+            None => panic!("invalid move: {}", move_str),
+        };
+        let face = FACES
+            .iter()
+            .position(|f| body.starts_with(f))
+            .unwrap();
+        let width = match (width, &body[1..]) {
+            (None, "") => 1,
+            (None, "w") => 2,
+            (Some(w), "w") => w,
+            _ => panic!("invalid move: {}", move_str),
+        };
+        Turn { face, width }
+    }
+
+    #[test]
+    fn test_available_turns() {
+        assert_eq!(available_turns(3).len(), 6);
+        assert_eq!(available_turns(4).len(), 9);
+        assert_eq!(available_turns(5).len(), 12);
+        assert_eq!(available_turns(6).len(), 15);
+        assert_eq!(available_turns(7).len(), 18);
+    }
+
+    #[test]
+    fn test_turn_notation() {
+        assert_eq!(Turn { face: 2, width: 1 }.notation(), "R");
+        assert_eq!(Turn { face: 0, width: 2 }.notation(), "Uw");
+        assert_eq!(Turn { face: 4, width: 3 }.notation(), "3Fw");
+    }
+
+    #[test]
+    fn test_generate_no_redundant_axis_turns() {
+        let mut rng = rand::thread_rng();
+        let scramble = generate(5, 500, &mut rng);
+        let turns: Vec<Turn> = scramble
+            .split_whitespace()
+            .map(parse)
+            .collect();
+
+        let mut start = 0;
+        for i in 1..=turns.len() {
+            if i == turns.len() || turns[i].axis() != turns[start].axis() {
+                let run = &turns[start..i];
+                for (j, turn) in run
+                    .iter()
+                    .enumerate()
+                {
+                    assert!(!run[j + 1..].contains(turn), "redundant turns in {:?}", run);
+                }
+                start = i;
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_valid_wide_moves() {
+        let mut rng = rand::thread_rng();
+        for size in 3..=7 {
+            let allowed = available_turns(size);
+            for move_str in generate(size, 100, &mut rng).split_whitespace() {
+                assert!(
+                    allowed.contains(&parse(move_str)),
+                    "{} on {}",
+                    move_str,
+                    size
+                );
+                assert!(MODIFIERS
+                    .iter()
+                    .any(|m| move_str.ends_with(m)));
+            }
+        }
+    }
+}
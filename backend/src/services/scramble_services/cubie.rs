@@ -0,0 +1,473 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::services::cube_state::Face;
+
+// Corners: URF, UFL, ULB, UBR, DFR, DLF, DBL, DRB.
+// Edges: UR, UF, UL, UB, DR, DF, DL, DB, FR, FL, BL, BR.
+pub const N_CORNERS: usize = 8;
+pub const N_EDGES: usize = 12;
+
+pub const N_TWIST: usize = 2187; // 3^7
+pub const N_FLIP: usize = 2048; // 2^11
+pub const N_SLICE: usize = 495; // 12 choose 4
+pub const N_CORNER_PERM: usize = 40320; // 8!
+pub const N_UD_EDGE_PERM: usize = 40320; // 8!
+pub const N_SLICE_PERM: usize = 24; // 4!
+
+// The four middle layer edges (FR, FL, BL, BR) start at this index.
+const SLICE_EDGE: u8 = 8;
+
+/// A 3x3x3 cube on the cubie level, where `cp[i]` is the corner that sits in position `i`
+/// and `co[i]` its orientation. Edges follow the same rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubieCube {
+    pub cp: [u8; N_CORNERS],
+    pub co: [u8; N_CORNERS],
+    pub ep: [u8; N_EDGES],
+    pub eo: [u8; N_EDGES],
+}
+
+pub const SOLVED: CubieCube = CubieCube {
+    cp: [0, 1, 2, 3, 4, 5, 6, 7],
+    co: [0; N_CORNERS],
+    ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+    eo: [0; N_EDGES],
+};
+
+pub const FACES: [&str; 6] = ["U", "R", "F", "D", "L", "B"];
+pub const POWERS: [&str; 3] = ["", "2", "'"];
+
+/// Clockwise quarter turns of the faces, in the order of `FACES`.
+pub const BASIC_MOVES: [CubieCube; 6] = [
+    CubieCube {
+        cp: [3, 0, 1, 2, 4, 5, 6, 7],
+        co: [0, 0, 0, 0, 0, 0, 0, 0],
+        ep: [3, 0, 1, 2, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    },
+    CubieCube {
+        cp: [4, 1, 2, 0, 7, 5, 6, 3],
+        co: [2, 0, 0, 1, 1, 0, 0, 2],
+        ep: [8, 1, 2, 3, 11, 5, 6, 7, 4, 9, 10, 0],
+        eo: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    },
+    CubieCube {
+        cp: [1, 5, 2, 3, 0, 4, 6, 7],
+        co: [1, 2, 0, 0, 2, 1, 0, 0],
+        ep: [0, 9, 2, 3, 4, 8, 6, 7, 1, 5, 10, 11],
+        eo: [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    },
+    CubieCube {
+        cp: [0, 1, 2, 3, 5, 6, 7, 4],
+        co: [0, 0, 0, 0, 0, 0, 0, 0],
+        ep: [0, 1, 2, 3, 5, 6, 7, 4, 8, 9, 10, 11],
+        eo: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    },
+    CubieCube {
+        cp: [0, 2, 6, 3, 4, 1, 5, 7],
+        co: [0, 1, 2, 0, 0, 2, 1, 0],
+        ep: [0, 1, 10, 3, 4, 5, 9, 7, 8, 2, 6, 11],
+        eo: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    },
+    CubieCube {
+        cp: [0, 1, 3, 7, 4, 5, 2, 6],
+        co: [0, 0, 1, 2, 0, 0, 2, 1],
+        ep: [0, 1, 2, 11, 4, 5, 6, 10, 8, 9, 3, 7],
+        eo: [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    },
+];
+
+/// All 18 face turns, indexed as `face * 3 + power` following `FACES` and `POWERS`.
+pub fn all_moves() -> Vec<CubieCube> {
+    let mut moves = Vec::with_capacity(18);
+    for basic in BASIC_MOVES {
+        let mut power = basic;
+        for _ in 0..3 {
+            moves.push(power);
+            power = power.multiply(&basic);
+        }
+    }
+    moves
+}
+
+pub fn move_notation(m: usize) -> String {
+    format!("{}{}", FACES[m / 3], POWERS[m % 3])
+}
+
+pub fn inverse_move(m: usize) -> usize {
+    m / 3 * 3 + (2 - m % 3)
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+fn perm_coord(values: &[u8]) -> usize {
+    let mut coord = 0;
+    for i in 0..values.len() {
+        let smaller = values[i + 1..]
+            .iter()
+            .filter(|&&v| v < values[i])
+            .count();
+        coord = coord * (values.len() - i) + smaller;
+    }
+    coord
+}
+
+fn set_perm_coord(values: &mut [u8], mut coord: usize, mut pool: Vec<u8>) {
+    let n = values.len();
+    let mut digits = vec![0; n];
+    for i in (0..n).rev() {
+        digits[i] = coord % (n - i);
+        coord /= n - i;
+    }
+    for (value, digit) in values
+        .iter_mut()
+        .zip(digits)
+    {
+        *value = pool.remove(digit);
+    }
+}
+
+fn parity(values: &[u8]) -> bool {
+    let inversions: usize = (0..values.len())
+        .map(|i| {
+            values[i + 1..]
+                .iter()
+                .filter(|&&v| v < values[i])
+                .count()
+        })
+        .sum();
+    inversions % 2 == 1
+}
+
+impl CubieCube {
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut result = SOLVED;
+        for i in 0..N_CORNERS {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..N_EDGES {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+
+    /// A uniformly random solvable state: the orientation of the last corner and edge
+    /// is fixed by the others, and the edge permutation parity must match the corners.
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> CubieCube {
+        let mut cube = SOLVED;
+        cube.cp
+            .shuffle(rng);
+        cube.ep
+            .shuffle(rng);
+        if parity(&cube.cp) != parity(&cube.ep) {
+            cube.ep
+                .swap(0, 1);
+        }
+        cube.set_twist(rng.gen_range(0..N_TWIST));
+        cube.set_flip(rng.gen_range(0..N_FLIP));
+        cube
+    }
+
+    /// The state reached from solved by the given face turns.
+    pub fn from_face_turns(turns: &[(Face, u8)]) -> CubieCube {
+        let all = all_moves();
+        turns
+            .iter()
+            .filter(|(_, quarters)| quarters % 4 != 0)
+            .fold(SOLVED, |cube, &(face, quarters)| {
+                cube.multiply(&all[face.index() * 3 + (quarters % 4) as usize - 1])
+            })
+    }
+
+    /// Randomly permutes the pieces in the given positions, keeping the orientation of every
+    /// position. The first two edges are swapped back when the parity doesn't match anymore.
+    pub fn permute<R: Rng + ?Sized>(&mut self, corners: &[usize], edges: &[usize], rng: &mut R) {
+        let mut pieces: Vec<u8> = corners
+            .iter()
+            .map(|&i| self.cp[i])
+            .collect();
+        pieces.shuffle(rng);
+        for (&i, piece) in corners
+            .iter()
+            .zip(pieces)
+        {
+            self.cp[i] = piece;
+        }
+
+        let mut pieces: Vec<u8> = edges
+            .iter()
+            .map(|&i| self.ep[i])
+            .collect();
+        pieces.shuffle(rng);
+        for (&i, piece) in edges
+            .iter()
+            .zip(pieces)
+        {
+            self.ep[i] = piece;
+        }
+
+        if parity(&self.cp) != parity(&self.ep) {
+            self.ep
+                .swap(edges[0], edges[1]);
+        }
+    }
+
+    /// Randomly orients the pieces in the given positions, the last corner and edge
+    /// being fixed by the others.
+    pub fn orient<R: Rng + ?Sized>(&mut self, corners: &[usize], edges: &[usize], rng: &mut R) {
+        let (last, others) = corners
+            .split_last()
+            .expect("at least one corner");
+        for &i in others {
+            self.co[i] = rng.gen_range(0..3);
+        }
+        self.co[*last] = 0;
+        let twist: u8 = self
+            .co
+            .iter()
+            .sum();
+        self.co[*last] = (3 - twist % 3) % 3;
+
+        let (last, others) = edges
+            .split_last()
+            .expect("at least one edge");
+        for &i in others {
+            self.eo[i] = rng.gen_range(0..2);
+        }
+        self.eo[*last] = 0;
+        let flip: u8 = self
+            .eo
+            .iter()
+            .sum();
+        self.eo[*last] = flip % 2;
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        let mut corners = self.cp;
+        corners.sort();
+        let mut edges = self.ep;
+        edges.sort();
+
+        corners == SOLVED.cp
+            && edges == SOLVED.ep
+            && parity(&self.cp) == parity(&self.ep)
+            && self
+                .co
+                .iter()
+                .map(|&o| o as usize)
+                .sum::<usize>()
+                % 3
+                == 0
+            && self
+                .eo
+                .iter()
+                .map(|&o| o as usize)
+                .sum::<usize>()
+                % 2
+                == 0
+    }
+
+    pub fn twist(&self) -> usize {
+        self.co[..N_CORNERS - 1]
+            .iter()
+            .fold(0, |acc, &o| acc * 3 + o as usize)
+    }
+
+    pub fn set_twist(&mut self, mut twist: usize) {
+        let mut sum = 0;
+        for i in (0..N_CORNERS - 1).rev() {
+            self.co[i] = (twist % 3) as u8;
+            sum += self.co[i];
+            twist /= 3;
+        }
+        self.co[N_CORNERS - 1] = (3 - sum % 3) % 3;
+    }
+
+    pub fn flip(&self) -> usize {
+        self.eo[..N_EDGES - 1]
+            .iter()
+            .fold(0, |acc, &o| acc * 2 + o as usize)
+    }
+
+    pub fn set_flip(&mut self, mut flip: usize) {
+        let mut sum = 0;
+        for i in (0..N_EDGES - 1).rev() {
+            self.eo[i] = (flip % 2) as u8;
+            sum += self.eo[i];
+            flip /= 2;
+        }
+        self.eo[N_EDGES - 1] = sum % 2;
+    }
+
+    /// Positions of the middle layer edges, ignoring their order. Solved cube is 0.
+    pub fn slice(&self) -> usize {
+        let mut coord = 0;
+        let mut found = 0;
+        for j in (0..N_EDGES).rev() {
+            if self.ep[j] >= SLICE_EDGE {
+                coord += binomial(N_EDGES - 1 - j, found + 1);
+                found += 1;
+            }
+        }
+        coord
+    }
+
+    pub fn set_slice(&mut self, mut slice: usize) {
+        let mut slice_edges = SLICE_EDGE..N_EDGES as u8;
+        let mut other_edges = 0..SLICE_EDGE;
+        let mut left = 4;
+        for j in 0..N_EDGES {
+            let c = binomial(N_EDGES - 1 - j, left);
+            if left > 0 && slice >= c {
+                slice -= c;
+                left -= 1;
+                self.ep[j] = slice_edges
+                    .next()
+                    .unwrap();
+            } else {
+                self.ep[j] = other_edges
+                    .next()
+                    .unwrap();
+            }
+        }
+    }
+
+    pub fn corner_perm(&self) -> usize {
+        perm_coord(&self.cp)
+    }
+
+    pub fn set_corner_perm(&mut self, coord: usize) {
+        set_perm_coord(&mut self.cp, coord, (0..N_CORNERS as u8).collect());
+    }
+
+    /// Permutation of the U and D layer edges, only meaningful once they are all in those layers.
+    pub fn ud_edge_perm(&self) -> usize {
+        perm_coord(&self.ep[..SLICE_EDGE as usize])
+    }
+
+    pub fn set_ud_edge_perm(&mut self, coord: usize) {
+        set_perm_coord(
+            &mut self.ep[..SLICE_EDGE as usize],
+            coord,
+            (0..SLICE_EDGE).collect(),
+        );
+    }
+
+    /// Permutation of the middle layer edges, only meaningful once they are all in that layer.
+    pub fn slice_perm(&self) -> usize {
+        perm_coord(&self.ep[SLICE_EDGE as usize..])
+    }
+
+    pub fn set_slice_perm(&mut self, coord: usize) {
+        set_perm_coord(
+            &mut self.ep[SLICE_EDGE as usize..],
+            coord,
+            (SLICE_EDGE..N_EDGES as u8).collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(moves: &[usize]) -> CubieCube {
+        let all = all_moves();
+        moves
+            .iter()
+            .fold(SOLVED, |cube, &m| cube.multiply(&all[m]))
+    }
+
+    #[test]
+    fn test_basic_moves_have_order_four() {
+        for basic in BASIC_MOVES {
+            let cube = (0..4).fold(SOLVED, |acc, _| acc.multiply(&basic));
+            assert_eq!(cube, SOLVED);
+        }
+    }
+
+    #[test]
+    fn test_sexy_move_has_order_six() {
+        // R U R' U'
+        let sexy = apply(&[3, 0, 5, 2]);
+        let cube = (0..6).fold(SOLVED, |acc, _| acc.multiply(&sexy));
+        assert_eq!(cube, SOLVED);
+    }
+
+    #[test]
+    fn test_coordinates_roundtrip() {
+        let mut cube = SOLVED;
+        for coord in [0, 1, 1000, N_TWIST - 1] {
+            cube.set_twist(coord);
+            assert_eq!(cube.twist(), coord);
+        }
+        for coord in [0, 1, 1000, N_FLIP - 1] {
+            cube.set_flip(coord);
+            assert_eq!(cube.flip(), coord);
+        }
+        for coord in [0, 1, 200, N_SLICE - 1] {
+            cube.set_slice(coord);
+            assert_eq!(cube.slice(), coord);
+        }
+        for coord in [0, 1, 20000, N_CORNER_PERM - 1] {
+            cube.set_corner_perm(coord);
+            assert_eq!(cube.corner_perm(), coord);
+        }
+        cube = SOLVED;
+        for coord in [0, 1, 20000, N_UD_EDGE_PERM - 1] {
+            cube.set_ud_edge_perm(coord);
+            assert_eq!(cube.ud_edge_perm(), coord);
+        }
+        for coord in [0, 1, 13, N_SLICE_PERM - 1] {
+            cube.set_slice_perm(coord);
+            assert_eq!(cube.slice_perm(), coord);
+        }
+    }
+
+    #[test]
+    fn test_random_state_is_solvable() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            assert!(CubieCube::random(&mut rng).is_solvable());
+        }
+    }
+
+    #[test]
+    fn test_moves_keep_cube_solvable() {
+        let cube = apply(&[0, 4, 8, 9, 13, 17, 2, 6]);
+        assert!(cube.is_solvable());
+        assert_ne!(cube, SOLVED);
+    }
+
+    #[test]
+    fn test_from_face_turns() {
+        // R U R' U'
+        let turns = [(Face::R, 1), (Face::U, 1), (Face::R, 3), (Face::U, 3)];
+        assert_eq!(CubieCube::from_face_turns(&turns), apply(&[3, 0, 5, 2]));
+    }
+
+    #[test]
+    fn test_permute_and_orient_keep_other_pieces() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let mut cube = SOLVED;
+            cube.permute(&[0, 1, 2, 3], &[0, 1, 2, 3], &mut rng);
+            cube.orient(&[0, 1, 2, 3], &[0, 1, 2, 3], &mut rng);
+
+            assert!(cube.is_solvable());
+            assert_eq!(cube.cp[4..], SOLVED.cp[4..]);
+            assert_eq!(cube.co[4..], SOLVED.co[4..]);
+            assert_eq!(cube.ep[4..], SOLVED.ep[4..]);
+            assert_eq!(cube.eo[4..], SOLVED.eo[4..]);
+        }
+    }
+}
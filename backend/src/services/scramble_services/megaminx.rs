@@ -0,0 +1,63 @@
+use rand::{seq::SliceRandom, Rng};
+
+const LINES: usize = 7;
+const MOVES_PER_LINE: usize = 10;
+
+/// Generates a Pochmann-style scramble: every line alternates `R` and `D` double turns
+/// (`++` or `--`) and ends with a `U` or `U'` turn.
+pub fn generate<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let lines: Vec<String> = (0..LINES)
+        .map(|_| {
+            let mut line: Vec<String> = (0..MOVES_PER_LINE)
+                .map(|i| {
+                    let face = if i % 2 == 0 { "R" } else { "D" };
+                    let modifier = ["++", "--"]
+                        .choose(rng)
+                        .unwrap();
+                    format!("{}{}", face, modifier)
+                })
+                .collect();
+            let last = ["U", "U'"]
+                .choose(rng)
+                .unwrap();
+            line.push(last.to_string());
+            line.join(" ")
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_megaminx_valid_lines() {
+        let scramble = generate(&mut rand::thread_rng());
+        let lines: Vec<&str> = scramble
+            .lines()
+            .collect();
+
+        assert_eq!(lines.len(), LINES);
+        for line in lines {
+            let moves: Vec<&str> = line
+                .split_whitespace()
+                .collect();
+            let (last, turns) = moves
+                .split_last()
+                .unwrap();
+
+            assert_eq!(turns.len(), MOVES_PER_LINE);
+            for (i, move_str) in turns
+                .iter()
+                .enumerate()
+            {
+                let face = if i % 2 == 0 { "R" } else { "D" };
+                assert_eq!(&move_str[..1], face);
+                assert!(["++", "--"].contains(&&move_str[1..]));
+            }
+            assert!(["U", "U'"].contains(last));
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::sync::OnceLock;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::routes::scrambles::{Scramble, ScrambleKind, TrainingMode};
+
+mod clock;
+mod cube_2x2;
+mod cube_nxn;
+mod cubie;
+mod megaminx;
+mod pyraminx;
+mod skewb;
+pub mod square_one;
+pub mod training;
+mod two_phase;
+
+/// Lookup tables of the random-state generators. Each set is only built
+/// the first time a scramble of its kind is requested.
+#[derive(Default)]
+pub struct ScrambleTables {
+    two: OnceLock<cube_2x2::Tables>,
+    three: OnceLock<two_phase::Tables>,
+}
+
+impl ScrambleTables {
+    fn two(&self) -> &cube_2x2::Tables {
+        self.two
+            .get_or_init(cube_2x2::Tables::build)
+    }
+
+    fn three(&self) -> &two_phase::Tables {
+        self.three
+            .get_or_init(two_phase::Tables::build)
+    }
+}
+
+/// The inverse of the two-phase solution of `cube`, or `None` when the state is too close
+/// to solved to be a valid scramble.
+fn scramble_3x3(tables: &two_phase::Tables, cube: &cubie::CubieCube) -> Option<String> {
+    let solution = two_phase::solve(tables, cube);
+
+    // WCA Regulation 4b3: the state must require at least 2 moves to solve.
+    (solution.len() >= 2).then(|| {
+        solution
+            .iter()
+            .rev()
+            .map(|&m| cubie::move_notation(cubie::inverse_move(m)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Picks a uniformly random 3x3x3 state and returns the inverse of its two-phase solution.
+fn generate_3x3<R: Rng + ?Sized>(tables: &two_phase::Tables, rng: &mut R) -> String {
+    loop {
+        let cube = cubie::CubieCube::random(rng);
+        if let Some(sequence) = scramble_3x3(tables, &cube) {
+            return sequence;
+        }
+    }
+}
+
+/// Largest accepted seed, so that seeds survive a round trip through JSON numbers
+/// in clients which store them as doubles.
+pub const MAX_SEED: u64 = (1 << 53) - 1;
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen_range(0..=MAX_SEED)
+}
+
+/// Random number generator whose output only depends on the seed, so that a set of
+/// scrambles can be generated again from the same seed.
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+pub fn generate<R: Rng + ?Sized>(
+    tables: &ScrambleTables,
+    kind: ScrambleKind,
+    rng: &mut R,
+) -> Scramble {
+    let sequence = match kind {
+        ScrambleKind::Two => cube_2x2::generate(tables.two(), rng),
+        ScrambleKind::Three => generate_3x3(tables.three(), rng),
+        ScrambleKind::Four => cube_nxn::generate(4, 40, rng),
+        ScrambleKind::Five => cube_nxn::generate(5, 60, rng),
+        ScrambleKind::Six => cube_nxn::generate(6, 80, rng),
+        ScrambleKind::Seven => cube_nxn::generate(7, 100, rng),
+        ScrambleKind::Pyraminx => pyraminx::generate(11, rng),
+        ScrambleKind::Skewb => skewb::generate(11, rng),
+        ScrambleKind::Megaminx => megaminx::generate(rng),
+        ScrambleKind::SquareOne => square_one::generate(12, rng),
+        ScrambleKind::Clock => clock::generate(rng),
+    };
+
+    Scramble {
+        kind,
+        sequence,
+        case: None,
+    }
+}
+
+/// A 3x3x3 scramble for practising one step of the solve, restricted to `cases` for the
+/// modes which have cases.
+pub fn generate_training<R: Rng + ?Sized>(
+    tables: &ScrambleTables,
+    mode: TrainingMode,
+    cases: &[&'static training::Case],
+    rng: &mut R,
+) -> Scramble {
+    loop {
+        let (cube, case) = training::random_state(mode, cases, rng);
+        if let Some(sequence) = scramble_3x3(tables.three(), &cube) {
+            return Scramble {
+                kind: ScrambleKind::Three,
+                sequence,
+                case,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+
+    static TABLES: LazyLock<ScrambleTables> = LazyLock::new(ScrambleTables::default);
+
+    #[test]
+    fn test_generate_3x3_scramble_length() {
+        let scramble = generate(&TABLES, ScrambleKind::Three, &mut rand::thread_rng());
+
+        // Check that the scramble sequence has between 2 and 22 moves
+        let length = scramble
+            .sequence
+            .split_whitespace()
+            .count();
+        assert!((2..=two_phase::MAX_LENGTH).contains(&length));
+    }
+
+    #[test]
+    fn test_generate_scramble_kind() {
+        let scramble = generate(&TABLES, ScrambleKind::Three, &mut rand::thread_rng());
+
+        assert!(matches!(scramble.kind, ScrambleKind::Three));
+    }
+
+    #[test]
+    fn test_generate_3x3_scramble_valid_moves() {
+        let scramble = generate(&TABLES, ScrambleKind::Three, &mut rand::thread_rng());
+        let valid_moves = ["U", "D", "R", "L", "F", "B"];
+        let valid_modifiers = ["", "'", "2"];
+
+        for move_str in scramble
+            .sequence
+            .split_whitespace()
+        {
+            let move_part = &move_str[..1];
+            let modifier_part = &move_str[1..];
+
+            assert!(valid_moves.contains(&move_part));
+            assert!(valid_modifiers.contains(&modifier_part));
+        }
+    }
+
+    #[test]
+    fn test_generate_2x2_scramble_valid_moves() {
+        let scramble = generate(&TABLES, ScrambleKind::Two, &mut rand::thread_rng());
+        let valid_moves = ["R", "U", "F"];
+        let valid_modifiers = ["", "'", "2"];
+
+        assert!(matches!(scramble.kind, ScrambleKind::Two));
+        for move_str in scramble
+            .sequence
+            .split_whitespace()
+        {
+            let move_part = &move_str[..1];
+            let modifier_part = &move_str[1..];
+
+            assert!(valid_moves.contains(&move_part));
+            assert!(valid_modifiers.contains(&modifier_part));
+        }
+    }
+
+    #[test]
+    fn test_generate_big_cube_scramble_lengths() {
+        let expected = [
+            (ScrambleKind::Four, 40),
+            (ScrambleKind::Five, 60),
+            (ScrambleKind::Six, 80),
+            (ScrambleKind::Seven, 100),
+        ];
+
+        for (kind, length) in expected {
+            let scramble = generate(&TABLES, kind.clone(), &mut rand::thread_rng());
+
+            assert_eq!(scramble.kind, kind);
+            assert_eq!(
+                scramble
+                    .sequence
+                    .split_whitespace()
+                    .count(),
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_same_seed_same_scrambles() {
+        let kinds = [
+            ScrambleKind::Two,
+            ScrambleKind::Three,
+            ScrambleKind::Five,
+            ScrambleKind::Pyraminx,
+            ScrambleKind::Megaminx,
+            ScrambleKind::SquareOne,
+            ScrambleKind::Clock,
+        ];
+
+        for kind in kinds {
+            let (mut first, mut second) = (seeded_rng(42), seeded_rng(42));
+            for _ in 0..3 {
+                assert_eq!(
+                    generate(&TABLES, kind.clone(), &mut first),
+                    generate(&TABLES, kind.clone(), &mut second)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_different_seeds_different_scrambles() {
+        let first = generate(&TABLES, ScrambleKind::Three, &mut seeded_rng(1));
+        let second = generate(&TABLES, ScrambleKind::Three, &mut seeded_rng(2));
+
+        assert_ne!(first, second);
+        assert!(random_seed() <= MAX_SEED);
+    }
+
+    #[test]
+    fn test_generated_scrambles_are_valid_notation() {
+        let kinds = [
+            ScrambleKind::Two,
+            ScrambleKind::Three,
+            ScrambleKind::Four,
+            ScrambleKind::Five,
+            ScrambleKind::Six,
+            ScrambleKind::Seven,
+            ScrambleKind::Pyraminx,
+            ScrambleKind::Skewb,
+            ScrambleKind::Megaminx,
+            ScrambleKind::SquareOne,
+            ScrambleKind::Clock,
+        ];
+
+        for kind in kinds {
+            let scramble = generate(&TABLES, kind, &mut rand::thread_rng());
+            assert_eq!(
+                crate::services::notation::validate(&scramble),
+                Ok(()),
+                "{}",
+                scramble.sequence
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_no_repeated_consecutive_moves() {
+        let scramble = generate(&TABLES, ScrambleKind::Three, &mut rand::thread_rng());
+
+        let moves: Vec<&str> = scramble
+            .sequence
+            .split_whitespace()
+            .collect();
+        for i in 0..moves.len() - 1 {
+            assert_ne!(moves[i], moves[i + 1]);
+        }
+    }
+
+    #[test]
+    fn test_generate_training_scramble() {
+        let cases = training::select_cases(TrainingMode::Pll, Some("G")).unwrap();
+        let scramble =
+            generate_training(&TABLES, TrainingMode::Pll, &cases, &mut rand::thread_rng());
+
+        assert_eq!(scramble.kind, ScrambleKind::Three);
+        assert!(scramble
+            .case
+            .as_deref()
+            .is_some_and(|case| case.starts_with("PLL G")));
+        assert_eq!(crate::services::notation::validate(&scramble), Ok(()));
+    }
+}
@@ -0,0 +1,68 @@
+use rand::{seq::SliceRandom, Rng};
+
+const FACES: [&str; 4] = ["U", "L", "R", "B"];
+const TIPS: [&str; 4] = ["u", "l", "r", "b"];
+const MODIFIERS: [&str; 2] = ["", "'"];
+
+/// Generates a random move scramble followed by random tip turns.
+/// Every tip can be turned either way or left untouched.
+pub fn generate<R: Rng + ?Sized>(length: usize, rng: &mut R) -> String {
+    let mut scramble = Vec::with_capacity(length + TIPS.len());
+    let mut last_face = None;
+
+    while scramble.len() < length {
+        let face = rng.gen_range(0..FACES.len());
+        if last_face == Some(face) {
+            continue;
+        }
+        let modifier = MODIFIERS
+            .choose(rng)
+            .unwrap();
+        scramble.push(format!("{}{}", FACES[face], modifier));
+        last_face = Some(face);
+    }
+
+    for tip in TIPS {
+        match rng.gen_range(0..3) {
+            0 => scramble.push(tip.to_owned()),
+            1 => scramble.push(format!("{}'", tip)),
+            _ => {}
+        }
+    }
+
+    scramble.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_pyraminx_valid_moves() {
+        let scramble = generate(11, &mut rand::thread_rng());
+        let moves: Vec<&str> = scramble
+            .split_whitespace()
+            .collect();
+        let (faces, tips) = moves.split_at(11);
+
+        for move_str in faces {
+            assert!(FACES.contains(&&move_str[..1]));
+            assert!(MODIFIERS.contains(&&move_str[1..]));
+        }
+        for pair in faces.windows(2) {
+            assert_ne!(pair[0][..1], pair[1][..1]);
+        }
+
+        assert!(tips.len() <= TIPS.len());
+        for (i, move_str) in tips
+            .iter()
+            .enumerate()
+        {
+            assert!(TIPS.contains(&&move_str[..1]));
+            assert!(MODIFIERS.contains(&&move_str[1..]));
+            assert!(!tips[i + 1..]
+                .iter()
+                .any(|other| other[..1] == move_str[..1]));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use rand::{seq::SliceRandom, Rng};
+
+const AXES: [&str; 4] = ["R", "U", "L", "B"];
+const MODIFIERS: [&str; 2] = ["", "'"];
+
+/// Generates a random move scramble in the WCA Skewb notation, where every turn
+/// rotates half of the puzzle around one of the four fixed corners.
+pub fn generate<R: Rng + ?Sized>(length: usize, rng: &mut R) -> String {
+    let mut scramble = Vec::with_capacity(length);
+    let mut last_axis = None;
+
+    while scramble.len() < length {
+        let axis = rng.gen_range(0..AXES.len());
+        if last_axis == Some(axis) {
+            continue;
+        }
+        let modifier = MODIFIERS
+            .choose(rng)
+            .unwrap();
+        scramble.push(format!("{}{}", AXES[axis], modifier));
+        last_axis = Some(axis);
+    }
+
+    scramble.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_skewb_valid_moves() {
+        let scramble = generate(11, &mut rand::thread_rng());
+        let moves: Vec<&str> = scramble
+            .split_whitespace()
+            .collect();
+
+        assert_eq!(moves.len(), 11);
+        for move_str in &moves {
+            assert!(AXES.contains(&&move_str[..1]));
+            assert!(MODIFIERS.contains(&&move_str[1..]));
+        }
+        for pair in moves.windows(2) {
+            assert_ne!(pair[0][..1], pair[1][..1]);
+        }
+    }
+}
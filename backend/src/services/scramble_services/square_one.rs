@@ -0,0 +1,237 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::services::notation::{
+    self,
+    square_one::{SquareOneMove, SquareOneNotation},
+    ParseError,
+};
+
+// Every layer is split into 12 slots of 30 degrees, numbered clockwise (as seen from the top)
+// starting at the back end of the slice, so slots 0..6 belong to the right half.
+// Corners take up two slots, edges only one.
+pub const LAYER_SLOTS: usize = 12;
+const HALF_SLOTS: usize = 6;
+
+/// Pieces are numbered 0..4 in the top layer and 4..8 in the bottom layer, clockwise
+/// starting with the corner at the back right and the edge at the right.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Piece {
+    Corner(u8),
+    Edge(u8),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SquareOne {
+    top: [Piece; LAYER_SLOTS],
+    bottom: [Piece; LAYER_SLOTS],
+    // Whether the right half of the middle layer is flipped relative to the left half.
+    middle_flipped: bool,
+}
+
+fn solved_layer(offset: u8) -> [Piece; LAYER_SLOTS] {
+    let mut layer = [Piece::Edge(0); LAYER_SLOTS];
+    for i in 0..4 {
+        let id = offset + i as u8;
+        layer[i * 3] = Piece::Corner(id);
+        layer[i * 3 + 1] = Piece::Corner(id);
+        layer[i * 3 + 2] = Piece::Edge(id);
+    }
+    layer
+}
+
+/// Whether a piece starts at `slot`, i.e. the layer can be cut right before it.
+pub fn is_cut(layer: &[Piece; LAYER_SLOTS], slot: usize) -> bool {
+    layer[slot] != layer[(slot + LAYER_SLOTS - 1) % LAYER_SLOTS]
+}
+
+impl SquareOne {
+    pub fn solved() -> SquareOne {
+        SquareOne {
+            top: solved_layer(0),
+            bottom: solved_layer(4),
+            middle_flipped: false,
+        }
+    }
+
+    pub fn top(&self) -> &[Piece; LAYER_SLOTS] {
+        &self.top
+    }
+
+    pub fn bottom(&self) -> &[Piece; LAYER_SLOTS] {
+        &self.bottom
+    }
+
+    pub fn middle_flipped(&self) -> bool {
+        self.middle_flipped
+    }
+
+    /// Turns the top layer clockwise by `top` and the bottom layer clockwise (as seen from
+    /// the bottom) by `bottom` twelfths of a full turn.
+    pub fn turn(&mut self, top: i32, bottom: i32) {
+        self.top
+            .rotate_right(top.rem_euclid(LAYER_SLOTS as i32) as usize);
+        self.bottom
+            .rotate_left(bottom.rem_euclid(LAYER_SLOTS as i32) as usize);
+    }
+
+    /// Applies `sequence` to a solved puzzle, failing at the first slice turn blocked
+    /// by a corner.
+    pub fn from_sequence(sequence: &str) -> Result<SquareOne, ParseError> {
+        let nodes = notation::parse(&SquareOneNotation, sequence)?;
+        let mut puzzle = SquareOne::solved();
+
+        for (offset, mv) in notation::expand_with_offsets(&nodes) {
+            match mv {
+                SquareOneMove::Turn { top, bottom } => puzzle.turn(top, bottom),
+                SquareOneMove::Slash if puzzle.can_slash() => puzzle.slash(),
+                SquareOneMove::Slash => {
+                    return Err(ParseError::IllegalMove {
+                        token: mv.to_string(),
+                        offset,
+                    })
+                }
+            }
+        }
+        Ok(puzzle)
+    }
+
+    /// The slice can only be turned when no corner lies across it in either layer.
+    pub fn can_slash(&self) -> bool {
+        [&self.top, &self.bottom]
+            .iter()
+            .all(|layer| is_cut(layer, 0) && is_cut(layer, HALF_SLOTS))
+    }
+
+    /// Turns the right half by 180 degrees, which swaps the right halves of both layers
+    /// and reverses their front to back order.
+    pub fn slash(&mut self) {
+        for i in 0..HALF_SLOTS {
+            std::mem::swap(&mut self.top[i], &mut self.bottom[HALF_SLOTS - 1 - i]);
+        }
+        self.middle_flipped = !self.middle_flipped;
+    }
+}
+
+/// Generates a random move scramble of `(x,y)/` tuples, choosing only layer turns
+/// after which the slice can be turned.
+pub fn generate<R: Rng + ?Sized>(slashes: usize, rng: &mut R) -> String {
+    let mut puzzle = SquareOne::solved();
+    let mut scramble = Vec::with_capacity(slashes);
+
+    for _ in 0..slashes {
+        let legal: Vec<(i32, i32)> = (-5..=6)
+            .flat_map(|top| (-5..=6).map(move |bottom| (top, bottom)))
+            .filter(|&turn| turn != (0, 0))
+            .filter(|&(top, bottom)| {
+                let mut next = puzzle.clone();
+                next.turn(top, bottom);
+                next.can_slash()
+            })
+            .collect();
+
+        let &(top, bottom) = legal
+            .choose(rng)
+            .unwrap();
+        puzzle.turn(top, bottom);
+        puzzle.slash();
+        scramble.push(format!("({},{})/", top, bottom));
+    }
+
+    scramble.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solved_cube_shape_turns() {
+        let puzzle = SquareOne::solved();
+        assert!(puzzle.can_slash());
+
+        let legal_top: Vec<i32> = (-5..=6)
+            .filter(|&top| {
+                let mut next = puzzle.clone();
+                next.turn(top, 0);
+                next.can_slash()
+            })
+            .collect();
+        assert_eq!(legal_top, vec![-5, -3, -2, 0, 1, 3, 4, 6]);
+    }
+
+    #[test]
+    fn test_slash_twice_restores_state() {
+        let mut puzzle = SquareOne::solved();
+        puzzle.turn(1, -1);
+        let before = puzzle.clone();
+        puzzle.slash();
+        puzzle.slash();
+
+        assert_eq!(puzzle, before);
+    }
+
+    #[test]
+    fn test_generate_square_one_legal_slashes() {
+        let scramble = generate(12, &mut rand::thread_rng());
+        let mut puzzle = SquareOne::solved();
+        let tuples: Vec<&str> = scramble
+            .split_whitespace()
+            .collect();
+
+        assert_eq!(tuples.len(), 12);
+        for tuple in tuples {
+            let (top, bottom) = tuple
+                .strip_prefix('(')
+                .and_then(|t| t.strip_suffix(")/"))
+                .and_then(|t| t.split_once(','))
+                .unwrap();
+            let top: i32 = top
+                .parse()
+                .unwrap();
+            let bottom: i32 = bottom
+                .parse()
+                .unwrap();
+
+            assert!((-5..=6).contains(&top));
+            assert!((-5..=6).contains(&bottom));
+            assert_ne!((top, bottom), (0, 0));
+
+            puzzle.turn(top, bottom);
+            assert!(puzzle.can_slash(), "illegal slash after {}", tuple);
+            puzzle.slash();
+        }
+    }
+
+    #[test]
+    fn test_from_sequence() {
+        let mut expected = SquareOne::solved();
+        expected.turn(1, 0);
+        expected.slash();
+        expected.turn(-1, -1);
+        expected.slash();
+
+        assert_eq!(
+            SquareOne::from_sequence("(1,0)/ (-1, -1) /").unwrap(),
+            expected
+        );
+        assert_eq!(SquareOne::from_sequence("").unwrap(), SquareOne::solved());
+    }
+
+    #[test]
+    fn test_from_sequence_errors() {
+        assert_eq!(
+            SquareOne::from_sequence("(1,0)/ (0,1)/").unwrap_err(),
+            ParseError::IllegalMove {
+                token: "/".to_owned(),
+                offset: 12
+            }
+        );
+        assert_eq!(
+            SquareOne::from_sequence("(1,0)/ (a,1)/").unwrap_err(),
+            ParseError::InvalidMove {
+                token: "a".to_owned(),
+                offset: 8
+            }
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    routes::scrambles::TrainingMode,
+    services::notation::{
+        self,
+        cube::{self, CubeNotation},
+    },
+};
+
+use super::cubie::{self, CubieCube};
+
+// Positions of the last layer, the front right slot and the cross pieces.
+const LL_CORNERS: [usize; 4] = [0, 1, 2, 3];
+const LL_EDGES: [usize; 4] = [0, 1, 2, 3];
+const SLOT_CORNER: usize = 4;
+const SLOT_EDGE: usize = 8;
+const CROSS_EDGES: [usize; 4] = [4, 5, 6, 7];
+
+/// Length of the random `<R, U>` walk, long enough to be close to a uniform 2-gen state.
+const TWO_GEN_WALK: usize = 250;
+
+/// A named case of a training mode, given by an algorithm which solves it.
+/// Cases of the same family (e.g. the four G perms) share a group.
+pub struct Case {
+    pub name: &'static str,
+    group: &'static str,
+    algorithm: &'static str,
+}
+
+const fn case(name: &'static str, group: &'static str, algorithm: &'static str) -> Case {
+    Case {
+        name,
+        group,
+        algorithm,
+    }
+}
+
+const PLL_CASES: [Case; 21] = [
+    case("Aa", "A", "x R' U R' D2 R U' R' D2 R2 x'"),
+    case("Ab", "A", "x R2 D2 R U R' D2 R U' R x'"),
+    case("E", "E", "x' R U' R' D R U R' D' R U R' D R U' R' D' x"),
+    case("F", "F", "R' U' F' R U R' U' R' F R2 U' R' U' R U R' U R"),
+    case("Ga", "G", "R2 U R' U R' U' R U' R2 U' D R' U R D'"),
+    case("Gb", "G", "R' U' R U D' R2 U R' U R U' R U' R2 D"),
+    case("Gc", "G", "R2 U' R U' R U R' U R2 U D' R U' R' D"),
+    case("Gd", "G", "R U R' U' D R2 U' R U' R' U R' U R2 D'"),
+    case("H", "H", "M2 U M2 U2 M2 U M2"),
+    case("Ja", "J", "x R2 F R F' R U2 r' U r U2 x'"),
+    case("Jb", "J", "R U R' F' R U R' U' R' F R2 U' R'"),
+    case(
+        "Na",
+        "N",
+        "R U R' U R U R' F' R U R' U' R' F R2 U' R' U2 R U' R'",
+    ),
+    case("Nb", "N", "R' U R U' R' F' U' F R U R' F R' F' R U' R"),
+    case("Ra", "R", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    case("Rb", "R", "R2 F R U R U' R' F' R U2 R' U2 R"),
+    case("T", "T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+    case("Ua", "U", "M2 U M U2 M' U M2"),
+    case("Ub", "U", "M2 U' M U2 M' U' M2"),
+    case("V", "V", "R' U R' U' y R' F' R2 U' R' U R' F R F"),
+    case("Y", "Y", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    case("Z", "Z", "M' U M2 U M2 U M' U2 M2"),
+];
+
+const OLL_CASES: [Case; 57] = [
+    case("1", "Dot", "R U2 R2 F R F' U2 R' F R F'"),
+    case("2", "Dot", "F R U R' U' F' f R U R' U' f'"),
+    case("3", "Dot", "f R U R' U' f' U' F R U R' U' F'"),
+    case("4", "Dot", "f R U R' U' f' U F R U R' U' F'"),
+    case("5", "Square", "r' U2 R U R' U r"),
+    case("6", "Square", "r U2 R' U' R U' r'"),
+    case("7", "Lightning", "r U R' U R U2 r'"),
+    case("8", "Lightning", "r' U' R U' R' U2 r"),
+    case("9", "Fish", "R U R' U' R' F R2 U R' U' F'"),
+    case("10", "Fish", "R U R' U R' F R F' R U2 R'"),
+    case("11", "Lightning", "r U R' U R' F R F' R U2 r'"),
+    case("12", "Lightning", "M' R' U' R U' R' U2 R U' R r'"),
+    case("13", "Knight", "F U R U' R2 F' R U R U' R'"),
+    case("14", "Knight", "R' F R U R' F' R F U' F'"),
+    case("15", "Knight", "r' U' r R' U' R U r' U r"),
+    case("16", "Knight", "r U r' R U R' U' r U' r'"),
+    case("17", "Dot", "F R' F' R2 r' U R U' R' U' M'"),
+    case("18", "Dot", "r U R' U R U2 r2 U' R U' R' U2 r"),
+    case("19", "Dot", "r' R U R U R' U' M' R' F R F'"),
+    case("20", "Dot", "r U R' U' M2 U R U' R' U' M'"),
+    case("21", "OCLL", "R U2 R' U' R U R' U' R U' R'"),
+    case("22", "OCLL", "R U2 R2 U' R2 U' R2 U2 R"),
+    case("23", "OCLL", "R2 D' R U2 R' D R U2 R"),
+    case("24", "OCLL", "r U R' U' r' F R F'"),
+    case("25", "OCLL", "F' r U R' U' r' F R"),
+    case("26", "OCLL", "R U2 R' U' R U' R'"),
+    case("27", "OCLL", "R U R' U R U2 R'"),
+    case("28", "Corners", "r U R' U' M U R U' R'"),
+    case("29", "Awkward", "R U R' U' R U' R' F' U' F R U R'"),
+    case("30", "Awkward", "F R' F R2 U' R' U' R U R' F2"),
+    case("31", "P", "R' U' F U R U' R' F' R"),
+    case("32", "P", "L U F' U' L' U L F L'"),
+    case("33", "T", "R U R' U' R' F R F'"),
+    case("34", "C", "R U R2 U' R' F R U R U' F'"),
+    case("35", "Fish", "R U2 R2 F R F' R U2 R'"),
+    case("36", "W", "L' U' L U' L' U L U L F' L' F"),
+    case("37", "Fish", "F R' F' R U R U' R'"),
+    case("38", "W", "R U R' U R U' R' U' R' F R F'"),
+    case("39", "Lightning", "L F' L' U' L U F U' L'"),
+    case("40", "Lightning", "R' F R U R' U' F' U R"),
+    case("41", "Awkward", "R U R' U R U2 R' F R U R' U' F'"),
+    case("42", "Awkward", "R' U' R U' R' U2 R F R U R' U' F'"),
+    case("43", "P", "F' U' L' U L F"),
+    case("44", "P", "F U R U' R' F'"),
+    case("45", "T", "F R U R' U' F'"),
+    case("46", "C", "R' U' R' F R F' U R"),
+    case("47", "L", "R' U' R' F R F' R' F R F' U R"),
+    case("48", "L", "F R U R' U' R U R' U' F'"),
+    case("49", "L", "r U' r2 U r2 U r2 U' r"),
+    case("50", "L", "r' U r2 U' r2 U' r2 U r'"),
+    case("51", "I", "F U R U' R' U R U' R' F'"),
+    case("52", "I", "R U R' U R U' B U' B' R'"),
+    case("53", "L", "r' U' R U' R' U R U' R' U2 r"),
+    case("54", "L", "r U R' U R U' R' U R U2 r'"),
+    case("55", "I", "R' F R U R U' R2 F' R2 U' R' U R U R'"),
+    case("56", "I", "r' U' r U' R' U R U' R' U R r' U r"),
+    case("57", "Corners", "R U R' U' M' U R U' r'"),
+];
+
+/// ZBLL cases are grouped by the OCLL case of their corners, named after its shape.
+const ZBLL_CASES: [Case; 7] = [
+    case("H", "H", "R U2 R' U' R U R' U' R U' R'"),
+    case("Pi", "Pi", "R U2 R2 U' R2 U' R2 U2 R"),
+    case("U", "U", "R2 D' R U2 R' D R U2 R"),
+    case("T", "T", "r U R' U' r' F R F'"),
+    case("L", "L", "F' r U R' U' r' F R"),
+    case("AS", "AS", "R U2 R' U' R U' R'"),
+    case("S", "S", "R U R' U R U2 R'"),
+];
+
+/// The cases a training mode can be restricted to, empty when it has none.
+fn cases(mode: TrainingMode) -> &'static [Case] {
+    match mode {
+        TrainingMode::Pll => &PLL_CASES,
+        TrainingMode::Oll => &OLL_CASES,
+        TrainingMode::Zbll => &ZBLL_CASES,
+        _ => &[],
+    }
+}
+
+/// Name of a case as stored in scrambles, prefixed by its step (e.g. `PLL Ga`).
+fn case_name(mode: TrainingMode, case: &Case) -> String {
+    let step = match mode {
+        TrainingMode::Pll => "PLL",
+        TrainingMode::Oll => "OLL",
+        TrainingMode::Zbll => "ZBLL",
+        _ => unreachable!("only modes with cases"),
+    };
+    format!("{} {}", step, case.name)
+}
+
+/// Parses a comma separated list of case names or groups (e.g. `G,Aa`), giving every case
+/// of the mode when there is none.
+pub fn select_cases(
+    mode: TrainingMode,
+    filter: Option<&str>,
+) -> Result<Vec<&'static Case>, String> {
+    let cases = cases(mode);
+    let Some(filter) = filter else {
+        return Ok(cases
+            .iter()
+            .collect());
+    };
+    if cases.is_empty() {
+        return Err("this training mode has no cases".to_owned());
+    }
+
+    let names: Vec<&str> = filter
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    let matches = |case: &Case, name: &str| {
+        name.eq_ignore_ascii_case(case.name)
+            || name.eq_ignore_ascii_case(case.group)
+            || name.eq_ignore_ascii_case(&case_name(mode, case))
+    };
+    if let Some(name) = names
+        .iter()
+        .find(|name| {
+            !cases
+                .iter()
+                .any(|case| matches(case, name))
+        })
+    {
+        return Err(format!("unknown case `{}`", name));
+    }
+
+    let selected: Vec<&Case> = cases
+        .iter()
+        .filter(|case| {
+            names
+                .iter()
+                .any(|name| matches(case, name))
+        })
+        .collect();
+    if selected.is_empty() {
+        return Err("at least one case is required".to_owned());
+    }
+    Ok(selected)
+}
+
+/// The state solved by the algorithm of the case.
+fn case_state(case: &Case) -> CubieCube {
+    let moves = notation::parse_moves(&CubeNotation { size: 3 }, case.algorithm)
+        .expect("case algorithms are valid");
+    CubieCube::from_face_turns(&cube::face_turns(&notation::invert(&moves)))
+}
+
+fn u_turns(quarters: usize) -> CubieCube {
+    (0..quarters).fold(cubie::SOLVED, |cube, _| {
+        cube.multiply(&cubie::BASIC_MOVES[0])
+    })
+}
+
+/// Every state of a PLL case, i.e. its state with any turn of U before and after.
+fn pll_states(case: &Case) -> Vec<CubieCube> {
+    let state = case_state(case);
+    let mut states = Vec::new();
+    for before in 0..4 {
+        for after in 0..4 {
+            let cube = u_turns(before)
+                .multiply(&state)
+                .multiply(&u_turns(after));
+            if !states.contains(&cube) {
+                states.push(cube);
+            }
+        }
+    }
+    states
+}
+
+/// Orientation of the last layer pieces, which is all that defines an OLL case.
+fn ll_orientation(cube: &CubieCube) -> ([u8; 4], [u8; 4]) {
+    (LL_CORNERS.map(|i| cube.co[i]), LL_EDGES.map(|i| cube.eo[i]))
+}
+
+/// Every orientation pattern of an OLL case, seen from each side of the cube.
+fn oll_patterns(case: &Case) -> Vec<CubieCube> {
+    let state = case_state(case);
+    let mut patterns: Vec<CubieCube> = Vec::new();
+    for after in 0..4 {
+        let cube = state.multiply(&u_turns(after));
+        if !patterns
+            .iter()
+            .any(|pattern| ll_orientation(pattern) == ll_orientation(&cube))
+        {
+            patterns.push(cube);
+        }
+    }
+    patterns
+}
+
+/// Picks a case with the odds of reaching it in a solve, then one of its states.
+fn random_case<R: Rng + ?Sized>(
+    cases: &[&'static Case],
+    states: fn(&Case) -> Vec<CubieCube>,
+    rng: &mut R,
+) -> (CubieCube, &'static Case) {
+    let states: Vec<(Vec<CubieCube>, &'static Case)> = cases
+        .iter()
+        .map(|&case| (states(case), case))
+        .collect();
+    let (states, case) = states
+        .choose_weighted(rng, |(states, _)| states.len())
+        .expect("at least one case");
+    (
+        *states
+            .choose(rng)
+            .expect("every case has a state"),
+        case,
+    )
+}
+
+/// A random state of the training mode, in which only the pieces of its step are mixed,
+/// together with its case for the modes which have cases.
+pub fn random_state<R: Rng + ?Sized>(
+    mode: TrainingMode,
+    cases: &[&'static Case],
+    rng: &mut R,
+) -> (CubieCube, Option<String>) {
+    let mut cube = cubie::SOLVED;
+    let case = match mode {
+        TrainingMode::LastLayer => {
+            cube.permute(&LL_CORNERS, &LL_EDGES, rng);
+            cube.orient(&LL_CORNERS, &LL_EDGES, rng);
+            None
+        }
+        TrainingMode::LastSlot => {
+            let corners = [0, 1, 2, 3, SLOT_CORNER];
+            let edges = [0, 1, 2, 3, SLOT_EDGE];
+            cube.permute(&corners, &edges, rng);
+            cube.orient(&corners, &edges, rng);
+            None
+        }
+        TrainingMode::CrossSolved => {
+            let corners: Vec<usize> = (0..cubie::N_CORNERS).collect();
+            let edges: Vec<usize> = (0..cubie::N_EDGES)
+                .filter(|i| !CROSS_EDGES.contains(i))
+                .collect();
+            cube.permute(&corners, &edges, rng);
+            cube.orient(&corners, &edges, rng);
+            None
+        }
+        TrainingMode::TwoGen => {
+            let moves = cubie::all_moves();
+            for _ in 0..TWO_GEN_WALK {
+                // U, U2, U', R, R2 and R' come first.
+                cube = cube.multiply(&moves[rng.gen_range(0..6)]);
+            }
+            None
+        }
+        TrainingMode::Pll => {
+            let (state, case) = random_case(cases, pll_states, rng);
+            cube = state;
+            Some(case)
+        }
+        TrainingMode::Oll | TrainingMode::Zbll => {
+            // The permutation of the last layer is left to chance.
+            let (state, case) = random_case(cases, oll_patterns, rng);
+            cube = state;
+            cube.permute(&LL_CORNERS, &LL_EDGES, rng);
+            Some(case)
+        }
+    };
+
+    (cube, case.map(|case| case_name(mode, case)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_f2l_solved(cube: &CubieCube) -> bool {
+        is_solved_at(cube, &[4, 5, 6, 7], &[4, 5, 6, 7, 8, 9, 10, 11])
+    }
+
+    fn is_solved_at(cube: &CubieCube, corners: &[usize], edges: &[usize]) -> bool {
+        corners
+            .iter()
+            .all(|&i| cube.cp[i] == i as u8 && cube.co[i] == 0)
+            && edges
+                .iter()
+                .all(|&i| cube.ep[i] == i as u8 && cube.eo[i] == 0)
+    }
+
+    fn oriented(cube: &CubieCube) -> (usize, usize) {
+        let (corners, edges) = ll_orientation(cube);
+        (
+            corners
+                .iter()
+                .filter(|&&o| o == 0)
+                .count(),
+            edges
+                .iter()
+                .filter(|&&o| o == 0)
+                .count(),
+        )
+    }
+
+    #[test]
+    fn test_pll_cases() {
+        let mut seen: Vec<CubieCube> = Vec::new();
+        for case in &PLL_CASES {
+            let states = pll_states(case);
+            for state in &states {
+                assert!(is_f2l_solved(state), "{}", case.name);
+                assert_eq!(oriented(state), (4, 4), "{}", case.name);
+                assert!(!seen.contains(state), "{} is another case", case.name);
+            }
+            seen.extend(states);
+        }
+
+        // With the 4 solved states, that's every permutation of the last layer (4! * 4! / 2).
+        assert_eq!(seen.len() + 4, 288);
+    }
+
+    #[test]
+    fn test_oll_cases() {
+        let mut seen = Vec::new();
+        for case in &OLL_CASES {
+            let patterns = oll_patterns(case);
+            for pattern in &patterns {
+                assert!(
+                    is_f2l_solved(&CubieCube {
+                        cp: cubie::SOLVED.cp,
+                        ep: cubie::SOLVED.ep,
+                        ..*pattern
+                    }),
+                    "{}",
+                    case.name
+                );
+                assert!(
+                    !seen.contains(&ll_orientation(pattern)),
+                    "{} is another case",
+                    case.name
+                );
+                seen.push(ll_orientation(pattern));
+            }
+
+            let (corners, edges) = oriented(&patterns[0]);
+            let expected_edges = match case.group {
+                "Dot" => 0,
+                "OCLL" => 4,
+                _ => 2,
+            };
+            assert_eq!(edges, expected_edges, "{}", case.name);
+            assert_eq!(
+                corners == 4,
+                matches!(case.name, "20" | "28" | "57"),
+                "{}",
+                case.name
+            );
+        }
+
+        // With the solved pattern, that's every orientation of the last layer (3^3 * 2^3).
+        assert_eq!(seen.len() + 1, 216);
+    }
+
+    #[test]
+    fn test_zbll_cases_match_ocll() {
+        for (zbll, oll) in ZBLL_CASES
+            .iter()
+            .zip(&OLL_CASES[20..27])
+        {
+            assert_eq!(case_state(zbll), case_state(oll), "{}", zbll.name);
+
+            let oriented_corners = match zbll.name {
+                "H" | "Pi" => 0,
+                "S" | "AS" => 1,
+                _ => 2,
+            };
+            assert_eq!(
+                oriented(&case_state(zbll)).0,
+                oriented_corners,
+                "{}",
+                zbll.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_cases() {
+        let names = |mode, filter| {
+            select_cases(mode, filter)
+                .unwrap()
+                .iter()
+                .map(|case| case.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(TrainingMode::Pll, Some("G")),
+            ["Ga", "Gb", "Gc", "Gd"]
+        );
+        assert_eq!(
+            names(TrainingMode::Pll, Some("t, aa,PLL Ua")),
+            ["Aa", "T", "Ua"]
+        );
+        assert_eq!(names(TrainingMode::Oll, Some("OCLL")).len(), 7);
+        assert_eq!(names(TrainingMode::Zbll, None).len(), 7);
+        assert!(names(TrainingMode::LastLayer, None).is_empty());
+
+        assert!(select_cases(TrainingMode::Pll, Some("G,Q")).is_err());
+        assert!(select_cases(TrainingMode::Pll, Some(" , ")).is_err());
+        assert!(select_cases(TrainingMode::TwoGen, Some("T")).is_err());
+    }
+
+    #[test]
+    fn test_random_states() {
+        let mut rng = rand::thread_rng();
+        let modes = [
+            TrainingMode::LastLayer,
+            TrainingMode::Oll,
+            TrainingMode::Pll,
+            TrainingMode::Zbll,
+            TrainingMode::LastSlot,
+            TrainingMode::TwoGen,
+            TrainingMode::CrossSolved,
+        ];
+
+        for mode in modes {
+            let cases = select_cases(mode, None).unwrap();
+            for _ in 0..20 {
+                let (cube, case) = random_state(mode, &cases, &mut rng);
+                assert!(cube.is_solvable(), "{:?}", mode);
+                assert_eq!(case.is_some(), !cases.is_empty(), "{:?}", mode);
+
+                match mode {
+                    TrainingMode::LastLayer | TrainingMode::Oll | TrainingMode::Pll => {
+                        assert!(is_f2l_solved(&cube), "{:?}", mode)
+                    }
+                    TrainingMode::Zbll => {
+                        assert!(is_f2l_solved(&cube));
+                        assert_eq!(oriented(&cube).1, 4);
+                    }
+                    TrainingMode::LastSlot => {
+                        assert!(is_solved_at(&cube, &[5, 6, 7], &[4, 5, 6, 7, 9, 10, 11]))
+                    }
+                    // R and U don't touch the pieces of the left block.
+                    TrainingMode::TwoGen => {
+                        assert!(is_solved_at(&cube, &[5, 6], &[5, 6, 7, 9, 10]))
+                    }
+                    TrainingMode::CrossSolved => assert!(is_solved_at(&cube, &[], &CROSS_EDGES)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_case_restricted() {
+        let cases = select_cases(TrainingMode::Pll, Some("G")).unwrap();
+        for _ in 0..20 {
+            let (_, case) = random_state(TrainingMode::Pll, &cases, &mut rand::thread_rng());
+            assert!(case
+                .unwrap()
+                .starts_with("PLL G"));
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use super::cubie::{
+    self, CubieCube, N_CORNER_PERM, N_FLIP, N_SLICE, N_SLICE_PERM, N_TWIST, N_UD_EDGE_PERM,
+};
+
+const N_MOVES: usize = 18;
+
+// Moves that keep the cube in the <U, D, R2, L2, F2, B2> subgroup.
+const PHASE_2_MOVES: [usize; 10] = [0, 1, 2, 4, 7, 9, 10, 11, 13, 16];
+
+/// Solutions longer than this are not accepted. Two-phase search finds a solution
+/// of this length for any state within milliseconds once the tables are built.
+pub const MAX_LENGTH: usize = 22;
+
+/// Move and pruning tables for Kociemba's two-phase algorithm.
+///
+/// Phase 1 brings the cube into the <U, D, R2, L2, F2, B2> subgroup (all orientations solved
+/// and the middle layer edges in the middle layer), phase 2 solves it using only those moves.
+pub struct Tables {
+    twist_move: Vec<[u16; N_MOVES]>,
+    flip_move: Vec<[u16; N_MOVES]>,
+    slice_move: Vec<[u16; N_MOVES]>,
+    corner_perm_move: Vec<[u16; PHASE_2_MOVES.len()]>,
+    ud_edge_perm_move: Vec<[u16; PHASE_2_MOVES.len()]>,
+    slice_perm_move: Vec<[u16; PHASE_2_MOVES.len()]>,
+    twist_slice_prune: Vec<u8>,
+    flip_slice_prune: Vec<u8>,
+    corner_slice_prune: Vec<u8>,
+    edge_slice_prune: Vec<u8>,
+}
+
+fn move_table<const M: usize>(
+    size: usize,
+    moves: &[CubieCube; M],
+    set: fn(&mut CubieCube, usize),
+    get: fn(&CubieCube) -> usize,
+) -> Vec<[u16; M]> {
+    (0..size)
+        .map(|coord| {
+            let mut cube = cubie::SOLVED;
+            set(&mut cube, coord);
+            moves.map(|m| get(&cube.multiply(&m)) as u16)
+        })
+        .collect()
+}
+
+/// Breadth-first search over the product of two coordinates, giving the exact
+/// number of moves needed to solve both of them at once.
+fn prune_table<const M: usize>(first: &[[u16; M]], second: &[[u16; M]]) -> Vec<u8> {
+    let size = second.len();
+    let mut distances = vec![u8::MAX; first.len() * size];
+    distances[0] = 0;
+    let mut frontier = vec![0];
+    let mut depth = 0;
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for index in frontier {
+            let (a, b) = (index / size, index % size);
+            for m in 0..M {
+                let target = first[a][m] as usize * size + second[b][m] as usize;
+                if distances[target] == u8::MAX {
+                    distances[target] = depth + 1;
+                    next.push(target);
+                }
+            }
+        }
+        frontier = next;
+        depth += 1;
+    }
+
+    distances
+}
+
+impl Tables {
+    pub fn build() -> Tables {
+        let all: [CubieCube; N_MOVES] = cubie::all_moves()
+            .try_into()
+            .unwrap();
+        let phase_2 = PHASE_2_MOVES.map(|m| all[m]);
+
+        let twist_move = move_table(N_TWIST, &all, CubieCube::set_twist, CubieCube::twist);
+        let flip_move = move_table(N_FLIP, &all, CubieCube::set_flip, CubieCube::flip);
+        let slice_move = move_table(N_SLICE, &all, CubieCube::set_slice, CubieCube::slice);
+        let corner_perm_move = move_table(
+            N_CORNER_PERM,
+            &phase_2,
+            CubieCube::set_corner_perm,
+            CubieCube::corner_perm,
+        );
+        let ud_edge_perm_move = move_table(
+            N_UD_EDGE_PERM,
+            &phase_2,
+            CubieCube::set_ud_edge_perm,
+            CubieCube::ud_edge_perm,
+        );
+        let slice_perm_move = move_table(
+            N_SLICE_PERM,
+            &phase_2,
+            CubieCube::set_slice_perm,
+            CubieCube::slice_perm,
+        );
+
+        Tables {
+            twist_slice_prune: prune_table(&twist_move, &slice_move),
+            flip_slice_prune: prune_table(&flip_move, &slice_move),
+            corner_slice_prune: prune_table(&corner_perm_move, &slice_perm_move),
+            edge_slice_prune: prune_table(&ud_edge_perm_move, &slice_perm_move),
+            twist_move,
+            flip_move,
+            slice_move,
+            corner_perm_move,
+            ud_edge_perm_move,
+            slice_perm_move,
+        }
+    }
+}
+
+/// Rejects turning the same face twice in a row, and fixes the order of
+/// commuting opposite faces so that each pair is only searched once.
+fn is_redundant(solution: &[usize], m: usize) -> bool {
+    solution
+        .last()
+        .is_some_and(|&last| {
+            let (face, last_face) = (m / 3, last / 3);
+            face == last_face || (face % 3 == last_face % 3 && face < last_face)
+        })
+}
+
+struct Search<'a> {
+    tables: &'a Tables,
+    moves: Vec<CubieCube>,
+    cube: CubieCube,
+    solution: Vec<usize>,
+}
+
+impl Search<'_> {
+    fn phase_1(&mut self, twist: usize, flip: usize, slice: usize, depth: usize) -> bool {
+        if depth == 0 {
+            // A phase 1 ending with a phase 2 move has already been tried one move shorter.
+            let redundant = self
+                .solution
+                .last()
+                .is_some_and(|m| PHASE_2_MOVES.contains(m));
+            return twist == 0 && flip == 0 && slice == 0 && !redundant && self.start_phase_2();
+        }
+
+        let t = self.tables;
+        let bound = t.twist_slice_prune[twist * N_SLICE + slice]
+            .max(t.flip_slice_prune[flip * N_SLICE + slice]) as usize;
+        if bound > depth {
+            return false;
+        }
+
+        for m in 0..N_MOVES {
+            if is_redundant(&self.solution, m) {
+                continue;
+            }
+            self.solution
+                .push(m);
+            if self.phase_1(
+                t.twist_move[twist][m] as usize,
+                t.flip_move[flip][m] as usize,
+                t.slice_move[slice][m] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.solution
+                .pop();
+        }
+
+        false
+    }
+
+    fn start_phase_2(&mut self) -> bool {
+        let cube = self
+            .solution
+            .iter()
+            .fold(self.cube, |cube, &m| cube.multiply(&self.moves[m]));
+        let (corners, edges, slice) = (cube.corner_perm(), cube.ud_edge_perm(), cube.slice_perm());
+
+        let max_depth = MAX_LENGTH.saturating_sub(
+            self.solution
+                .len(),
+        );
+        (0..=max_depth).any(|depth| self.phase_2(corners, edges, slice, depth))
+    }
+
+    fn phase_2(&mut self, corners: usize, edges: usize, slice: usize, depth: usize) -> bool {
+        if depth == 0 {
+            return corners == 0 && edges == 0 && slice == 0;
+        }
+
+        let t = self.tables;
+        let bound = t.corner_slice_prune[corners * N_SLICE_PERM + slice]
+            .max(t.edge_slice_prune[edges * N_SLICE_PERM + slice]) as usize;
+        if bound > depth {
+            return false;
+        }
+
+        for (i, &m) in PHASE_2_MOVES
+            .iter()
+            .enumerate()
+        {
+            if is_redundant(&self.solution, m) {
+                continue;
+            }
+            self.solution
+                .push(m);
+            if self.phase_2(
+                t.corner_perm_move[corners][i] as usize,
+                t.ud_edge_perm_move[edges][i] as usize,
+                t.slice_perm_move[slice][i] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.solution
+                .pop();
+        }
+
+        false
+    }
+}
+
+/// Finds a solution of at most `MAX_LENGTH` moves for a solvable cube.
+/// Moves are indexed the same way as in `cubie::all_moves`.
+pub fn solve(tables: &Tables, cube: &CubieCube) -> Vec<usize> {
+    debug_assert!(cube.is_solvable(), "cannot solve {:?}", cube);
+    let mut search = Search {
+        tables,
+        moves: cubie::all_moves(),
+        cube: *cube,
+        solution: Vec::new(),
+    };
+    let (twist, flip, slice) = (cube.twist(), cube.flip(), cube.slice());
+
+    for depth in 0..=MAX_LENGTH {
+        if search.phase_1(twist, flip, slice, depth) {
+            return search.solution;
+        }
+    }
+
+    unreachable!("every solvable cube has a two-phase solution within {MAX_LENGTH} moves")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+
+    static TABLES: LazyLock<Tables> = LazyLock::new(Tables::build);
+
+    #[test]
+    fn test_prune_tables_are_complete() {
+        assert!(!TABLES
+            .twist_slice_prune
+            .contains(&u8::MAX));
+        assert!(!TABLES
+            .flip_slice_prune
+            .contains(&u8::MAX));
+        assert!(!TABLES
+            .corner_slice_prune
+            .contains(&u8::MAX));
+        assert!(!TABLES
+            .edge_slice_prune
+            .contains(&u8::MAX));
+    }
+
+    #[test]
+    fn test_solve_random_states() {
+        let mut rng = rand::thread_rng();
+        let moves = cubie::all_moves();
+
+        for _ in 0..5 {
+            let cube = CubieCube::random(&mut rng);
+            let solution = solve(&TABLES, &cube);
+            let solved = solution
+                .iter()
+                .fold(cube, |acc, &m| acc.multiply(&moves[m]));
+
+            assert!(solution.len() <= MAX_LENGTH);
+            assert_eq!(solved, cubie::SOLVED);
+        }
+    }
+
+    #[test]
+    fn test_solve_solved_cube() {
+        assert!(solve(&TABLES, &cubie::SOLVED).is_empty());
+    }
+}
@@ -1,19 +1,22 @@
-use std::sync::Arc;
+use std::{fmt, str::FromStr, sync::Arc};
 
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, Uuid},
-    results::{DeleteResult, InsertOneResult, UpdateResult},
+    bson::{self, doc, Bson, Document, Uuid},
+    results::{DeleteResult, InsertManyResult, InsertOneResult, UpdateResult},
     Collection,
 };
 
 use crate::{
     error::AppError,
-    models::session::{Session, Time},
+    models::session::{
+        IndexedTime, Penalty, Session, SessionSummary, SessionUpdate, Solution, Time, TimeUpdate,
+    },
+    routes::sessions::{SortOrder, TimesQuery},
     AppState,
 };
 
-use super::{get_collection, Collections};
+use super::{get_collection, validation_services::field_error, Collections};
 
 pub async fn find_all_by_account_id(
     state: &Arc<AppState>,
@@ -29,6 +32,32 @@ pub async fn find_all_by_account_id(
     Ok(result)
 }
 
+pub async fn find_summaries_by_account_id(
+    state: &Arc<AppState>,
+    id: Uuid,
+) -> Result<Vec<SessionSummary>, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let result = sessions
+        .aggregate([
+            doc! { "$match": { "account_id": id } },
+            doc! { "$project": {
+                "account_id": 1,
+                "name": 1,
+                "description": 1,
+                "tags": 1,
+                "kind": 1,
+                "time_count": { "$size": "$times" },
+                "last_recorded_at": { "$max": "$times.recorded_at" },
+            } },
+        ])
+        .with_type::<SessionSummary>()
+        .await?
+        .try_collect()
+        .await?;
+
+    Ok(result)
+}
+
 pub async fn find_by_id_and_account_id(
     state: &Arc<AppState>,
     account_id: Uuid,
@@ -51,36 +80,494 @@ pub async fn create(state: &Arc<AppState>, session: Session) -> Result<InsertOne
     Ok(result)
 }
 
-pub async fn update_by_id_and_account_id(
+pub async fn create_many(
+    state: &Arc<AppState>,
+    sessions: &[Session],
+) -> Result<InsertManyResult, AppError> {
+    let collection: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let result = collection
+        .insert_many(sessions)
+        .await?;
+
+    Ok(result)
+}
+
+/// Applies the changes to the metadata of a session. A session is only bound to a puzzle when
+/// all its times are of it, and matches nothing otherwise.
+pub async fn update_metadata(
     state: &Arc<AppState>,
     account_id: Uuid,
     id: Uuid,
-    body: Session,
+    update: &SessionUpdate,
 ) -> Result<UpdateResult, AppError> {
     let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let (filter, modifications) = metadata_update(account_id, id, update)?;
     let result = sessions
-        .replace_one(doc! { "_id": id, "account_id": account_id }, body)
+        .update_one(filter, modifications)
         .await?;
 
     Ok(result)
 }
 
+/// Filter and modifications of `update_metadata`.
+fn metadata_update(
+    account_id: Uuid,
+    id: Uuid,
+    update: &SessionUpdate,
+) -> Result<(Document, Document), AppError> {
+    let mut filter = doc! { "_id": id, "account_id": account_id };
+    let mut changes = Document::new();
+    let mut removed = Document::new();
+
+    if let Some(name) = &update.name {
+        changes.insert("name", name);
+    }
+    match &update.description {
+        Some(Some(description)) => {
+            changes.insert("description", description);
+        }
+        Some(None) => {
+            removed.insert("description", "");
+        }
+        None => {}
+    }
+    if let Some(tags) = &update.tags {
+        changes.insert("tags", tags);
+    }
+    match &update.kind {
+        Some(Some(kind)) => {
+            let kind = bson::to_bson(kind)?;
+            filter.insert(
+                "times",
+                doc! { "$not": { "$elemMatch": { "scramble.kind": { "$ne": &kind } } } },
+            );
+            changes.insert("kind", kind);
+        }
+        Some(None) => {
+            removed.insert("kind", "");
+        }
+        None => {}
+    }
+
+    let mut modifications = Document::new();
+    if !changes.is_empty() {
+        modifications.insert("$set", changes);
+    }
+    if !removed.is_empty() {
+        modifications.insert("$unset", removed);
+    }
+    Ok((filter, modifications))
+}
+
+/// Condition for a session to accept the times: it isn't bound to a puzzle, or they are all
+/// of the puzzle it is bound to.
+fn accepts(times: &[Time]) -> Result<Document, AppError> {
+    let Some(first) = times.first() else {
+        return Ok(Document::new());
+    };
+    let kind = &first
+        .scramble
+        .kind;
+    if times
+        .iter()
+        .any(|time| {
+            &time
+                .scramble
+                .kind
+                != kind
+        })
+    {
+        return Ok(doc! { "kind": Bson::Null });
+    }
+    Ok(doc! { "kind": { "$in": [Bson::Null, bson::to_bson(kind)?] } })
+}
+
+/// Why times couldn't be inserted into a session, which either doesn't exist or is bound to
+/// another puzzle.
+pub async fn insert_error(state: &Arc<AppState>, account_id: Uuid, id: Uuid) -> AppError {
+    match find_by_id_and_account_id(state, account_id, id).await {
+        Ok(Some(Session {
+            kind: Some(kind), ..
+        })) => field_error(
+            "scramble",
+            format!("the session only accepts {:?} scrambles", kind),
+        ),
+        Ok(_) => AppError::NotFound,
+        Err(err) => err,
+    }
+}
+
 pub async fn insert_time(
     state: &Arc<AppState>,
     account_id: Uuid,
     id: Uuid,
     time: Time,
 ) -> Result<UpdateResult, AppError> {
-    let mut session = match find_by_id_and_account_id(state, account_id, id).await? {
-        None => return Err(AppError::NotFound),
-        Some(s) => s,
+    insert_times(state, account_id, id, &[time]).await
+}
+
+/// Appends the times to the session in a single update, so that times added at the same
+/// moment from another device aren't lost. Matches nothing when the session is bound to another
+/// puzzle than the one of a time.
+pub async fn insert_times(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    times: &[Time],
+) -> Result<UpdateResult, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let (filter, modifications) = times_insertion(account_id, id, times)?;
+    let result = sessions
+        .update_one(filter, modifications)
+        .await?;
+
+    Ok(result)
+}
+
+/// Filter and modifications of `insert_times`.
+fn times_insertion(
+    account_id: Uuid,
+    id: Uuid,
+    times: &[Time],
+) -> Result<(Document, Document), AppError> {
+    let mut filter = accepts(times)?;
+    filter.insert("_id", id);
+    filter.insert("account_id", account_id);
+
+    Ok((
+        filter,
+        doc! { "$push": { "times": { "$each": bson::to_bson(times)? } } },
+    ))
+}
+
+/// Position of the last time of a page, the next page starts after it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeCursor {
+    recorded_at: u64,
+    index: u64,
+}
+
+impl fmt::Display for TimeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.recorded_at, self.index)
+    }
+}
+
+impl FromStr for TimeCursor {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<TimeCursor, ()> {
+        let (recorded_at, index) = value
+            .split_once('-')
+            .ok_or(())?;
+        Ok(TimeCursor {
+            recorded_at: recorded_at
+                .parse()
+                .map_err(|_| ())?,
+            index: index
+                .parse()
+                .map_err(|_| ())?,
+        })
+    }
+}
+
+pub struct TimesPage {
+    pub times: Vec<IndexedTime>,
+    pub next_cursor: Option<String>,
+}
+
+/// Adds a `$gte`/`$lte` range on `field` to the filter, when there are bounds.
+fn insert_range(filter: &mut Document, field: &str, min: Option<u64>, max: Option<u64>) {
+    let mut range = Document::new();
+    if let Some(min) = min {
+        range.insert("$gte", min as i64);
+    }
+    if let Some(max) = max {
+        range.insert("$lte", max as i64);
+    }
+    if !range.is_empty() {
+        filter.insert(field, range);
+    }
+}
+
+/// Pipeline listing the matching times of a session one page at a time. Times are ordered
+/// by `recorded_at`, then by their position for times recorded at the same moment.
+fn times_pipeline(
+    account_id: Uuid,
+    id: Uuid,
+    query: &TimesQuery,
+    cursor: Option<TimeCursor>,
+) -> Result<Vec<Document>, AppError> {
+    let mut filter = Document::new();
+    insert_range(&mut filter, "times.recorded_at", query.from, query.to);
+    insert_range(
+        &mut filter,
+        "times.millis",
+        query.min_millis,
+        query.max_millis,
+    );
+    if let Some(penalty) = query.penalty {
+        // Times recorded before penalties existed have none.
+        let values = match penalty {
+            Penalty::None => vec![bson::to_bson(&penalty)?, Bson::Null],
+            _ => vec![bson::to_bson(&penalty)?],
+        };
+        filter.insert("times.penalty", doc! { "$in": values });
+    }
+    if let Some(kind) = &query.kind {
+        filter.insert("times.scramble.kind", bson::to_bson(kind)?);
+    }
+
+    let (after, direction) = match query.order {
+        SortOrder::Asc => ("$gt", 1),
+        SortOrder::Desc => ("$lt", -1),
+    };
+    if let Some(TimeCursor { recorded_at, index }) = cursor {
+        let (recorded_at, index) = (recorded_at as i64, index as i64);
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "times.recorded_at": { after: recorded_at } },
+                doc! { "times.recorded_at": recorded_at, "index": { after: index } },
+            ],
+        );
+    }
+
+    Ok(vec![
+        doc! { "$match": { "_id": id, "account_id": account_id } },
+        doc! { "$unwind": { "path": "$times", "includeArrayIndex": "index" } },
+        doc! { "$match": filter },
+        doc! { "$sort": { "times.recorded_at": direction, "index": direction } },
+        // One more time than asked tells whether there is a next page.
+        doc! { "$limit": query.limit as i64 + 1 },
+        doc! { "$project": { "_id": 0, "index": 1, "time": "$times" } },
+    ])
+}
+
+/// A page of the times of a session matching the query, `None` when there is no such session.
+pub async fn find_times(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    query: &TimesQuery,
+) -> Result<Option<TimesPage>, AppError> {
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|_| field_error("cursor", "invalid cursor"))?;
+
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let exists = sessions
+        .count_documents(doc! { "_id": id, "account_id": account_id })
+        .await?
+        > 0;
+    if !exists {
+        return Ok(None);
+    }
+
+    let mut times: Vec<IndexedTime> = sessions
+        .aggregate(times_pipeline(account_id, id, query, cursor)?)
+        .with_type::<IndexedTime>()
+        .await?
+        .try_collect()
+        .await?;
+
+    let next_cursor = if times.len() > query.limit as usize {
+        times.truncate(query.limit as usize);
+        times
+            .last()
+            .map(|last| {
+                TimeCursor {
+                    recorded_at: last
+                        .time
+                        .recorded_at,
+                    index: last.index,
+                }
+                .to_string()
+            })
+    } else {
+        None
     };
 
-    session
-        .times
-        .push(time);
+    Ok(Some(TimesPage { times, next_cursor }))
+}
+
+/// A time of a session, by id or by its position in the session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeRef {
+    Id(Uuid),
+    Index(usize),
+}
+
+impl FromStr for TimeRef {
+    type Err = ();
 
-    update_by_id_and_account_id(state, account_id, id, session).await
+    fn from_str(value: &str) -> Result<TimeRef, ()> {
+        if let Ok(index) = value.parse() {
+            return Ok(TimeRef::Index(index));
+        }
+        Uuid::parse_str(value)
+            .map(TimeRef::Id)
+            .map_err(|_| ())
+    }
+}
+
+impl TimeRef {
+    /// The time among the times of a session.
+    pub fn find_in(self, times: &[Time]) -> Option<&Time> {
+        match self {
+            TimeRef::Id(id) => times
+                .iter()
+                .find(|time| time.id == id),
+            TimeRef::Index(index) => times.get(index),
+        }
+    }
+
+    /// Condition on the session for the time to exist, and the path of the time to update.
+    fn locate(self) -> (Document, String) {
+        match self {
+            TimeRef::Id(id) => (doc! { "times.id": id }, "times.$".to_owned()),
+            TimeRef::Index(index) => {
+                let path = format!("times.{}", index);
+                (doc! { &path: { "$exists": true } }, path)
+            }
+        }
+    }
+}
+
+/// Finds a single time of a session, `None` when there is no such session or time.
+pub async fn find_time(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    time: TimeRef,
+) -> Result<Option<Time>, AppError> {
+    let session = find_by_id_and_account_id(state, account_id, id).await?;
+
+    Ok(session.and_then(|session| {
+        time.find_in(&session.times)
+            .cloned()
+    }))
+}
+
+/// Attaches a solution to a time, or removes it. The solution must have been checked against
+/// the scramble of the time beforehand.
+pub async fn set_solution(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    time_id: Uuid,
+    solution: Option<&Solution>,
+) -> Result<UpdateResult, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let (filter, modifications) = solution_update(account_id, id, time_id, solution)?;
+    let result = sessions
+        .update_one(filter, modifications)
+        .await?;
+
+    Ok(result)
+}
+
+/// Filter and modifications of `set_solution`.
+fn solution_update(
+    account_id: Uuid,
+    id: Uuid,
+    time_id: Uuid,
+    solution: Option<&Solution>,
+) -> Result<(Document, Document), AppError> {
+    let filter = doc! { "_id": id, "account_id": account_id, "times.id": time_id };
+    let modifications = match solution {
+        Some(solution) => doc! { "$set": { "times.$.solution": bson::to_bson(solution)? } },
+        None => doc! { "$unset": { "times.$.solution": "" } },
+    };
+    Ok((filter, modifications))
+}
+
+/// Applies the changes to a single time, matching nothing when the session has no such time.
+pub async fn update_time(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    time: TimeRef,
+    update: &TimeUpdate,
+) -> Result<UpdateResult, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let (filter, modifications) = time_update(account_id, id, time, update)?;
+    let result = sessions
+        .update_one(filter, modifications)
+        .await?;
+
+    Ok(result)
+}
+
+/// Filter and modifications of `update_time`. A new `millis` drops the splits of the time.
+fn time_update(
+    account_id: Uuid,
+    id: Uuid,
+    time: TimeRef,
+    update: &TimeUpdate,
+) -> Result<(Document, Document), AppError> {
+    let (mut filter, path) = time.locate();
+    filter.insert("_id", id);
+    filter.insert("account_id", account_id);
+
+    let mut changes = Document::new();
+    let mut removed = Document::new();
+    if let Some(millis) = update.millis {
+        changes.insert(format!("{}.millis", path), bson::to_bson(&millis)?);
+        removed.insert(format!("{}.splits", path), "");
+    }
+    if let Some(penalty) = update.penalty {
+        changes.insert(format!("{}.penalty", path), bson::to_bson(&penalty)?);
+    }
+
+    let mut modifications = doc! { "$set": changes };
+    if !removed.is_empty() {
+        modifications.insert("$unset", removed);
+    }
+    Ok((filter, modifications))
+}
+
+/// Removes a single time, leaving the session unmodified when it has no such time.
+pub async fn delete_time(
+    state: &Arc<AppState>,
+    account_id: Uuid,
+    id: Uuid,
+    time_id: Uuid,
+) -> Result<UpdateResult, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let result = sessions
+        .update_one(
+            doc! { "_id": id, "account_id": account_id },
+            doc! { "$pull": { "times": { "id": time_id } } },
+        )
+        .await?;
+
+    Ok(result)
+}
+
+/// Stores an id for every time recorded before times had one. Reading such a session
+/// already gives its times new ids, so it only has to be written back.
+pub async fn assign_time_ids(state: &Arc<AppState>) -> Result<u64, AppError> {
+    let sessions: Collection<Session> = get_collection(state, Collections::SESSIONS);
+    let mut outdated = sessions
+        .find(doc! { "times": { "$elemMatch": { "id": { "$exists": false } } } })
+        .await?;
+
+    let mut count = 0;
+    while let Some(session) = outdated
+        .try_next()
+        .await?
+    {
+        sessions
+            .replace_one(doc! { "_id": session.id }, &session)
+            .await?;
+        count += 1;
+    }
+
+    Ok(count)
 }
 
 pub async fn delete_by_id_and_account_id(
@@ -112,8 +599,17 @@ pub async fn delete_all_by_account_id(
 mod tests {
     use crate::{
         error::AppError,
-        models::session::{Session, Time},
-        services::scramble_services,
+        models::session::{Penalty, Session, SessionUpdate, Solution, Time, TimeUpdate},
+        services::scramble_services::{self, ScrambleTables},
+    };
+
+    use super::{
+        accepts, metadata_update, solution_update, time_update, times_insertion, times_pipeline,
+        TimeCursor, TimeRef,
+    };
+    use crate::routes::{
+        scrambles::ScrambleKind,
+        sessions::{SortOrder, TimesQuery},
     };
     use async_trait::async_trait;
     use mockall::{mock, predicate::eq};
@@ -170,12 +666,6 @@ mod tests {
             id: Uuid,
         ) -> Result<Option<Session>, AppError>;
         async fn create(&self, session: Session) -> Result<MockInsertOneResult, AppError>;
-        async fn update_by_id_and_account_id(
-            &self,
-            account_id: Uuid,
-            id: Uuid,
-            session: Session,
-        ) -> Result<MockUpdateResult, AppError>;
         async fn insert_time(
             &self,
             account_id: Uuid,
@@ -201,7 +691,6 @@ mod tests {
             async fn find_all_by_account_id(&self, account_id: Uuid) -> Result<Vec<Session>, AppError>;
             async fn find_by_id_and_account_id(&self, account_id: Uuid, id: Uuid) -> Result<Option<Session>, AppError>;
             async fn create(&self, session: Session) -> Result<MockInsertOneResult, AppError>;
-            async fn update_by_id_and_account_id(&self, account_id: Uuid, id: Uuid, session: Session) -> Result<MockUpdateResult, AppError>;
             async fn insert_time(&self, account_id: Uuid, id: Uuid, time: Time) -> Result<MockUpdateResult, AppError>;
             async fn delete_by_id_and_account_id(&self, account_id: Uuid, id: Uuid) -> Result<MockDeleteResult, AppError>;
             async fn delete_all_by_account_id(&self, account_id: Uuid) -> Result<MockDeleteResult, AppError>;
@@ -214,14 +703,26 @@ mod tests {
             account_id,
             name: "Test Session".to_string(),
             times: vec![],
+            description: None,
+            tags: vec![],
+            kind: None,
         }
     }
 
     fn create_mock_time() -> Time {
         Time {
+            id: Uuid::new(),
             millis: 1000,
             recorded_at: 1629209981,
-            scramble: scramble_services::generate(crate::routes::scrambles::ScrambleKind::Three),
+            scramble: scramble_services::generate(
+                &ScrambleTables::default(),
+                crate::routes::scrambles::ScrambleKind::Three,
+                &mut rand::thread_rng(),
+            ),
+            penalty: Penalty::None,
+            comment: None,
+            splits: Vec::new(),
+            solution: None,
         }
     }
 
@@ -286,31 +787,6 @@ mod tests {
         assert!(result.is_ok());
     }
 
-    #[tokio::test]
-    async fn test_update_by_id_and_account_id() {
-        let mut mock_repo = MockSessionRepo::new();
-        let account_id = Uuid::new();
-        let session_id = Uuid::new();
-        let session = create_mock_session(account_id);
-
-        let update_result = MockUpdateResult {
-            matched_count: 1,
-            modified_count: 1,
-        };
-
-        mock_repo
-            .expect_update_by_id_and_account_id()
-            .with(eq(account_id), eq(session_id), eq(session.clone()))
-            .returning(move |_, _, _| Ok(update_result.clone()));
-
-        let result = mock_repo
-            .update_by_id_and_account_id(account_id, session_id, session)
-            .await
-            .unwrap();
-        assert_eq!(result.matched_count, 1);
-        assert_eq!(result.modified_count, 1);
-    }
-
     #[tokio::test]
     async fn test_insert_time() {
         let mut mock_repo = MockSessionRepo::new();
@@ -336,6 +812,270 @@ mod tests {
         assert_eq!(result.modified_count, 1);
     }
 
+    #[test]
+    fn test_session_update_tells_null_from_missing() {
+        let update: SessionUpdate =
+            serde_json::from_str(r#"{ "description": null, "kind": "Two" }"#).unwrap();
+
+        assert_eq!(update.name, None);
+        assert_eq!(update.description, Some(None));
+        assert_eq!(update.kind, Some(Some(ScrambleKind::Two)));
+    }
+
+    #[test]
+    fn test_metadata_update() {
+        let (account_id, id) = (Uuid::new(), Uuid::new());
+
+        let update = SessionUpdate {
+            name: Some("OH".to_owned()),
+            kind: Some(Some(ScrambleKind::Three)),
+            ..Default::default()
+        };
+        let (filter, modifications) = metadata_update(account_id, id, &update).unwrap();
+        assert_eq!(
+            filter,
+            doc! {
+                "_id": id,
+                "account_id": account_id,
+                "times": { "$not": { "$elemMatch": { "scramble.kind": { "$ne": "Three" } } } },
+            }
+        );
+        assert_eq!(
+            modifications,
+            doc! { "$set": { "name": "OH", "kind": "Three" } }
+        );
+
+        let update = SessionUpdate {
+            description: Some(None),
+            kind: Some(None),
+            ..Default::default()
+        };
+        let (filter, modifications) = metadata_update(account_id, id, &update).unwrap();
+        assert_eq!(filter, doc! { "_id": id, "account_id": account_id });
+        assert_eq!(
+            modifications,
+            doc! { "$unset": { "description": "", "kind": "" } }
+        );
+    }
+
+    #[test]
+    fn test_accepts() {
+        let three = create_mock_time();
+        let mut two = create_mock_time();
+        two.scramble
+            .kind = ScrambleKind::Two;
+
+        assert_eq!(
+            accepts(&[three.clone(), three.clone()]).unwrap(),
+            doc! { "kind": { "$in": [Bson::Null, "Three"] } }
+        );
+        assert_eq!(accepts(&[three, two]).unwrap(), doc! { "kind": Bson::Null });
+        assert_eq!(accepts(&[]).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_times_insertion() {
+        let (account_id, id) = (Uuid::new(), Uuid::new());
+        let times = vec![create_mock_time(), create_mock_time()];
+
+        let (filter, modifications) = times_insertion(account_id, id, &times).unwrap();
+        assert_eq!(
+            filter,
+            doc! {
+                "kind": { "$in": [Bson::Null, "Three"] },
+                "_id": id,
+                "account_id": account_id,
+            }
+        );
+        assert_eq!(
+            modifications,
+            doc! { "$push": { "times": { "$each": bson::to_bson(&times).unwrap() } } }
+        );
+    }
+
+    #[test]
+    fn test_time_cursor_round_trip() {
+        let cursor: TimeCursor = "1629209981-42"
+            .parse()
+            .unwrap();
+        assert_eq!(cursor.to_string(), "1629209981-42");
+
+        assert!("1629209981"
+            .parse::<TimeCursor>()
+            .is_err());
+        assert!("a-1"
+            .parse::<TimeCursor>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_times_pipeline_filters() {
+        let (account_id, id) = (Uuid::new(), Uuid::new());
+        let query = TimesQuery {
+            limit: 50,
+            from: Some(100),
+            max_millis: Some(20000),
+            penalty: Some(Penalty::None),
+            kind: Some(ScrambleKind::Three),
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+        let cursor = TimeCursor {
+            recorded_at: 500,
+            index: 7,
+        };
+
+        let pipeline = times_pipeline(account_id, id, &query, Some(cursor)).unwrap();
+        assert_eq!(
+            pipeline[2],
+            doc! { "$match": {
+                "times.recorded_at": { "$gte": 100_i64 },
+                "times.millis": { "$lte": 20000_i64 },
+                "times.penalty": { "$in": ["None", Bson::Null] },
+                "times.scramble.kind": "Three",
+                "$or": [
+                    { "times.recorded_at": { "$lt": 500_i64 } },
+                    { "times.recorded_at": 500_i64, "index": { "$lt": 7_i64 } },
+                ],
+            } }
+        );
+        assert_eq!(
+            pipeline[3],
+            doc! { "$sort": { "times.recorded_at": -1, "index": -1 } }
+        );
+        assert_eq!(pipeline[4], doc! { "$limit": 51_i64 });
+    }
+
+    #[test]
+    fn test_solution_update() {
+        let (account_id, id, time_id) = (Uuid::new(), Uuid::new(), Uuid::new());
+        let solution = Solution {
+            sequence: "U R U' R'".to_owned(),
+            timestamps: vec![0, 100, 200, 300],
+        };
+
+        let (filter, modifications) =
+            solution_update(account_id, id, time_id, Some(&solution)).unwrap();
+        assert_eq!(
+            filter,
+            doc! { "_id": id, "account_id": account_id, "times.id": time_id }
+        );
+        assert_eq!(
+            modifications,
+            doc! { "$set": { "times.$.solution": {
+                "sequence": "U R U' R'",
+                "timestamps": [0_i64, 100_i64, 200_i64, 300_i64],
+            } } }
+        );
+
+        let (_, modifications) = solution_update(account_id, id, time_id, None).unwrap();
+        assert_eq!(modifications, doc! { "$unset": { "times.$.solution": "" } });
+    }
+
+    #[test]
+    fn test_time_ref_from_str() {
+        let id = Uuid::new();
+
+        assert_eq!("12".parse(), Ok(TimeRef::Index(12)));
+        assert_eq!(
+            id.to_string()
+                .parse(),
+            Ok(TimeRef::Id(id))
+        );
+        assert!("-1"
+            .parse::<TimeRef>()
+            .is_err());
+        assert!("latest"
+            .parse::<TimeRef>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_time_ref_locate() {
+        let (filter, path) = TimeRef::Index(3).locate();
+        assert_eq!(filter, doc! { "times.3": { "$exists": true } });
+        assert_eq!(path, "times.3");
+
+        let id = Uuid::new();
+        let (filter, path) = TimeRef::Id(id).locate();
+        assert_eq!(filter, doc! { "times.id": id });
+        assert_eq!(path, "times.$");
+    }
+
+    #[test]
+    fn test_time_update() {
+        let (account_id, id, time_id) = (Uuid::new(), Uuid::new(), Uuid::new());
+
+        let update = TimeUpdate {
+            penalty: Some(Penalty::Dnf),
+            ..Default::default()
+        };
+        let (filter, modifications) =
+            time_update(account_id, id, TimeRef::Id(time_id), &update).unwrap();
+        assert_eq!(
+            filter,
+            doc! { "times.id": time_id, "_id": id, "account_id": account_id }
+        );
+        assert_eq!(modifications, doc! { "$set": { "times.$.penalty": "Dnf" } });
+
+        let update = TimeUpdate {
+            millis: Some(12000),
+            ..Default::default()
+        };
+        let (filter, modifications) =
+            time_update(account_id, id, TimeRef::Index(2), &update).unwrap();
+        assert_eq!(
+            filter,
+            doc! { "times.2": { "$exists": true }, "_id": id, "account_id": account_id }
+        );
+        assert_eq!(
+            modifications,
+            doc! {
+                "$set": { "times.2.millis": 12000_i64 },
+                "$unset": { "times.2.splits": "" },
+            }
+        );
+    }
+
+    #[test]
+    fn test_time_ref_find_in() {
+        let times = vec![create_mock_time(), create_mock_time()];
+
+        assert_eq!(TimeRef::Index(1).find_in(&times), Some(&times[1]));
+        assert_eq!(TimeRef::Index(2).find_in(&times), None);
+        assert_eq!(TimeRef::Id(times[1].id).find_in(&times), Some(&times[1]));
+        assert_eq!(TimeRef::Id(Uuid::new()).find_in(&times), None);
+    }
+
+    #[test]
+    fn test_time_without_penalty_or_id_deserializes() {
+        let time: Time = bson::from_document(doc! {
+            "millis": 12345_i64,
+            "recorded_at": 1629209981_i64,
+            "scramble": { "kind": "Three", "sequence": "R U R' U'" },
+        })
+        .unwrap();
+
+        assert_eq!(time.penalty, Penalty::None);
+        assert_eq!(time.result(), Some(12345));
+        assert_eq!(
+            Time {
+                penalty: Penalty::PlusTwo,
+                ..time.clone()
+            }
+            .result(),
+            Some(14345)
+        );
+        assert_eq!(
+            Time {
+                penalty: Penalty::Dnf,
+                ..time
+            }
+            .result(),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_delete_by_id_and_account_id() {
         let mut mock_repo = MockSessionRepo::new();
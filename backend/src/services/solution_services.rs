@@ -0,0 +1,275 @@
+use serde::Serialize;
+
+use crate::{
+    models::session::{Solution, MAX_SOLUTION_MOVES},
+    routes::scrambles::Scramble,
+};
+
+use super::{
+    cube_state::{CubeState, Move},
+    notation::{self, cube::CubeNotation},
+};
+
+/// Length of the solution in the usual metrics. Rotations are free in all of them.
+#[derive(Clone, Copy, Debug, Default, Serialize, PartialEq)]
+pub struct MoveCounts {
+    /// Half turn metric: any turn of an outer block counts 1, a turn of inner layers only 2.
+    pub htm: usize,
+    /// Quarter turn metric: as HTM, counting half turns twice.
+    pub qtm: usize,
+    /// Slice turn metric: any turn counts 1.
+    pub stm: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SolutionAnalysis {
+    pub moves: MoveCounts,
+    /// Turns per second over the whole solve, counted in STM.
+    pub tps: Option<f64>,
+    /// Turns per second from the first move to the last one, when they have timestamps.
+    pub execution_tps: Option<f64>,
+    /// The reconstruction on alg.cubing.net.
+    pub link: String,
+}
+
+/// Parses the solution and checks that it solves the cube scrambled by `scramble`, with one
+/// timestamp per move when there are timestamps. Solutions of more than `MAX_SOLUTION_MOVES`
+/// moves are rejected before being applied.
+pub fn check(scramble: &Scramble, solution: &Solution) -> Result<Vec<Move>, String> {
+    let mut cube = CubeState::from_scramble(scramble)
+        .ok_or("solutions can only be checked for cubes")?
+        .map_err(|err| format!("invalid scramble: {}", err))?;
+    let size = cube.size();
+
+    let moves: Vec<Move> = notation::parse_moves(&CubeNotation { size }, &solution.sequence)
+        .map_err(|err| err.to_string())?
+        .iter()
+        .map(|mv| mv.layers(size))
+        .collect();
+    if moves.len() > MAX_SOLUTION_MOVES {
+        return Err(format!(
+            "solutions can't have more than {} moves",
+            MAX_SOLUTION_MOVES
+        ));
+    }
+
+    let timestamps = &solution.timestamps;
+    if !timestamps.is_empty() {
+        if timestamps.len() != moves.len() {
+            return Err(format!(
+                "expected a timestamp for each of the {} moves, got {}",
+                moves.len(),
+                timestamps.len()
+            ));
+        }
+        if timestamps
+            .windows(2)
+            .any(|pair| pair[0] > pair[1])
+        {
+            return Err("timestamps must be in increasing order".to_owned());
+        }
+    }
+
+    // Usually the scramble itself, entered as the solution.
+    let mut inverse = cube.inverse();
+    cube.apply_moves(&moves);
+    if !cube.is_solved() {
+        inverse.apply_moves(&moves);
+        if inverse.is_solved() {
+            return Err("the solution solves the inverse of the scramble".to_owned());
+        }
+        return Err("the solution doesn't solve the scramble".to_owned());
+    }
+    Ok(moves)
+}
+
+/// Counts the moves as made, so `R R` counts 2 even though it is `R2`.
+pub fn count_moves(moves: &[Move], size: usize) -> MoveCounts {
+    let mut counts = MoveCounts::default();
+    for mv in moves {
+        let quarters = match mv.turns {
+            0 => continue,
+            2 => 2,
+            _ => 1,
+        };
+        // A turn of every layer is a rotation, one of inner layers moves two outer blocks.
+        let blocks = match (mv.from == 1, mv.to == size) {
+            (true, true) => continue,
+            (true, false) | (false, true) => 1,
+            (false, false) => 2,
+        };
+        counts.htm += blocks;
+        counts.qtm += blocks * quarters;
+        counts.stm += 1;
+    }
+    counts
+}
+
+/// Turns per second, to two decimals.
+fn tps(turns: usize, millis: u64) -> Option<f64> {
+    (millis > 0).then(|| (turns as f64 * 100_000.0 / millis as f64).round() / 100.0)
+}
+
+/// Writes a sequence the way alg.cubing.net does in its URLs: spaces as `_`, primes as `-`
+/// and the rest percent-encoded.
+fn encode(sequence: &str) -> String {
+    let mut encoded = String::new();
+    for c in sequence.chars() {
+        match c {
+            ' ' => encoded.push('_'),
+            '\'' => encoded.push('-'),
+            c if c.is_ascii_alphanumeric() || "()[],:.".contains(c) => encoded.push(c),
+            c => {
+                let mut bytes = [0; 4];
+                for byte in c
+                    .encode_utf8(&mut bytes)
+                    .bytes()
+                {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    encoded
+}
+
+/// Link to the reconstruction of a solve on alg.cubing.net, the scramble as setup.
+pub fn reconstruction_link(scramble: &Scramble, solution: &str) -> String {
+    let size = scramble
+        .kind
+        .cube_size()
+        .unwrap_or(3);
+    format!(
+        "https://alg.cubing.net/?puzzle={n}x{n}x{n}&setup={}&alg={}&type=reconstruction",
+        encode(&scramble.sequence),
+        encode(solution),
+        n = size
+    )
+}
+
+/// Checks the solution of a solve of `millis` and computes its move counts and speed.
+pub fn analyze(
+    scramble: &Scramble,
+    solution: &Solution,
+    millis: u64,
+) -> Result<SolutionAnalysis, String> {
+    let moves = check(scramble, solution)?;
+    let size = scramble
+        .kind
+        .cube_size()
+        .unwrap_or(3);
+    let counts = count_moves(&moves, size);
+
+    let execution_tps = match (
+        solution
+            .timestamps
+            .first(),
+        solution
+            .timestamps
+            .last(),
+    ) {
+        (Some(first), Some(last)) => tps(counts.stm, last - first),
+        _ => None,
+    };
+
+    Ok(SolutionAnalysis {
+        moves: counts,
+        tps: tps(counts.stm, millis),
+        execution_tps,
+        link: reconstruction_link(scramble, &solution.sequence),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::scrambles::ScrambleKind;
+
+    fn scramble(kind: ScrambleKind, sequence: &str) -> Scramble {
+        Scramble {
+            kind,
+            sequence: sequence.to_owned(),
+            case: None,
+        }
+    }
+
+    fn solution(sequence: &str, timestamps: &[u64]) -> Solution {
+        Solution {
+            sequence: sequence.to_owned(),
+            timestamps: timestamps.to_vec(),
+        }
+    }
+
+    fn counts(size: usize, sequence: &str) -> MoveCounts {
+        let moves: Vec<Move> = notation::parse_moves(&CubeNotation { size }, sequence)
+            .unwrap()
+            .iter()
+            .map(|mv| mv.layers(size))
+            .collect();
+        count_moves(&moves, size)
+    }
+
+    #[test]
+    fn test_count_moves() {
+        let metrics = |htm, qtm, stm| MoveCounts { htm, qtm, stm };
+
+        assert_eq!(counts(3, "R U2 R' x y2"), metrics(3, 4, 3));
+        assert_eq!(counts(3, "M2 U M' E S r"), metrics(10, 12, 6));
+        assert_eq!(counts(3, "R4 3R x2"), metrics(1, 1, 1));
+        assert_eq!(counts(5, "2R 3Rw 3R' Rw2"), metrics(6, 7, 4));
+    }
+
+    #[test]
+    fn test_check() {
+        let sexy = scramble(ScrambleKind::Three, "R U R' U'");
+
+        assert!(check(&sexy, &solution("U R U' R'", &[])).is_ok());
+        // The cube may end up in any orientation.
+        assert!(check(&sexy, &solution("U R U' R' x y", &[])).is_ok());
+        assert!(check(&sexy, &solution("[U, R]", &[0, 100, 200, 300])).is_ok());
+
+        assert!(check(&sexy, &solution("U R U'", &[])).is_err());
+        assert_eq!(
+            check(&sexy, &solution("R U R' U'", &[])),
+            Err("the solution solves the inverse of the scramble".to_owned())
+        );
+        assert!(check(&sexy, &solution("U R U' Q", &[])).is_err());
+        assert!(check(&sexy, &solution("U R U' R'", &[0, 100, 200])).is_err());
+        assert!(check(&sexy, &solution("U R U' R'", &[0, 300, 200, 400])).is_err());
+        assert!(check(&sexy, &solution("U R U' R' (R R')498", &[])).is_ok());
+        assert!(check(&sexy, &solution("U R U' R' (R R')500", &[])).is_err());
+        assert!(check(
+            &scramble(ScrambleKind::Pyraminx, "R U"),
+            &solution("U' R'", &[])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_analyze() {
+        let analysis = analyze(
+            &scramble(ScrambleKind::Three, "R U R' U'"),
+            &solution("U R U' R'", &[600, 800, 1000, 1400]),
+            2000,
+        )
+        .unwrap();
+
+        assert_eq!(
+            analysis
+                .moves
+                .stm,
+            4
+        );
+        assert_eq!(analysis.tps, Some(2.0));
+        assert_eq!(analysis.execution_tps, Some(5.0));
+        assert_eq!(
+            analysis.link,
+            "https://alg.cubing.net/?puzzle=3x3x3&setup=R_U_R-_U-&alg=U_R_U-_R-&type=reconstruction"
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("[R, U'] // OLL\nR2"), "[R,_U-]_%2F%2F_OLL%0AR2");
+    }
+}
@@ -0,0 +1,430 @@
+use serde::{Serialize, Serializer};
+
+use crate::models::session::{Penalty, Time};
+
+/// Sizes of the rolling averages computed for every session.
+pub const AVERAGE_SIZES: [usize; 5] = [5, 12, 50, 100, 1000];
+
+/// A single or an average, serialized as milliseconds or as `"DNF"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SolveResult {
+    Millis(u64),
+    Dnf,
+}
+
+impl Serialize for SolveResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SolveResult::Millis(millis) => serializer.serialize_u64(*millis),
+            SolveResult::Dnf => serializer.serialize_str("DNF"),
+        }
+    }
+}
+
+impl From<&Time> for SolveResult {
+    fn from(time: &Time) -> SolveResult {
+        time.result()
+            .map_or(SolveResult::Dnf, SolveResult::Millis)
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AverageStats {
+    pub size: usize,
+    /// Average of the last `size` times, `None` until there are enough times.
+    pub current: Option<SolveResult>,
+    pub best: Option<SolveResult>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SessionStats {
+    pub count: usize,
+    pub dnf_count: usize,
+    pub best: Option<SolveResult>,
+    /// Mean and standard deviation of the times which aren't DNFs.
+    pub mean: Option<u64>,
+    pub std_dev: Option<u64>,
+    pub averages: Vec<AverageStats>,
+}
+
+/// Statistics of a phase, over the times which have a split of its name.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PhaseStats {
+    pub name: String,
+    pub count: usize,
+    pub best: u64,
+    pub mean: u64,
+    pub averages: Vec<AverageStats>,
+}
+
+/// Number of times dropped at each end of an average of `size`, 5% rounded up as the WCA does.
+fn trimmed(size: usize) -> usize {
+    size.div_ceil(20)
+}
+
+/// Average of a window sorted from best to worst, where DNFs sort last. The average is a
+/// DNF when there are more DNFs than trimmed times.
+fn sorted_average(sorted: &[SolveResult]) -> SolveResult {
+    let trim = trimmed(sorted.len());
+    mean(&sorted[trim..sorted.len() - trim])
+}
+
+/// Mean of at least one result, a DNF when any of them is.
+pub fn mean(results: &[SolveResult]) -> SolveResult {
+    let mut sum = 0;
+    for result in results {
+        match result {
+            SolveResult::Millis(millis) => sum += millis,
+            SolveResult::Dnf => return SolveResult::Dnf,
+        }
+    }
+    SolveResult::Millis((sum as f64 / results.len() as f64).round() as u64)
+}
+
+/// Average of the results with the best and worst 5% left out, as for rolling averages.
+pub fn average(results: &[SolveResult]) -> SolveResult {
+    let mut sorted = results.to_vec();
+    sorted.sort();
+    sorted_average(&sorted)
+}
+
+/// Current and best average of every window of `size` consecutive results.
+fn rolling_average(results: &[SolveResult], size: usize) -> AverageStats {
+    if results.len() < size {
+        return AverageStats {
+            size,
+            current: None,
+            best: None,
+        };
+    }
+
+    // The window is kept sorted while it slides, instead of sorting every window again.
+    let mut window = results[..size].to_vec();
+    window.sort();
+    let mut best = sorted_average(&window);
+    let mut current = best;
+    for (leaving, entering) in results
+        .iter()
+        .zip(&results[size..])
+    {
+        let at = window
+            .binary_search(leaving)
+            .expect("the leaving result is in the window");
+        window.remove(at);
+        let at = window
+            .binary_search(entering)
+            .unwrap_or_else(|at| at);
+        window.insert(at, *entering);
+
+        current = sorted_average(&window);
+        best = best.min(current);
+    }
+
+    AverageStats {
+        size,
+        current: Some(current),
+        best: Some(best),
+    }
+}
+
+/// Statistics of the times of a session, in the order they were recorded.
+pub fn session_stats(times: &[Time]) -> SessionStats {
+    let results: Vec<SolveResult> = times
+        .iter()
+        .map(SolveResult::from)
+        .collect();
+    let solved: Vec<f64> = times
+        .iter()
+        .filter_map(Time::result)
+        .map(|millis| millis as f64)
+        .collect();
+
+    let mean = (!solved.is_empty()).then(|| {
+        solved
+            .iter()
+            .sum::<f64>()
+            / solved.len() as f64
+    });
+    let std_dev = mean.map(|mean| {
+        (solved
+            .iter()
+            .map(|millis| (millis - mean).powi(2))
+            .sum::<f64>()
+            / solved.len() as f64)
+            .sqrt()
+    });
+
+    SessionStats {
+        count: times.len(),
+        dnf_count: times.len() - solved.len(),
+        best: results
+            .iter()
+            .min()
+            .copied(),
+        mean: mean.map(|mean| mean.round() as u64),
+        std_dev: std_dev.map(|std_dev| std_dev.round() as u64),
+        averages: AVERAGE_SIZES
+            .iter()
+            .map(|&size| rolling_average(&results, size))
+            .collect(),
+    }
+}
+
+/// Statistics of every phase of the times of a session, in the order the phases first appear.
+/// A phase lasts from the previous split of its time, and DNFs are left out as their last
+/// phase wasn't finished. Penalties don't count, since they aren't the time of a phase.
+pub fn phase_stats(times: &[Time]) -> Vec<PhaseStats> {
+    let mut phases: Vec<(&str, Vec<u64>)> = Vec::new();
+    for time in times
+        .iter()
+        .filter(|time| time.penalty != Penalty::Dnf)
+    {
+        let mut start = 0;
+        for split in &time.splits {
+            let durations = match phases
+                .iter()
+                .position(|(name, _)| *name == split.name)
+            {
+                Some(at) => &mut phases[at].1,
+                None => {
+                    phases.push((&split.name, Vec::new()));
+                    &mut phases
+                        .last_mut()
+                        .expect("just pushed")
+                        .1
+                }
+            };
+            durations.push(split.millis - start);
+            start = split.millis;
+        }
+    }
+
+    phases
+        .into_iter()
+        .map(|(name, durations)| {
+            let results: Vec<SolveResult> = durations
+                .iter()
+                .map(|&millis| SolveResult::Millis(millis))
+                .collect();
+            PhaseStats {
+                name: name.to_owned(),
+                count: durations.len(),
+                best: durations
+                    .iter()
+                    .min()
+                    .copied()
+                    .expect("a phase has at least one duration"),
+                mean: (durations
+                    .iter()
+                    .sum::<u64>() as f64
+                    / durations.len() as f64)
+                    .round() as u64,
+                averages: AVERAGE_SIZES
+                    .iter()
+                    .map(|&size| rolling_average(&results, size))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Uuid;
+
+    use super::*;
+    use crate::{
+        models::session::Split,
+        routes::scrambles::{Scramble, ScrambleKind},
+    };
+
+    fn times(results: &[Option<u64>]) -> Vec<Time> {
+        results
+            .iter()
+            .map(|result| Time {
+                id: Uuid::new(),
+                millis: result.unwrap_or(30000),
+                recorded_at: 0,
+                scramble: Scramble {
+                    kind: ScrambleKind::Three,
+                    sequence: "R U".to_owned(),
+                    case: None,
+                },
+                penalty: if result.is_some() {
+                    Penalty::None
+                } else {
+                    Penalty::Dnf
+                },
+                comment: None,
+                splits: Vec::new(),
+                solution: None,
+            })
+            .collect()
+    }
+
+    fn average_of(results: &[Option<u64>], size: usize) -> AverageStats {
+        session_stats(&times(results))
+            .averages
+            .into_iter()
+            .find(|average| average.size == size)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_trimmed() {
+        assert_eq!(trimmed(5), 1);
+        assert_eq!(trimmed(12), 1);
+        assert_eq!(trimmed(50), 3);
+        assert_eq!(trimmed(100), 5);
+        assert_eq!(trimmed(1000), 50);
+    }
+
+    #[test]
+    fn test_single_mean_and_std_dev() {
+        let stats = session_stats(&times(&[Some(10000), None, Some(12000), Some(14000)]));
+
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.dnf_count, 1);
+        assert_eq!(stats.best, Some(SolveResult::Millis(10000)));
+        assert_eq!(stats.mean, Some(12000));
+        assert_eq!(stats.std_dev, Some(1633));
+    }
+
+    #[test]
+    fn test_empty_and_all_dnf_sessions() {
+        let stats = session_stats(&[]);
+        assert_eq!(stats.best, None);
+        assert_eq!(stats.mean, None);
+        assert!(stats
+            .averages
+            .iter()
+            .all(|average| average == &rolling_average(&[], average.size)));
+
+        let stats = session_stats(&times(&[None, None]));
+        assert_eq!(stats.best, Some(SolveResult::Dnf));
+        assert_eq!(stats.mean, None);
+    }
+
+    #[test]
+    fn test_average_of_5() {
+        let results = [
+            Some(9000),
+            Some(12000),
+            Some(11000),
+            Some(10000),
+            Some(20000),
+        ];
+        let average = average_of(&results, 5);
+        assert_eq!(average.current, Some(SolveResult::Millis(11000)));
+        assert_eq!(average.best, Some(SolveResult::Millis(11000)));
+
+        // A single DNF is dropped as the worst time, two make the average a DNF.
+        let results = [Some(9000), None, Some(11000), Some(10000), Some(12000)];
+        assert_eq!(
+            average_of(&results, 5).current,
+            Some(SolveResult::Millis(11000))
+        );
+        let results = [Some(9000), None, Some(11000), None, Some(12000)];
+        assert_eq!(average_of(&results, 5).current, Some(SolveResult::Dnf));
+    }
+
+    #[test]
+    fn test_current_and_best_rolling_averages() {
+        let results = [
+            Some(10000),
+            Some(10000),
+            Some(10000),
+            Some(10000),
+            Some(10000),
+            None,
+            None,
+        ];
+        let average = average_of(&results, 5);
+        assert_eq!(average.current, Some(SolveResult::Dnf));
+        assert_eq!(average.best, Some(SolveResult::Millis(10000)));
+        assert_eq!(average_of(&results, 12).current, None);
+    }
+
+    #[test]
+    fn test_rolling_average_matches_sorting_every_window() {
+        let results: Vec<Option<u64>> = (0..300_u64)
+            .map(|i| (i % 17 != 3).then_some(8000 + i * 7919 % 5000))
+            .collect();
+        let all = times(&results)
+            .iter()
+            .map(SolveResult::from)
+            .collect::<Vec<_>>();
+
+        for size in [5, 12, 50, 100] {
+            let expected = all
+                .windows(size)
+                .map(|window| {
+                    let mut window = window.to_vec();
+                    window.sort();
+                    sorted_average(&window)
+                })
+                .min();
+            assert_eq!(average_of(&results, size).best, expected, "{}", size);
+        }
+    }
+
+    fn with_splits(penalty: Penalty, splits: &[(&str, u64)]) -> Time {
+        let mut time = times(&[Some(0)]).remove(0);
+        time.penalty = penalty;
+        time.splits = splits
+            .iter()
+            .map(|&(name, millis)| Split {
+                name: name.to_owned(),
+                millis,
+            })
+            .collect();
+        time.millis = time
+            .splits
+            .last()
+            .map_or(10000, |split| split.millis);
+        time
+    }
+
+    #[test]
+    fn test_phase_stats() {
+        let times = [
+            with_splits(
+                Penalty::None,
+                &[("cross", 2000), ("f2l", 9000), ("ll", 12000)],
+            ),
+            with_splits(
+                Penalty::PlusTwo,
+                &[("cross", 1000), ("f2l", 9000), ("ll", 11000)],
+            ),
+            with_splits(Penalty::Dnf, &[("cross", 500), ("f2l", 1000), ("ll", 1500)]),
+            with_splits(Penalty::None, &[]),
+            with_splits(Penalty::None, &[("cross", 3000), ("xcross", 4000)]),
+        ];
+        let stats = phase_stats(&times);
+
+        let summary: Vec<(&str, usize, u64, u64)> = stats
+            .iter()
+            .map(|phase| {
+                (
+                    phase
+                        .name
+                        .as_str(),
+                    phase.count,
+                    phase.best,
+                    phase.mean,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("cross", 3, 1000, 2000),
+                ("f2l", 2, 7000, 7500),
+                ("ll", 2, 2000, 2500),
+                ("xcross", 1, 1000, 1000),
+            ]
+        );
+        assert_eq!(stats[0].averages[0].current, None);
+        assert!(phase_stats(&[]).is_empty());
+    }
+}
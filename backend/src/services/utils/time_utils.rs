@@ -1,6 +1,5 @@
 use chrono::{TimeZone, Utc};
 
-#[allow(unused)]
 pub trait UtcFromMillis {
     fn utc_from_millis(&self) -> chrono::DateTime<Utc>;
 }
@@ -9,7 +8,9 @@ impl UtcFromMillis for u64 {
     fn utc_from_millis(&self) -> chrono::DateTime<Utc> {
         let secs = (self / 1_000) as i64;
         let nsecs = ((self % 1_000) * 1_000_000) as u32;
+        // Clamped, as a timestamp given by a client may be past what chrono represents.
         Utc.timestamp_opt(secs, nsecs)
-            .unwrap()
+            .single()
+            .unwrap_or(chrono::DateTime::<Utc>::MAX_UTC)
     }
 }
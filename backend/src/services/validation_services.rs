@@ -1,4 +1,4 @@
-use std::ops::RangeInclusive;
+use std::{borrow::Cow, ops::RangeInclusive};
 
 use axum::{
     extract::{FromRequest, FromRequestParts, Path, Query, Request},
@@ -6,9 +6,18 @@ use axum::{
     Json,
 };
 use serde::de::DeserializeOwned;
-use validator::{Validate, ValidationError};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{
+    error::AppError,
+    models::{
+        event::{Advancement, CreateRoundDto, RegistrationWindow},
+        session::Time,
+    },
+    routes::scrambles::Scramble,
+};
 
-use crate::error::AppError;
+use super::{alg_services::Operation, notation, solution_services};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ValidatedJson<T>(pub T);
@@ -60,6 +69,17 @@ where
     }
 }
 
+/// Builds a validation error for a single field, for checks which need more context
+/// than the payload itself.
+pub fn field_error(field: &'static str, message: impl Into<Cow<'static, str>>) -> AppError {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        field,
+        ValidationError::new("invalid").with_message(message.into()),
+    );
+    AppError::Validation(errors)
+}
+
 pub fn ascii_string(value: &str) -> Result<(), ValidationError> {
     if !value.is_ascii() {
         return Err(ValidationError::new("invalid")
@@ -68,6 +88,138 @@ pub fn ascii_string(value: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Checks that the scramble sequence is written in the notation of its puzzle.
+pub fn valid_scramble(scramble: &Scramble) -> Result<(), ValidationError> {
+    notation::validate(scramble).map_err(|err| {
+        ValidationError::new("invalid").with_message(
+            err.to_string()
+                .into(),
+        )
+    })
+}
+
+/// Checks that the splits of a time have distinct names, never go back and end at its `millis`.
+pub fn valid_splits(time: &Time) -> Result<(), ValidationError> {
+    let invalid =
+        |message: &'static str| Err(ValidationError::new("splits").with_message(message.into()));
+    let Some(last) = time
+        .splits
+        .last()
+    else {
+        return Ok(());
+    };
+
+    for (i, split) in time
+        .splits
+        .iter()
+        .enumerate()
+    {
+        let before = &time.splits[..i];
+        if before
+            .iter()
+            .any(|other| other.name == split.name)
+        {
+            return invalid("split names must be distinct");
+        }
+        if before
+            .last()
+            .is_some_and(|previous| previous.millis > split.millis)
+        {
+            return invalid("splits must be in increasing order of millis");
+        }
+    }
+    if last.millis != time.millis {
+        return invalid("the last split must end at millis");
+    }
+    Ok(())
+}
+
+/// Checks that the solution of a time, if any, solves its scramble.
+pub fn valid_solution(time: &Time) -> Result<(), ValidationError> {
+    let Some(solution) = &time.solution else {
+        return Ok(());
+    };
+    solution_services::check(&time.scramble, solution)
+        .map(drop)
+        .map_err(|err| ValidationError::new("solution").with_message(err.into()))
+}
+
+/// Checks that registration to an event doesn't close before it opens.
+pub fn valid_registration_window(window: &RegistrationWindow) -> Result<(), ValidationError> {
+    match window.registration_opens {
+        Some(opens) if opens < window.registration_closes => Err(ValidationError::new("invalid")
+            .with_message("registration must open before it closes".into())),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a cutoff leaves attempts to make after it, as only averages can have one.
+pub fn valid_round(round: &CreateRoundDto) -> Result<(), ValidationError> {
+    match round.cutoff {
+        Some(cutoff)
+            if !round
+                .format
+                .is_average()
+                || cutoff.attempts
+                    >= round
+                        .format
+                        .attempt_count() =>
+        {
+            Err(ValidationError::new("cutoff").with_message(
+                "a cutoff must be on fewer attempts than a mean or an average has".into(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a round lets at most 75% of its competitors advance, as the WCA requires.
+pub fn valid_advancement(advancement: &Advancement) -> Result<(), ValidationError> {
+    match advancement {
+        Advancement::Top(0) | Advancement::Percent(0) => Err(ValidationError::new("range")
+            .with_message("at least one competitor must advance".into())),
+        Advancement::Percent(percent) if *percent > 75 => Err(ValidationError::new("range")
+            .with_message("at most 75% of the competitors may advance".into())),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the rotation of every `rotate` operation is at most 64 characters long.
+pub fn valid_operations(operations: &[Operation]) -> Result<(), ValidationError> {
+    let too_long = operations
+        .iter()
+        .any(|operation| {
+            matches!(operation, Operation::Rotate { rotation } if rotation.chars().count() > 64)
+        });
+    if too_long {
+        return Err(ValidationError::new("length")
+            .with_message("every rotation must be at most 64 characters long".into()));
+    }
+    Ok(())
+}
+
+/// Checks that every tag is 1 to 32 characters long and that no tag is repeated.
+pub fn valid_tags(tags: &[String]) -> Result<(), ValidationError> {
+    for (i, tag) in tags
+        .iter()
+        .enumerate()
+    {
+        if !(1..=32).contains(
+            &tag.chars()
+                .count(),
+        ) {
+            return Err(ValidationError::new("length")
+                .with_message("every tag must be 1 to 32 characters long".into()));
+        }
+        if tags[..i].contains(tag) {
+            return Err(
+                ValidationError::new("invalid").with_message("tags must be distinct".into())
+            );
+        }
+    }
+    Ok(())
+}
+
 enum PasswordRules {
     Length(RangeInclusive<usize>),
     CapitalLetter,
@@ -135,6 +287,7 @@ mod tests {
     use serde::{Deserialize, Serialize};
 
     use super::*;
+    use crate::routes::scrambles::ScrambleKind;
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct TestPayload {
@@ -263,4 +416,154 @@ mod tests {
         let result = strong_password(invalid_password);
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_valid_scramble() {
+        let scramble = Scramble {
+            kind: ScrambleKind::Three,
+            sequence: "R U R' [F: (R U)2]".to_string(),
+            case: None,
+        };
+        let result = valid_scramble(&scramble);
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_valid_scramble_invalid() {
+        let scramble = Scramble {
+            kind: ScrambleKind::Two,
+            sequence: "R U M".to_string(),
+            case: None,
+        };
+        let error = valid_scramble(&scramble).unwrap_err();
+        assert_eq!(
+            error
+                .message
+                .unwrap(),
+            "invalid move `M` at offset 4"
+        );
+    }
+
+    #[test]
+    fn test_valid_splits() {
+        let time = |millis, splits: &[(&str, u64)]| Time {
+            id: mongodb::bson::Uuid::new(),
+            millis,
+            recorded_at: 0,
+            scramble: Scramble {
+                kind: ScrambleKind::Three,
+                sequence: "R U".to_owned(),
+                case: None,
+            },
+            penalty: Default::default(),
+            comment: None,
+            splits: splits
+                .iter()
+                .map(|&(name, millis)| crate::models::session::Split {
+                    name: name.to_owned(),
+                    millis,
+                })
+                .collect(),
+            solution: None,
+        };
+
+        assert!(valid_splits(&time(9000, &[])).is_ok());
+        assert!(valid_splits(&time(
+            9000,
+            &[("cross", 2000), ("pll", 2000), ("oll", 9000)]
+        ))
+        .is_ok());
+        assert!(
+            valid_splits(&time(9000, &[("cross", 2000), ("f2l", 1000), ("ll", 9000)])).is_err()
+        );
+        assert!(valid_splits(&time(9000, &[("cross", 2000), ("f2l", 8000)])).is_err());
+        assert!(valid_splits(&time(9000, &[("f2l", 2000), ("f2l", 9000)])).is_err());
+        assert!(time(9000, &[("", 9000)])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_valid_solution() {
+        let time = |solution: &str| Time {
+            id: mongodb::bson::Uuid::new(),
+            millis: 2000,
+            recorded_at: 0,
+            scramble: Scramble {
+                kind: ScrambleKind::Three,
+                sequence: "R U".to_owned(),
+                case: None,
+            },
+            penalty: Default::default(),
+            comment: None,
+            splits: Vec::new(),
+            solution: Some(crate::models::session::Solution {
+                sequence: solution.to_owned(),
+                timestamps: Vec::new(),
+            }),
+        };
+
+        assert!(time("U' R'")
+            .validate()
+            .is_ok());
+        assert!(time("R' U'")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_valid_tags() {
+        let tags = |tags: &[&str]| {
+            tags.iter()
+                .map(|tag| tag.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(valid_tags(&tags(&[])).is_ok());
+        assert!(valid_tags(&tags(&["oh", "feet"])).is_ok());
+        assert!(valid_tags(&tags(&["oh", ""])).is_err());
+        assert!(valid_tags(&tags(&["oh", "oh"])).is_err());
+        assert!(valid_tags(&tags(&[&"a".repeat(33)])).is_err());
+    }
+
+    #[test]
+    fn test_valid_operations() {
+        let rotate = |rotation: String| Operation::Rotate { rotation };
+
+        assert!(valid_operations(&[Operation::Invert, rotate("x y2".to_owned())]).is_ok());
+        assert!(valid_operations(&[rotate("x ".repeat(32))]).is_ok());
+        assert!(valid_operations(&[Operation::Invert, rotate("x ".repeat(33))]).is_err());
+    }
+
+    #[test]
+    fn test_valid_round() {
+        let round = |json: &str| {
+            serde_json::from_str::<CreateRoundDto>(json)
+                .unwrap()
+                .validate()
+        };
+
+        assert!(round(r#"{"kind": "Three", "format": "ao5", "advancement": {"top": 16}}"#).is_ok());
+        assert!(round(
+            r#"{"kind": "Three", "format": "mo3", "cutoff": {"attempts": 1, "millis": 60000},
+                "time_limit": {"millis": 120000, "cumulative": true}}"#
+        )
+        .is_ok());
+        assert!(round(
+            r#"{"kind": "Three", "format": "mo3", "cutoff": {"attempts": 2, "millis": 60000}}"#
+        )
+        .is_ok());
+        assert!(round(
+            r#"{"kind": "Three", "format": "mo3", "cutoff": {"attempts": 3, "millis": 60000}}"#
+        )
+        .is_err());
+        assert!(round(
+            r#"{"kind": "Three", "format": "bo3", "cutoff": {"attempts": 1, "millis": 60000}}"#
+        )
+        .is_err());
+        assert!(
+            round(r#"{"kind": "Three", "format": "ao5", "advancement": {"percent": 80}}"#).is_err()
+        );
+        assert!(round(r#"{"kind": "Three", "format": "ao5", "advancement": {"top": 0}}"#).is_err());
+    }
 }